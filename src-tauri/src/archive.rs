@@ -0,0 +1,692 @@
+// 备份文件格式：ZIP之外还支持单文件SQL流（.sql.gz、.sql.zst）和tar.zst，
+// 备份引擎始终先写出ZIP，其他格式由暂存的ZIP转换而来
+use crate::crypto::{self, ArchiveWriter, DecryptionKey, EncryptionOptions};
+use crate::manifest::{self, BackupManifest, HashingWriter, ManifestEntry, MANIFEST_ENTRY};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use zip::write::FileOptions;
+use zip::ZipArchive;
+
+// 单文件SQL格式中整个SQL流对应的条目名，清单和校验报告中使用
+pub const SQL_STREAM_ENTRY: &str = "backup.sql";
+
+// 单文件SQL格式的清单保存在备份文件旁边，文件名为备份文件名加上此后缀
+const SIDECAR_MANIFEST_SUFFIX: &str = ".manifest.json";
+
+// 备份文件格式
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveFormat {
+    #[default]
+    Zip,
+    SqlGz,
+    SqlZst,
+    TarZst,
+}
+
+impl ArchiveFormat {
+    // 备份文件的扩展名
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => ".zip",
+            ArchiveFormat::SqlGz => ".sql.gz",
+            ArchiveFormat::SqlZst => ".sql.zst",
+            ArchiveFormat::TarZst => ".tar.zst",
+        }
+    }
+
+    // 是否为单文件SQL流
+    pub fn is_sql_stream(self) -> bool {
+        matches!(self, ArchiveFormat::SqlGz | ArchiveFormat::SqlZst)
+    }
+}
+
+// ZIP条目的压缩方式
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Codec {
+    #[default]
+    Deflate,
+    Zstd,
+    Bzip2,
+    Store,
+}
+
+// 根据压缩方式和级别生成ZIP条目选项，级别为None时使用各算法的默认级别。
+// 级别范围：deflate和bzip2为0-9，zstd为-7到22
+pub fn zip_file_options(codec: Codec, level: Option<i32>) -> FileOptions {
+    let (method, level) = match codec {
+        Codec::Deflate => (zip::CompressionMethod::Deflated, level),
+        Codec::Zstd => (zip::CompressionMethod::Zstd, level),
+        Codec::Bzip2 => (zip::CompressionMethod::Bzip2, level),
+        // 不压缩时不能指定级别
+        Codec::Store => (zip::CompressionMethod::Stored, None),
+    };
+
+    // 表数据可能超过4GB，需要启用ZIP64
+    FileOptions::default()
+        .compression_method(method)
+        .compression_level(level)
+        .unix_permissions(0o755)
+        .large_file(true)
+}
+
+// 检查非ZIP格式的压缩级别，ZIP的级别由zip库在写入条目时检查
+pub fn validate_level(format: ArchiveFormat, level: Option<i32>) -> Result<(), String> {
+    let level = match level {
+        Some(level) => level,
+        None => return Ok(()),
+    };
+
+    let valid = match format {
+        ArchiveFormat::Zip => true,
+        ArchiveFormat::SqlGz => (0..=9).contains(&level),
+        ArchiveFormat::SqlZst | ArchiveFormat::TarZst => {
+            zstd::compression_level_range().contains(&level)
+        }
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "压缩级别 {} 不适用于 {} 格式",
+            level,
+            format.extension()
+        ))
+    }
+}
+
+// 是否为本工具生成的备份文件名，包括加密的备份
+pub fn is_backup_file_name(file_name: &str) -> bool {
+    file_name.starts_with("BACKUP_") && is_archive_file_name(file_name)
+}
+
+// 扩展名是否为支持的备份格式，包括加密的备份
+pub fn is_archive_file_name(file_name: &str) -> bool {
+    let file_name = crypto::strip_encrypted_suffix(file_name);
+    [
+        ArchiveFormat::Zip,
+        ArchiveFormat::SqlGz,
+        ArchiveFormat::SqlZst,
+        ArchiveFormat::TarZst,
+    ]
+    .iter()
+    .any(|format| file_name.ends_with(format.extension()))
+}
+
+// 单文件SQL格式备份的清单路径
+pub fn sidecar_manifest_path(archive_path: &Path) -> PathBuf {
+    let mut path = archive_path.as_os_str().to_os_string();
+    path.push(SIDECAR_MANIFEST_SUFFIX);
+    PathBuf::from(path)
+}
+
+// 判断备份格式，加密的文件先解密
+pub fn detect_format(
+    archive_path: &Path,
+    key: Option<&DecryptionKey>,
+) -> Result<ArchiveFormat, String> {
+    open_archive(archive_path, key).map(|(format, _, _)| format)
+}
+
+// 把暂存的ZIP转换为目标格式，需要时同时加密。tar.zst中的条目与ZIP完全相同（包括清单）；
+// 单文件SQL格式按ZIP中的顺序拼接所有SQL条目，清单改写后保存在旁边
+pub fn convert_staging_zip(
+    staging_path: &Path,
+    output_path: &Path,
+    format: ArchiveFormat,
+    level: Option<i32>,
+    encryption: &EncryptionOptions,
+) -> Result<(), String> {
+    let staging_file = File::open(staging_path).map_err(|e| format!("打开暂存文件失败: {}", e))?;
+    let mut archive =
+        ZipArchive::new(staging_file).map_err(|e| format!("读取暂存文件失败: {}", e))?;
+    let output = ArchiveWriter::create(output_path, encryption)?;
+
+    match format {
+        ArchiveFormat::Zip => Err("ZIP格式不需要转换".to_string()),
+        ArchiveFormat::TarZst => {
+            let encoder = zstd_encoder(output, level)?;
+            let mut builder = tar::Builder::new(encoder);
+            let mtime = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+
+            for index in 0..archive.len() {
+                let mut entry = archive
+                    .by_index(index)
+                    .map_err(|e| format!("读取暂存文件条目失败: {}", e))?;
+                if entry.is_dir() {
+                    continue;
+                }
+                let name = entry.name().to_string();
+
+                let mut header = tar::Header::new_gnu();
+                header.set_size(entry.size());
+                header.set_mode(0o644);
+                header.set_mtime(mtime);
+                builder
+                    .append_data(&mut header, &name, &mut entry)
+                    .map_err(|e| format!("写入 {} 到tar失败: {}", name, e))?;
+            }
+
+            let encoder = builder
+                .into_inner()
+                .map_err(|e| format!("完成tar文件失败: {}", e))?;
+            encoder
+                .finish()
+                .map_err(|e| format!("完成zstd压缩流失败: {}", e))?
+                .finish()
+        }
+        ArchiveFormat::SqlGz => {
+            let level = level.unwrap_or(6) as u32;
+            let mut encoder =
+                flate2::write::GzEncoder::new(output, flate2::Compression::new(level));
+            let stream_entry = write_sql_stream(&mut archive, &mut encoder)?;
+            encoder
+                .finish()
+                .map_err(|e| format!("完成gzip压缩流失败: {}", e))?
+                .finish()?;
+            write_sidecar_manifest(&mut archive, output_path, stream_entry)
+        }
+        ArchiveFormat::SqlZst => {
+            let mut encoder = zstd_encoder(output, level)?;
+            let stream_entry = write_sql_stream(&mut archive, &mut encoder)?;
+            encoder
+                .finish()
+                .map_err(|e| format!("完成zstd压缩流失败: {}", e))?
+                .finish()?;
+            write_sidecar_manifest(&mut archive, output_path, stream_entry)
+        }
+    }
+}
+
+// 创建zstd压缩流，写入帧校验和以便校验时发现损坏
+fn zstd_encoder<W: Write>(
+    output: W,
+    level: Option<i32>,
+) -> Result<zstd::stream::write::Encoder<'static, W>, String> {
+    let mut encoder = zstd::stream::write::Encoder::new(output, level.unwrap_or(0))
+        .map_err(|e| format!("创建zstd压缩流失败: {}", e))?;
+    encoder
+        .include_checksum(true)
+        .map_err(|e| format!("创建zstd压缩流失败: {}", e))?;
+    Ok(encoder)
+}
+
+// 按ZIP中的顺序把所有SQL条目写入一个SQL流，返回整个流的大小和校验值
+fn write_sql_stream<W: Write>(
+    archive: &mut ZipArchive<File>,
+    writer: W,
+) -> Result<ManifestEntry, String> {
+    let mut writer = HashingWriter::new(writer);
+
+    for index in 0..archive.len() {
+        let mut entry = archive
+            .by_index(index)
+            .map_err(|e| format!("读取暂存文件条目失败: {}", e))?;
+        if entry.is_dir() || !entry.name().ends_with(".sql") {
+            continue;
+        }
+        let name = entry.name().to_string();
+
+        io::copy(&mut entry, &mut writer)
+            .and_then(|_| writer.write_all(b"\n"))
+            .map_err(|e| format!("写入 {} 到SQL流失败: {}", name, e))?;
+    }
+
+    Ok(ManifestEntry {
+        name: SQL_STREAM_ENTRY.to_string(),
+        size: writer.size(),
+        sha256: writer.hex_digest(),
+    })
+}
+
+// 把暂存ZIP中的清单改写为只有一个SQL流条目，保存在备份文件旁边
+fn write_sidecar_manifest(
+    archive: &mut ZipArchive<File>,
+    output_path: &Path,
+    stream_entry: ManifestEntry,
+) -> Result<(), String> {
+    let mut manifest = match manifest::read_manifest(archive)? {
+        Some(manifest) => manifest,
+        None => return Ok(()),
+    };
+    manifest.entries = vec![stream_entry];
+
+    let json =
+        serde_json::to_vec_pretty(&manifest).map_err(|e| format!("生成备份清单失败: {}", e))?;
+    fs::write(sidecar_manifest_path(output_path), json)
+        .map_err(|e| format!("写入备份清单失败: {}", e))
+}
+
+// 读取备份清单：ZIP和tar.zst从归档中读取，单文件SQL格式从旁边的文件读取，没有清单时返回None
+pub fn read_manifest(
+    archive_path: &Path,
+    key: Option<&DecryptionKey>,
+) -> Result<Option<BackupManifest>, String> {
+    let (format, reader, progress) = open_archive(archive_path, key)?;
+    match format {
+        ArchiveFormat::Zip => {
+            let mut archive =
+                ZipArchive::new(reader).map_err(|e| format!("读取备份文件失败: {}", e))?;
+            manifest::read_manifest(&mut archive)
+        }
+        ArchiveFormat::TarZst => {
+            // tar只能顺序读取，清单在最后，需要读完整个归档
+            let mut manifest = None;
+            visit_entries(format, reader, &progress, |name, reader, _| {
+                if name == MANIFEST_ENTRY {
+                    let mut content = Vec::new();
+                    reader
+                        .read_to_end(&mut content)
+                        .map_err(|e| format!("读取备份清单失败: {}", e))?;
+                    manifest = Some(manifest::parse_manifest(&content)?);
+                }
+                Ok(())
+            })?;
+            Ok(manifest)
+        }
+        ArchiveFormat::SqlGz | ArchiveFormat::SqlZst => read_manifest_sidecar(archive_path),
+    }
+}
+
+// 不需要读完整个归档就能取得的清单：ZIP从中央目录读取，单文件SQL格式读取旁边的文件。
+// tar.zst的清单在归档末尾，返回None，由调用方在读取条目时取得
+pub fn read_manifest_upfront(
+    archive_path: &Path,
+    key: Option<&DecryptionKey>,
+) -> Result<Option<BackupManifest>, String> {
+    let (format, reader, _) = open_archive(archive_path, key)?;
+    match format {
+        ArchiveFormat::Zip => {
+            let mut archive =
+                ZipArchive::new(reader).map_err(|e| format!("读取备份文件失败: {}", e))?;
+            manifest::read_manifest(&mut archive)
+        }
+        ArchiveFormat::TarZst => Ok(None),
+        ArchiveFormat::SqlGz | ArchiveFormat::SqlZst => read_manifest_sidecar(archive_path),
+    }
+}
+
+// 读取单文件SQL格式备份旁边的清单，不存在时返回None
+fn read_manifest_sidecar(archive_path: &Path) -> Result<Option<BackupManifest>, String> {
+    let path = sidecar_manifest_path(archive_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read(&path).map_err(|e| format!("读取备份清单失败: {}", e))?;
+    manifest::parse_manifest(&content).map(Some)
+}
+
+// ZIP备份中的条目名，其他格式只能顺序读取，返回None
+pub fn zip_entry_names(
+    archive_path: &Path,
+    key: Option<&DecryptionKey>,
+) -> Result<Option<Vec<String>>, String> {
+    let (format, reader, _) = open_archive(archive_path, key)?;
+    if format != ArchiveFormat::Zip {
+        return Ok(None);
+    }
+
+    let archive = ZipArchive::new(reader).map_err(|e| format!("读取备份文件失败: {}", e))?;
+    Ok(Some(
+        archive.file_names().map(|name| name.to_string()).collect(),
+    ))
+}
+
+// 按已读取的备份文件字节数计算进度
+pub struct ArchiveProgress {
+    position: Arc<AtomicU64>,
+    total: u64,
+}
+
+impl ArchiveProgress {
+    // 已读取的比例，0.0到1.0
+    pub fn fraction(&self) -> f64 {
+        let position = self.position.load(Ordering::Relaxed);
+        (position as f64 / self.total.max(1) as f64).min(1.0)
+    }
+}
+
+// 记录底层文件读取位置的读取器
+struct PositionedFile {
+    file: File,
+    position: Arc<AtomicU64>,
+}
+
+impl Read for PositionedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.file.read(buf)?;
+        self.position.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+impl Seek for PositionedFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = self.file.seek(pos)?;
+        self.position.store(position, Ordering::Relaxed);
+        Ok(position)
+    }
+}
+
+// 可读取和定位的备份内容，加密的备份为解密后的内容
+trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+// 打开备份文件并根据文件头判断格式，加密的文件返回解密后的内容，
+// 进度按底层文件的读取位置计算。zstd压缩的文件再根据扩展名区分tar和SQL
+fn open_archive(
+    archive_path: &Path,
+    key: Option<&DecryptionKey>,
+) -> Result<(ArchiveFormat, Box<dyn ReadSeek>, ArchiveProgress), String> {
+    let encrypted = crypto::is_encrypted(archive_path)?;
+    let file = File::open(archive_path).map_err(|e| format!("打开备份文件失败: {}", e))?;
+    let total = file
+        .metadata()
+        .map_err(|e| format!("读取备份文件失败: {}", e))?
+        .len();
+    let position = Arc::new(AtomicU64::new(0));
+    let progress = ArchiveProgress {
+        position: Arc::clone(&position),
+        total,
+    };
+    let reader = PositionedFile { file, position };
+
+    let mut reader: Box<dyn ReadSeek> = if encrypted {
+        Box::new(crypto::decrypt(reader, key)?)
+    } else {
+        Box::new(reader)
+    };
+
+    let mut magic = [0u8; 4];
+    let mut n = 0;
+    while n < magic.len() {
+        match reader.read(&mut magic[n..]) {
+            Ok(0) => break,
+            Ok(read) => n += read,
+            Err(e) => return Err(format!("读取备份文件失败: {}", e)),
+        }
+    }
+    reader
+        .seek(SeekFrom::Start(0))
+        .map_err(|e| format!("读取备份文件失败: {}", e))?;
+
+    let magic = &magic[..n];
+    let format = if magic.starts_with(b"PK") {
+        ArchiveFormat::Zip
+    } else if magic.starts_with(&[0x1f, 0x8b]) {
+        ArchiveFormat::SqlGz
+    } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        let file_name = archive_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if crypto::strip_encrypted_suffix(&file_name).ends_with(".tar.zst") {
+            ArchiveFormat::TarZst
+        } else {
+            ArchiveFormat::SqlZst
+        }
+    } else {
+        return Err("无法识别的备份文件格式".to_string());
+    };
+
+    Ok((format, reader, progress))
+}
+
+// 按存储顺序依次读取备份中的每个文件条目，单文件SQL格式只有一个条目SQL_STREAM_ENTRY。
+// 回调不读取条目内容时该条目会被跳过
+pub fn read_entries<F>(
+    archive_path: &Path,
+    key: Option<&DecryptionKey>,
+    visit: F,
+) -> Result<(), String>
+where
+    F: FnMut(&str, &mut dyn Read, &ArchiveProgress) -> Result<(), String>,
+{
+    let (format, reader, progress) = open_archive(archive_path, key)?;
+    visit_entries(format, reader, &progress, visit)
+}
+
+// 按格式依次读取已打开的备份中的每个文件条目
+fn visit_entries<F>(
+    format: ArchiveFormat,
+    reader: Box<dyn ReadSeek>,
+    progress: &ArchiveProgress,
+    mut visit: F,
+) -> Result<(), String>
+where
+    F: FnMut(&str, &mut dyn Read, &ArchiveProgress) -> Result<(), String>,
+{
+    match format {
+        ArchiveFormat::Zip => {
+            let mut archive =
+                ZipArchive::new(reader).map_err(|e| format!("读取备份文件失败: {}", e))?;
+            for index in 0..archive.len() {
+                let mut entry = archive
+                    .by_index(index)
+                    .map_err(|e| format!("读取备份文件条目失败: {}", e))?;
+                if entry.is_dir() {
+                    continue;
+                }
+                let name = entry.name().to_string();
+                visit(&name, &mut entry, progress)?;
+            }
+        }
+        ArchiveFormat::TarZst => {
+            let decoder = zstd::stream::read::Decoder::new(reader)
+                .map_err(|e| format!("读取zstd压缩流失败: {}", e))?;
+            let mut archive = tar::Archive::new(decoder);
+            let entries = archive
+                .entries()
+                .map_err(|e| format!("读取tar文件失败: {}", e))?;
+            for entry in entries {
+                let mut entry = entry.map_err(|e| format!("读取tar文件条目失败: {}", e))?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let name = entry
+                    .path()
+                    .map_err(|e| format!("读取tar文件条目失败: {}", e))?
+                    .to_string_lossy()
+                    .into_owned();
+                visit(&name, &mut entry, progress)?;
+            }
+        }
+        ArchiveFormat::SqlGz => {
+            let mut decoder = flate2::read::MultiGzDecoder::new(BufReader::new(reader));
+            visit(SQL_STREAM_ENTRY, &mut decoder, progress)?;
+        }
+        ArchiveFormat::SqlZst => {
+            let mut decoder = zstd::stream::read::Decoder::new(reader)
+                .map_err(|e| format!("读取zstd压缩流失败: {}", e))?;
+            visit(SQL_STREAM_ENTRY, &mut decoder, progress)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zip::ZipWriter;
+
+    const ORDERS: &str =
+        "CREATE TABLE `orders` (`id` int);\nINSERT INTO `orders` VALUES (1),(2);\n";
+    const USERS: &str = "CREATE TABLE `users` (`id` int);\nINSERT INTO `users` VALUES (1);\n";
+
+    // 写出与备份引擎相同结构的暂存ZIP：两个表条目加清单
+    fn write_staging_zip(path: &Path) {
+        let manifest = serde_json::json!({
+            "format_version": 1,
+            "tool_version": "test",
+            "engine": "builtin",
+            "database": "shop",
+            "server_version": "8.0.36",
+            "sql_mode": "",
+            "character_set": "utf8mb4",
+            "collation": "utf8mb4_0900_ai_ci",
+            "started_at": "2024-01-15T10:00:00+08:00",
+            "finished_at": "2024-01-15T10:00:01+08:00",
+            "tables": [
+                { "name": "orders", "kind": "table", "rows": 2 },
+                { "name": "users", "kind": "table", "rows": 1 }
+            ],
+            "entries": [
+                { "name": "table_orders.sql", "size": ORDERS.len(), "sha256": "" },
+                { "name": "table_users.sql", "size": USERS.len(), "sha256": "" }
+            ]
+        });
+
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        let options = zip_file_options(Codec::Deflate, None);
+        for (name, content) in [
+            ("table_orders.sql", ORDERS.as_bytes().to_vec()),
+            ("table_users.sql", USERS.as_bytes().to_vec()),
+            (MANIFEST_ENTRY, serde_json::to_vec(&manifest).unwrap()),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(&content).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    // 转换为指定格式后读出所有条目的名称和内容
+    fn convert_and_read(format: ArchiveFormat) -> (Vec<(String, String)>, Option<BackupManifest>) {
+        let dir = tempfile::tempdir().unwrap();
+        let staging = dir.path().join("staging.zip");
+        write_staging_zip(&staging);
+        let output = dir
+            .path()
+            .join(format!("BACKUP_shop{}", format.extension()));
+        convert_staging_zip(
+            &staging,
+            &output,
+            format,
+            Some(3),
+            &EncryptionOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(detect_format(&output, None).unwrap(), format);
+        let mut entries = Vec::new();
+        read_entries(&output, None, |name, reader, _| {
+            let mut content = String::new();
+            reader
+                .read_to_string(&mut content)
+                .map_err(|e| e.to_string())?;
+            entries.push((name.to_string(), content));
+            Ok(())
+        })
+        .unwrap();
+        (entries, read_manifest(&output, None).unwrap())
+    }
+
+    #[test]
+    fn sql_gz_round_trip_concatenates_sql_entries() {
+        let (entries, manifest) = convert_and_read(ArchiveFormat::SqlGz);
+        let script = format!("{}\n{}\n", ORDERS, USERS);
+        assert_eq!(entries, [(SQL_STREAM_ENTRY.to_string(), script.clone())]);
+
+        // 旁边的清单只有一个SQL流条目，校验值与流内容一致
+        let manifest = manifest.unwrap();
+        assert_eq!(manifest.tables.len(), 2);
+        assert_eq!(manifest.entries.len(), 1);
+        assert_eq!(manifest.entries[0].name, SQL_STREAM_ENTRY);
+        assert_eq!(manifest.entries[0].size, script.len() as u64);
+        let mut hasher = HashingWriter::new(io::sink());
+        hasher.write_all(script.as_bytes()).unwrap();
+        assert_eq!(manifest.entries[0].sha256, hasher.hex_digest());
+    }
+
+    #[test]
+    fn sql_zst_round_trip_concatenates_sql_entries() {
+        let (entries, manifest) = convert_and_read(ArchiveFormat::SqlZst);
+        assert_eq!(
+            entries,
+            [(
+                SQL_STREAM_ENTRY.to_string(),
+                format!("{}\n{}\n", ORDERS, USERS)
+            )]
+        );
+        assert_eq!(manifest.unwrap().entries[0].name, SQL_STREAM_ENTRY);
+    }
+
+    #[test]
+    fn tar_zst_round_trip_keeps_every_entry() {
+        let (entries, manifest) = convert_and_read(ArchiveFormat::TarZst);
+        let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["table_orders.sql", "table_users.sql", MANIFEST_ENTRY]
+        );
+        assert_eq!(entries[0].1, ORDERS);
+        assert_eq!(entries[1].1, USERS);
+
+        // tar.zst的清单与ZIP中的完全相同
+        let manifest = manifest.unwrap();
+        assert_eq!(manifest.entries.len(), 2);
+        assert_eq!(manifest.tables[0].rows, Some(2));
+    }
+
+    #[test]
+    fn convert_staging_zip_rejects_zip_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let staging = dir.path().join("staging.zip");
+        write_staging_zip(&staging);
+        let result = convert_staging_zip(
+            &staging,
+            &dir.path().join("BACKUP_shop.zip"),
+            ArchiveFormat::Zip,
+            None,
+            &EncryptionOptions::default(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn validate_level_checks_range_per_format() {
+        assert!(validate_level(ArchiveFormat::SqlGz, None).is_ok());
+        assert!(validate_level(ArchiveFormat::SqlGz, Some(0)).is_ok());
+        assert!(validate_level(ArchiveFormat::SqlGz, Some(9)).is_ok());
+        assert!(validate_level(ArchiveFormat::SqlGz, Some(10)).is_err());
+        assert!(validate_level(ArchiveFormat::SqlGz, Some(-1)).is_err());
+
+        let zstd_levels = zstd::compression_level_range();
+        for format in [ArchiveFormat::SqlZst, ArchiveFormat::TarZst] {
+            assert!(validate_level(format, Some(*zstd_levels.start())).is_ok());
+            assert!(validate_level(format, Some(*zstd_levels.end())).is_ok());
+            assert!(validate_level(format, Some(zstd_levels.end() + 1)).is_err());
+            assert!(validate_level(format, Some(zstd_levels.start() - 1)).is_err());
+        }
+
+        // ZIP的级别由zip库检查
+        assert!(validate_level(ArchiveFormat::Zip, Some(100)).is_ok());
+        assert_eq!(
+            validate_level(ArchiveFormat::SqlGz, Some(10)).unwrap_err(),
+            "压缩级别 10 不适用于 .sql.gz 格式"
+        );
+    }
+
+    #[test]
+    fn backup_file_names_include_encrypted_archives() {
+        assert!(is_backup_file_name("BACKUP_shop_20240115.zip"));
+        assert!(is_backup_file_name("BACKUP_shop_20240115.sql.gz"));
+        assert!(is_backup_file_name("BACKUP_shop_20240115.tar.zst.age"));
+        assert!(!is_backup_file_name("shop_20240115.zip"));
+        assert!(!is_backup_file_name("BACKUP_shop_20240115.sql"));
+        assert_eq!(
+            sidecar_manifest_path(Path::new("/backups/BACKUP_shop.sql.gz")),
+            PathBuf::from("/backups/BACKUP_shop.sql.gz.manifest.json")
+        );
+    }
+}
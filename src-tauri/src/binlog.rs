@@ -0,0 +1,366 @@
+// binlog位置：记录备份快照对应的binlog文件、位置和GTID集合，用于基于时间点的恢复和建立副本
+use mysql::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::process::Command;
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+// 从mysqldump输出中保留的最大字节数，binlog位置写在建表语句之前
+const HEADER_CAPTURE_LIMIT: usize = 1024 * 1024;
+
+// 备份快照对应的binlog位置
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+#[serde(default)]
+pub struct BinlogPosition {
+    pub file: String,
+    pub position: u64,
+    // 快照时已执行的GTID集合，与@@GLOBAL.gtid_executed相同，服务器未启用GTID时为空
+    pub gtid_executed: String,
+}
+
+impl BinlogPosition {
+    // 注释掉的复制配置语句，用此备份建立副本时去掉注释执行
+    pub fn change_source_comment(&self, server_version: &str) -> String {
+        let statement = if uses_replication_source(server_version) {
+            format!(
+                "CHANGE REPLICATION SOURCE TO SOURCE_LOG_FILE='{}', SOURCE_LOG_POS={};",
+                self.file, self.position
+            )
+        } else {
+            format!(
+                "CHANGE MASTER TO MASTER_LOG_FILE='{}', MASTER_LOG_POS={};",
+                self.file, self.position
+            )
+        };
+
+        let mut comment = format!(
+            "\n-- 备份快照对应的binlog位置，用此备份建立副本时去掉注释执行\n-- {}\n",
+            statement
+        );
+        if !self.gtid_executed.is_empty() {
+            comment.push_str(&format!(
+                "-- 副本使用GTID自动定位时改为执行：\n-- SET @@GLOBAL.GTID_PURGED='{}';\n",
+                self.gtid_executed
+            ));
+        }
+        comment
+    }
+}
+
+// MySQL 8.0.23起使用CHANGE REPLICATION SOURCE TO，旧版本和MariaDB使用CHANGE MASTER TO
+fn uses_replication_source(server_version: &str) -> bool {
+    if server_version.to_lowercase().contains("mariadb") {
+        return false;
+    }
+    let numbers: Vec<u32> = server_version
+        .split('-')
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect();
+    let version = (
+        numbers.first().copied().unwrap_or(0),
+        numbers.get(1).copied().unwrap_or(0),
+        numbers.get(2).copied().unwrap_or(0),
+    );
+    version >= (8, 0, 23)
+}
+
+// 服务器是否启用了binlog
+pub fn binlog_enabled<C: Queryable>(conn: &mut C) -> Result<bool, String> {
+    let log_bin: Option<Option<String>> = conn
+        .query_first("SELECT @@GLOBAL.log_bin")
+        .map_err(|e| format!("读取binlog设置失败: {}", e))?;
+    Ok(matches!(
+        log_bin.flatten().as_deref(),
+        Some("1") | Some("ON")
+    ))
+}
+
+// 读取当前的binlog位置，服务器未启用binlog时返回None。
+// 需要在全局读锁期间调用，读取的位置才与同一时间开启的快照对应
+pub fn read_binlog_position<C: Queryable>(conn: &mut C) -> Result<Option<BinlogPosition>, String> {
+    // MySQL 8.2起改名为SHOW BINARY LOG STATUS，旧版本和MariaDB只支持SHOW MASTER STATUS
+    let row: Option<mysql::Row> = match conn.query_first("SHOW BINARY LOG STATUS") {
+        Ok(row) => row,
+        Err(_) => conn
+            .query_first("SHOW MASTER STATUS")
+            .map_err(|e| format!("读取binlog位置失败: {}", e))?,
+    };
+    let row = match row {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+
+    let file: String = row
+        .get("File")
+        .ok_or_else(|| "读取binlog位置失败: 没有File列".to_string())?;
+    let position: u64 = row
+        .get("Position")
+        .ok_or_else(|| "读取binlog位置失败: 没有Position列".to_string())?;
+    // MariaDB的GTID不在该列中，只记录文件和位置
+    let gtid_executed: String = row
+        .get::<Option<String>, _>("Executed_Gtid_Set")
+        .flatten()
+        .unwrap_or_default();
+
+    Ok(Some(BinlogPosition {
+        file,
+        position,
+        gtid_executed: normalize_gtid_set(&gtid_executed),
+    }))
+}
+
+// 服务器返回的GTID集合中每个UUID之间有换行，去掉所有空白
+fn normalize_gtid_set(gtid_set: &str) -> String {
+    gtid_set.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+// mysqldump记录binlog位置的参数，8.0.26起--master-data改名为--source-data。
+// 值为2时位置以注释的形式写入导出文件
+pub fn mysqldump_source_data_arg() -> &'static str {
+    let mut cmd = Command::new("mysqldump");
+    cmd.arg("--help");
+
+    // 在Windows平台上添加无窗口标志
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(0x08000000);
+
+    match cmd.output() {
+        Ok(output) if String::from_utf8_lossy(&output.stdout).contains("--source-data") => {
+            "--source-data=2"
+        }
+        _ => "--master-data=2",
+    }
+}
+
+// 从mysqldump输出的开头解析binlog位置和GTID_PURGED设置的GTID集合
+pub fn parse_dump_header(header: &[u8]) -> Option<BinlogPosition> {
+    let text = String::from_utf8_lossy(header);
+
+    let file_start = ["SOURCE_LOG_FILE='", "MASTER_LOG_FILE='"]
+        .iter()
+        .find_map(|marker| text.find(marker).map(|index| index + marker.len()))?;
+    let file_end = file_start + text[file_start..].find('\'')?;
+    let file = text[file_start..file_end].to_string();
+
+    let position_start = file_end + text[file_end..].find("_LOG_POS=")? + "_LOG_POS=".len();
+    let position = text[position_start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse()
+        .ok()?;
+
+    // SET @@GLOBAL.GTID_PURGED=/*!80000 '+'*/ 'uuid:1-5';
+    let gtid_executed = text
+        .find("GTID_PURGED=")
+        .and_then(|index| {
+            let mut rest = &text[index + "GTID_PURGED=".len()..];
+            if rest.trim_start().starts_with("/*") {
+                rest = &rest[rest.find("*/")? + 2..];
+            }
+            let start = rest.find('\'')? + 1;
+            let end = start + rest[start..].find('\'')?;
+            Some(normalize_gtid_set(&rest[start..end]))
+        })
+        .unwrap_or_default();
+
+    Some(BinlogPosition {
+        file,
+        position,
+        gtid_executed,
+    })
+}
+
+// 写入时保留开头的一部分内容，用于从mysqldump的输出中解析binlog位置
+pub struct HeaderCapture<W: Write> {
+    inner: W,
+    header: Vec<u8>,
+}
+
+impl<W: Write> HeaderCapture<W> {
+    pub fn new(inner: W) -> Self {
+        HeaderCapture {
+            inner,
+            header: Vec::new(),
+        }
+    }
+
+    pub fn header(&self) -> &[u8] {
+        &self.header
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for HeaderCapture<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        let room = HEADER_CAPTURE_LIMIT.saturating_sub(self.header.len());
+        self.header.extend_from_slice(&buf[..written.min(room)]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // MySQL 8.0 mysqldump --source-data=2 --set-gtid-purged=ON的输出开头
+    const MYSQL_80_HEADER: &str = "-- MySQL dump 10.13  Distrib 8.0.36, for Linux (x86_64)
+--
+-- Host: 127.0.0.1    Database: shop
+-- ------------------------------------------------------
+-- Server version\t8.0.36
+
+/*!40101 SET @OLD_CHARACTER_SET_CLIENT=@@CHARACTER_SET_CLIENT */;
+/*!50503 SET NAMES utf8mb4 */;
+SET @MYSQLDUMP_TEMP_LOG_BIN = @@SESSION.SQL_LOG_BIN;
+SET @@SESSION.SQL_LOG_BIN= 0;
+
+--
+-- GTID state at the beginning of the backup 
+--
+
+SET @@GLOBAL.GTID_PURGED=/*!80000 '+'*/ '3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5,
+4d8f1c2e-71ca-11e1-9e33-c80aa9429562:1-12';
+
+--
+-- Position to start replication or point-in-time recovery from
+--
+
+-- CHANGE REPLICATION SOURCE TO SOURCE_LOG_FILE='binlog.000042', SOURCE_LOG_POS=157;
+
+--
+-- Current Database: `shop`
+--
+";
+
+    // MySQL 5.7 mysqldump --master-data=2的输出开头
+    const MYSQL_57_HEADER: &str = "-- MySQL dump 10.13  Distrib 5.7.44, for Linux (x86_64)
+--
+-- Server version\t5.7.44-log
+
+SET @@GLOBAL.GTID_PURGED='3e11fa47-71ca-11e1-9e33-c80aa9429562:1-7';
+
+--
+-- Position to start replication or point-in-time recovery from
+--
+
+-- CHANGE MASTER TO MASTER_LOG_FILE='mysql-bin.000003', MASTER_LOG_POS=1234;
+";
+
+    // MariaDB mysqldump --master-data=2的输出开头，GTID不在GTID_PURGED中
+    const MARIADB_HEADER: &str =
+        "-- MariaDB dump 10.19  Distrib 10.11.6-MariaDB, for debian-linux-gnu (x86_64)
+--
+-- Server version\t10.11.6-MariaDB-0+deb12u1
+
+--
+-- Position to start replication or point-in-time recovery from
+--
+
+-- CHANGE MASTER TO MASTER_LOG_FILE='mariadb-bin.000007', MASTER_LOG_POS=342;
+";
+
+    #[test]
+    fn parse_dump_header_reads_mysql_80_source_data() {
+        assert_eq!(
+            parse_dump_header(MYSQL_80_HEADER.as_bytes()),
+            Some(BinlogPosition {
+                file: "binlog.000042".to_string(),
+                position: 157,
+                gtid_executed: "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5,\
+                                4d8f1c2e-71ca-11e1-9e33-c80aa9429562:1-12"
+                    .to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_dump_header_reads_mysql_57_master_data() {
+        assert_eq!(
+            parse_dump_header(MYSQL_57_HEADER.as_bytes()),
+            Some(BinlogPosition {
+                file: "mysql-bin.000003".to_string(),
+                position: 1234,
+                gtid_executed: "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-7".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_dump_header_reads_mariadb_without_gtid() {
+        assert_eq!(
+            parse_dump_header(MARIADB_HEADER.as_bytes()),
+            Some(BinlogPosition {
+                file: "mariadb-bin.000007".to_string(),
+                position: 342,
+                gtid_executed: String::new(),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_dump_header_without_position_returns_none() {
+        let header = "-- MySQL dump 10.13\n/*!40101 SET NAMES utf8mb4 */;\n";
+        assert_eq!(parse_dump_header(header.as_bytes()), None);
+
+        // 截断在位置之前的输出
+        let truncated = "-- CHANGE MASTER TO MASTER_LOG_FILE='mysql-bin.000003'";
+        assert_eq!(parse_dump_header(truncated.as_bytes()), None);
+    }
+
+    #[test]
+    fn uses_replication_source_from_mysql_8_0_23() {
+        assert!(uses_replication_source("8.0.23"));
+        assert!(uses_replication_source("8.0.36-0ubuntu0.22.04.1"));
+        assert!(uses_replication_source("8.4.0"));
+        assert!(uses_replication_source("9.1.0-commercial"));
+        assert!(!uses_replication_source("8.0.22"));
+        assert!(!uses_replication_source("5.7.44-log"));
+        assert!(!uses_replication_source(""));
+    }
+
+    #[test]
+    fn uses_replication_source_never_for_mariadb() {
+        assert!(!uses_replication_source("10.11.6-MariaDB-0+deb12u1"));
+        assert!(!uses_replication_source("11.4.2-MariaDB"));
+        assert!(!uses_replication_source("5.5.5-10.6.12-MariaDB"));
+    }
+
+    #[test]
+    fn change_source_comment_matches_server_version() {
+        let position = BinlogPosition {
+            file: "binlog.000042".to_string(),
+            position: 157,
+            gtid_executed: "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5".to_string(),
+        };
+
+        let comment = position.change_source_comment("8.0.36");
+        assert!(comment.contains(
+            "-- CHANGE REPLICATION SOURCE TO SOURCE_LOG_FILE='binlog.000042', SOURCE_LOG_POS=157;"
+        ));
+        assert!(comment
+            .contains("-- SET @@GLOBAL.GTID_PURGED='3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5';"));
+        // 注释中的位置可以被解析回来
+        assert_eq!(
+            parse_dump_header(comment.as_bytes()),
+            Some(position.clone())
+        );
+
+        let comment = position.change_source_comment("5.7.44-log");
+        assert!(comment
+            .contains("-- CHANGE MASTER TO MASTER_LOG_FILE='binlog.000042', MASTER_LOG_POS=157;"));
+    }
+}
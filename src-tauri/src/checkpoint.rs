@@ -0,0 +1,545 @@
+// 可续传备份：内置引擎把每个工作单元写入工作目录，完成后记录到检查点文件，
+// 中断后再次备份同一数据库时跳过已完成的工作单元，最后再合并成完整的备份
+use crate::crypto;
+use crate::manifest::BackupMode;
+use crate::{TableWorkItem, WrittenItem};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::iter;
+use std::path::{Path, PathBuf};
+
+// 检查点在工作目录中的文件名
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+// 检查点格式版本，字段发生不兼容变化时递增，版本不同的检查点会被丢弃
+const CHECKPOINT_FORMAT_VERSION: u32 = 2;
+
+// 可续传备份的检查点
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub format_version: u32,
+    pub host: String,
+    pub port: u16,
+    pub database: String,
+    // 备份包含的内容，与本次备份不同时不能继续
+    #[serde(default)]
+    pub mode: BackupMode,
+    // 第一次开始备份的时间，RFC 3339格式
+    pub started_at: String,
+    // 每次继续备份的时间，不为空说明各工作单元来自不同的快照
+    pub resumed_at: Vec<String>,
+    pub items: Vec<CheckpointItem>,
+}
+
+// 检查点中的工作单元，内容写入工作目录中以id命名的分片ZIP
+#[derive(Serialize, Deserialize)]
+pub struct CheckpointItem {
+    pub id: usize,
+    pub work: TableWorkItem,
+    pub done: bool,
+    // 分片中条目的校验值、数据行数和表的校验和，完成后记录
+    pub written: Option<WrittenItem>,
+}
+
+// 返回给前端的未完成备份信息，用于询问是否继续
+#[derive(Serialize)]
+pub struct ResumableBackup {
+    pub started_at: String,
+    pub resumed_at: Vec<String>,
+    pub done_items: usize,
+    pub total_items: usize,
+}
+
+impl Checkpoint {
+    pub fn new(host: &str, port: u16, database: &str, mode: BackupMode, started_at: &str) -> Self {
+        Checkpoint {
+            format_version: CHECKPOINT_FORMAT_VERSION,
+            host: host.to_string(),
+            port,
+            database: database.to_string(),
+            mode,
+            started_at: started_at.to_string(),
+            resumed_at: Vec::new(),
+            items: Vec::new(),
+        }
+    }
+
+    // 读取工作目录中的检查点，不存在或版本不同时返回None
+    pub fn load(work_dir: &Path) -> Result<Option<Self>, String> {
+        let path = work_dir.join(CHECKPOINT_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read(&path).map_err(|e| format!("读取备份检查点失败: {}", e))?;
+        let checkpoint: Checkpoint =
+            serde_json::from_slice(&content).map_err(|e| format!("解析备份检查点失败: {}", e))?;
+        if checkpoint.format_version != CHECKPOINT_FORMAT_VERSION {
+            return Ok(None);
+        }
+        Ok(Some(checkpoint))
+    }
+
+    // 先写临时文件再重命名，避免中断时留下写了一半的检查点
+    pub fn save(&self, work_dir: &Path) -> Result<(), String> {
+        let json =
+            serde_json::to_vec_pretty(self).map_err(|e| format!("生成备份检查点失败: {}", e))?;
+        let temp_path = work_dir.join(format!("{}.tmp", CHECKPOINT_FILE));
+        fs::write(&temp_path, json).map_err(|e| format!("写入备份检查点失败: {}", e))?;
+        fs::rename(&temp_path, work_dir.join(CHECKPOINT_FILE))
+            .map_err(|e| format!("写入备份检查点失败: {}", e))
+    }
+
+    // 检查点是否属于同一个备份任务
+    pub fn matches(&self, host: &str, port: u16, database: &str) -> bool {
+        self.host == host && self.port == port && self.database == database
+    }
+
+    // 追加新规划的工作单元
+    pub fn append(&mut self, items: Vec<TableWorkItem>) {
+        let mut next_id = self.items.iter().map(|item| item.id + 1).max().unwrap_or(0);
+        for work in items {
+            self.items.push(CheckpointItem {
+                id: next_id,
+                work,
+                done: false,
+                written: None,
+            });
+            next_id += 1;
+        }
+    }
+
+    // 与当前的表列表对照：删除已不存在的表的工作单元，返回检查点中还没有的表
+    pub fn reconcile(&mut self, work_dir: &Path, tables: &[String]) -> Vec<String> {
+        self.items.retain(|item| {
+            let exists = tables.contains(&item.work.table);
+            if !exists {
+                let _ = fs::remove_file(part_path(work_dir, item.id));
+            }
+            exists
+        });
+
+        tables
+            .iter()
+            .filter(|table| !self.items.iter().any(|item| &item.work.table == *table))
+            .cloned()
+            .collect()
+    }
+
+    pub fn mark_done(&mut self, id: usize, written: WrittenItem) {
+        if let Some(item) = self.items.iter_mut().find(|item| item.id == id) {
+            item.done = true;
+            item.written = Some(written);
+        }
+    }
+
+    pub fn summary(&self) -> ResumableBackup {
+        ResumableBackup {
+            started_at: self.started_at.clone(),
+            resumed_at: self.resumed_at.clone(),
+            done_items: self.items.iter().filter(|item| item.done).count(),
+            total_items: self.items.len(),
+        }
+    }
+}
+
+// 数据库的工作目录，一个数据库同时只有一个未完成的可续传备份。一般放在备份目录下；
+// 加密备份的分片是明文，工作目录放在私有临时目录中，备份目录下只记录它的位置
+pub fn work_dir(backup_dir: &Path, database: &str) -> PathBuf {
+    private_work_dir(backup_dir, database).unwrap_or_else(|| shared_work_dir(backup_dir, database))
+}
+
+// 备份目录下的工作目录
+fn shared_work_dir(backup_dir: &Path, database: &str) -> PathBuf {
+    backup_dir.join(format!(".mysql_backup_resume_{}", database))
+}
+
+// 记录加密备份工作目录位置的文件
+fn location_path(backup_dir: &Path, database: &str) -> PathBuf {
+    backup_dir.join(format!(".mysql_backup_resume_{}.location", database))
+}
+
+// 加密备份的私有工作目录，没有记录或目录已被删除时返回None
+pub fn private_work_dir(backup_dir: &Path, database: &str) -> Option<PathBuf> {
+    let location = fs::read_to_string(location_path(backup_dir, database)).ok()?;
+    let dir = PathBuf::from(location.trim());
+    if dir.is_dir() {
+        Some(dir)
+    } else {
+        None
+    }
+}
+
+// 丢弃旧的工作目录后创建新的工作目录，需要加密时放在私有临时目录中
+pub fn create_work_dir(
+    backup_dir: &Path,
+    database: &str,
+    private: bool,
+) -> Result<PathBuf, String> {
+    discard(backup_dir, database)?;
+
+    if !private {
+        let dir = shared_work_dir(backup_dir, database);
+        fs::create_dir_all(&dir).map_err(|e| format!("创建备份工作目录失败: {}", e))?;
+        return Ok(dir);
+    }
+
+    // 工作目录需要保留到下次继续备份，不随TempDir释放而删除
+    let dir = crypto::private_staging_dir(".mysql_backup_resume_")?.into_path();
+    if let Err(e) = fs::write(
+        location_path(backup_dir, database),
+        dir.to_string_lossy().as_bytes(),
+    ) {
+        let _ = fs::remove_dir_all(&dir);
+        return Err(format!("写入备份工作目录位置失败: {}", e));
+    }
+    Ok(dir)
+}
+
+// 工作单元的分片ZIP路径
+pub fn part_path(work_dir: &Path, id: usize) -> PathBuf {
+    work_dir.join(format!("part_{:06}.zip", id))
+}
+
+// 查找备份目录中同一备份任务未完成的检查点
+pub fn find_resumable(
+    backup_dir: &Path,
+    host: &str,
+    port: u16,
+    database: &str,
+) -> Result<Option<ResumableBackup>, String> {
+    let checkpoint = Checkpoint::load(&work_dir(backup_dir, database))?;
+    Ok(checkpoint
+        .filter(|checkpoint| checkpoint.matches(host, port, database))
+        .map(|checkpoint| checkpoint.summary()))
+}
+
+// 删除工作目录和其中的检查点、分片，以及私有工作目录的位置记录
+pub fn discard(backup_dir: &Path, database: &str) -> Result<(), String> {
+    let private_dir = private_work_dir(backup_dir, database);
+    for work_dir in private_dir
+        .iter()
+        .chain(iter::once(&shared_work_dir(backup_dir, database)))
+    {
+        if work_dir.exists() {
+            fs::remove_dir_all(work_dir).map_err(|e| format!("删除备份工作目录失败: {}", e))?;
+        }
+    }
+
+    let location = location_path(backup_dir, database);
+    if location.exists() {
+        fs::remove_file(&location).map_err(|e| format!("删除备份工作目录失败: {}", e))?;
+    }
+    Ok(())
+}
+
+// 分块边界的键值在检查点中的表示，与mysql::Value一一对应
+#[derive(Serialize, Deserialize)]
+enum StoredValue {
+    Null,
+    // 十六进制编码
+    Bytes(String),
+    Int(i64),
+    UInt(u64),
+    Float(f32),
+    Double(f64),
+    Date(u16, u8, u8, u8, u8, u8, u32),
+    Time(bool, u32, u8, u8, u8, u32),
+}
+
+impl From<&mysql::Value> for StoredValue {
+    fn from(value: &mysql::Value) -> Self {
+        match value {
+            mysql::Value::NULL => StoredValue::Null,
+            mysql::Value::Bytes(bytes) => StoredValue::Bytes(hex::encode(bytes)),
+            mysql::Value::Int(v) => StoredValue::Int(*v),
+            mysql::Value::UInt(v) => StoredValue::UInt(*v),
+            mysql::Value::Float(v) => StoredValue::Float(*v),
+            mysql::Value::Double(v) => StoredValue::Double(*v),
+            mysql::Value::Date(y, mo, d, h, mi, s, us) => {
+                StoredValue::Date(*y, *mo, *d, *h, *mi, *s, *us)
+            }
+            mysql::Value::Time(neg, d, h, mi, s, us) => {
+                StoredValue::Time(*neg, *d, *h, *mi, *s, *us)
+            }
+        }
+    }
+}
+
+impl TryFrom<StoredValue> for mysql::Value {
+    type Error = String;
+
+    fn try_from(value: StoredValue) -> Result<Self, String> {
+        Ok(match value {
+            StoredValue::Null => mysql::Value::NULL,
+            StoredValue::Bytes(bytes) => mysql::Value::Bytes(
+                hex::decode(bytes).map_err(|e| format!("无效的分块边界: {}", e))?,
+            ),
+            StoredValue::Int(v) => mysql::Value::Int(v),
+            StoredValue::UInt(v) => mysql::Value::UInt(v),
+            StoredValue::Float(v) => mysql::Value::Float(v),
+            StoredValue::Double(v) => mysql::Value::Double(v),
+            StoredValue::Date(y, mo, d, h, mi, s, us) => mysql::Value::Date(y, mo, d, h, mi, s, us),
+            StoredValue::Time(neg, d, h, mi, s, us) => mysql::Value::Time(neg, d, h, mi, s, us),
+        })
+    }
+}
+
+// 供KeyRange的边界字段使用：#[serde(with = "checkpoint::key_values")]
+pub mod key_values {
+    use super::StoredValue;
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        values: &Option<Vec<mysql::Value>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        values
+            .as_ref()
+            .map(|values| values.iter().map(StoredValue::from).collect::<Vec<_>>())
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<mysql::Value>>, D::Error> {
+        let stored = Option::<Vec<StoredValue>>::deserialize(deserializer)?;
+        stored
+            .map(|values| {
+                values
+                    .into_iter()
+                    .map(mysql::Value::try_from)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(D::Error::custom)
+            })
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KeyRange, TableWorkKind};
+
+    // 覆盖mysql::Value的每一种取值
+    fn all_values() -> Vec<mysql::Value> {
+        vec![
+            mysql::Value::NULL,
+            mysql::Value::Bytes(vec![0x00, 0xff, b'a']),
+            mysql::Value::Int(-42),
+            mysql::Value::UInt(u64::MAX),
+            mysql::Value::Float(1.5),
+            mysql::Value::Double(-0.1),
+            mysql::Value::Date(2024, 2, 29, 23, 59, 58, 999_999),
+            mysql::Value::Time(true, 3, 4, 5, 6, 7),
+        ]
+    }
+
+    #[test]
+    fn stored_value_round_trips_every_variant() {
+        for value in all_values() {
+            let stored = StoredValue::from(&value);
+            assert_eq!(mysql::Value::try_from(stored).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn stored_value_rejects_invalid_hex() {
+        assert!(mysql::Value::try_from(StoredValue::Bytes("zz".to_string())).is_err());
+    }
+
+    fn work_item(table: &str, kind: TableWorkKind) -> TableWorkItem {
+        TableWorkItem {
+            table: table.to_string(),
+            entry_name: format!("table_{}.sql", table),
+            kind,
+        }
+    }
+
+    fn chunk(
+        lower: Option<Vec<mysql::Value>>,
+        upper: Option<Vec<mysql::Value>>,
+        part: usize,
+    ) -> TableWorkKind {
+        TableWorkKind::Chunk(KeyRange {
+            columns: vec!["id".to_string()],
+            lower,
+            upper,
+            part,
+        })
+    }
+
+    fn new_checkpoint() -> Checkpoint {
+        Checkpoint::new(
+            "127.0.0.1",
+            3306,
+            "shop",
+            BackupMode::Full,
+            "2024-01-15T10:00:00+08:00",
+        )
+    }
+
+    #[test]
+    fn key_values_serialize_every_variant_and_none() {
+        let range = KeyRange {
+            columns: vec!["a".to_string(), "b".to_string()],
+            lower: None,
+            upper: Some(all_values()),
+            part: 3,
+        };
+        let json = serde_json::to_value(&range).unwrap();
+        assert_eq!(json["lower"], serde_json::Value::Null);
+        assert_eq!(json["upper"][0], serde_json::json!("Null"));
+        assert_eq!(json["upper"][1], serde_json::json!({ "Bytes": "00ff61" }));
+
+        let loaded: KeyRange = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.columns, range.columns);
+        assert_eq!(loaded.lower, None);
+        assert_eq!(loaded.upper, Some(all_values()));
+        assert_eq!(loaded.part, 3);
+
+        // 损坏的边界值在读取检查点时报错，而不是用错误的边界继续备份
+        let invalid = serde_json::json!({
+            "columns": ["id"],
+            "lower": [{ "Bytes": "zz" }],
+            "upper": null,
+            "part": 1
+        });
+        assert!(serde_json::from_value::<KeyRange>(invalid).is_err());
+    }
+
+    #[test]
+    fn checkpoint_round_trips_through_save_and_load() {
+        let work_dir = tempfile::tempdir().unwrap();
+        let mut checkpoint = new_checkpoint();
+        checkpoint.append(vec![
+            work_item("users", TableWorkKind::Full),
+            work_item("orders", TableWorkKind::Structure),
+            work_item("orders", chunk(None, Some(all_values()), 1)),
+            work_item("orders", chunk(Some(all_values()), None, 2)),
+        ]);
+        checkpoint.mark_done(
+            0,
+            WrittenItem {
+                entry: crate::manifest::ManifestEntry {
+                    name: "table_users.sql".to_string(),
+                    size: 120,
+                    sha256: "ab".repeat(32),
+                },
+                rows: 7,
+                checksum: Some(3_520_135_284),
+            },
+        );
+        checkpoint.save(work_dir.path()).unwrap();
+
+        let loaded = Checkpoint::load(work_dir.path()).unwrap().unwrap();
+        assert!(loaded.matches("127.0.0.1", 3306, "shop"));
+        assert!(!loaded.matches("127.0.0.1", 3307, "shop"));
+        assert_eq!(loaded.started_at, checkpoint.started_at);
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&checkpoint).unwrap()
+        );
+
+        let written = loaded.items[0].written.as_ref().unwrap();
+        assert!(loaded.items[0].done);
+        assert_eq!((written.rows, written.checksum), (7, Some(3_520_135_284)));
+        match (&loaded.items[2].work.kind, &loaded.items[3].work.kind) {
+            (TableWorkKind::Chunk(first), TableWorkKind::Chunk(second)) => {
+                assert_eq!((&first.lower, &first.upper), (&None, &Some(all_values())));
+                assert_eq!((&second.lower, &second.upper), (&Some(all_values()), &None));
+                assert_eq!((first.part, second.part), (1, 2));
+            }
+            _ => panic!("分块工作单元读取后类型改变"),
+        }
+
+        let summary = loaded.summary();
+        assert_eq!((summary.done_items, summary.total_items), (1, 4));
+    }
+
+    #[test]
+    fn load_ignores_missing_and_other_version_checkpoints() {
+        let work_dir = tempfile::tempdir().unwrap();
+        assert!(Checkpoint::load(work_dir.path()).unwrap().is_none());
+
+        let mut checkpoint = new_checkpoint();
+        checkpoint.format_version = CHECKPOINT_FORMAT_VERSION + 1;
+        checkpoint.save(work_dir.path()).unwrap();
+        assert!(Checkpoint::load(work_dir.path()).unwrap().is_none());
+
+        fs::write(work_dir.path().join(CHECKPOINT_FILE), b"{").unwrap();
+        assert!(Checkpoint::load(work_dir.path()).is_err());
+    }
+
+    #[test]
+    fn append_allocates_ids_after_the_largest_existing_id() {
+        let work_dir = tempfile::tempdir().unwrap();
+        let mut checkpoint = new_checkpoint();
+        checkpoint.append(vec![
+            work_item("a", TableWorkKind::Full),
+            work_item("b", TableWorkKind::Full),
+            work_item("c", TableWorkKind::Full),
+        ]);
+        let ids: Vec<usize> = checkpoint.items.iter().map(|item| item.id).collect();
+        assert_eq!(ids, [0, 1, 2]);
+
+        // 删除中间的表后新追加的工作单元不会复用已有的分片编号
+        checkpoint.reconcile(work_dir.path(), &["a".to_string(), "c".to_string()]);
+        checkpoint.append(vec![
+            work_item("d", TableWorkKind::Full),
+            work_item("e", TableWorkKind::Full),
+        ]);
+        let ids: Vec<usize> = checkpoint.items.iter().map(|item| item.id).collect();
+        assert_eq!(ids, [0, 2, 3, 4]);
+        assert!(checkpoint.items.iter().all(|item| !item.done));
+    }
+
+    #[test]
+    fn reconcile_drops_removed_tables_and_returns_new_ones() {
+        let work_dir = tempfile::tempdir().unwrap();
+        let mut checkpoint = new_checkpoint();
+        checkpoint.append(vec![
+            work_item("users", TableWorkKind::Full),
+            work_item("orders", TableWorkKind::Structure),
+            work_item("orders", chunk(None, Some(vec![mysql::Value::Int(100)]), 1)),
+            work_item("orders", chunk(Some(vec![mysql::Value::Int(100)]), None, 2)),
+        ]);
+        for item in &checkpoint.items {
+            fs::write(part_path(work_dir.path(), item.id), b"part").unwrap();
+        }
+
+        let tables = ["users".to_string(), "invoices".to_string()];
+        let new_tables = checkpoint.reconcile(work_dir.path(), &tables);
+        assert_eq!(new_tables, ["invoices"]);
+
+        // 已删除的表的所有分块连同分片文件一起删除
+        assert_eq!(checkpoint.items.len(), 1);
+        assert_eq!(checkpoint.items[0].work.table, "users");
+        assert!(part_path(work_dir.path(), 0).exists());
+        for id in 1..4 {
+            assert!(!part_path(work_dir.path(), id).exists());
+        }
+    }
+
+    #[test]
+    fn encrypted_work_dir_stays_out_of_backup_dir() {
+        let backup_dir = tempfile::tempdir().unwrap();
+        let backup_dir = backup_dir.path();
+
+        let shared = create_work_dir(backup_dir, "shop", false).unwrap();
+        assert_eq!(shared, backup_dir.join(".mysql_backup_resume_shop"));
+        assert_eq!(work_dir(backup_dir, "shop"), shared);
+        assert!(private_work_dir(backup_dir, "shop").is_none());
+
+        // 重新创建为私有工作目录时丢弃备份目录下的工作目录，只留下位置记录
+        let private = create_work_dir(backup_dir, "shop", true).unwrap();
+        assert!(!private.starts_with(backup_dir));
+        assert!(!shared.exists());
+        assert_eq!(work_dir(backup_dir, "shop"), private);
+        assert_eq!(private_work_dir(backup_dir, "shop"), Some(private.clone()));
+
+        discard(backup_dir, "shop").unwrap();
+        assert!(!private.exists());
+        assert!(private_work_dir(backup_dir, "shop").is_none());
+        assert_eq!(fs::read_dir(backup_dir).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,379 @@
+// 备份加密：用age格式把整个备份文件包起来，支持密码（scrypt）和X25519公钥两种方式
+use age::secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, Write};
+use std::iter;
+use std::path::Path;
+use tempfile::TempDir;
+
+// age文件头的开头，用于识别加密的备份文件
+const AGE_MAGIC: &[u8] = b"age-encryption.org/v1";
+
+// 加密备份文件名在原扩展名后追加的后缀
+pub const ENCRYPTED_SUFFIX: &str = ".age";
+
+// 备份时的加密选项，密码和公钥只能选择一种，都为空时不加密
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct EncryptionOptions {
+    // 用于加密的密码
+    pub passphrase: Option<String>,
+    // age格式的X25519公钥（age1...），任一对应私钥都可以解密
+    pub recipients: Vec<String>,
+}
+
+impl EncryptionOptions {
+    // 是否需要加密
+    pub fn is_enabled(&self) -> bool {
+        self.passphrase.as_deref().map_or(false, |p| !p.is_empty()) || !self.recipients.is_empty()
+    }
+
+    // 写入清单的加密方式，不包含任何密钥信息
+    pub fn scheme(&self) -> Option<&'static str> {
+        if !self.is_enabled() {
+            None
+        } else if self.recipients.is_empty() {
+            Some("age-scrypt")
+        } else {
+            Some("age-x25519")
+        }
+    }
+
+    // 检查选项并创建加密器
+    fn encryptor(&self) -> Result<age::Encryptor, String> {
+        let passphrase = self.passphrase.as_deref().filter(|p| !p.is_empty());
+        if passphrase.is_some() && !self.recipients.is_empty() {
+            return Err("加密时密码和公钥只能选择一种".to_string());
+        }
+
+        if let Some(passphrase) = passphrase {
+            return Ok(age::Encryptor::with_user_passphrase(Secret::new(
+                passphrase.to_string(),
+            )));
+        }
+
+        let mut recipients: Vec<Box<dyn age::Recipient + Send>> = Vec::new();
+        for recipient in &self.recipients {
+            let recipient = recipient
+                .trim()
+                .parse::<age::x25519::Recipient>()
+                .map_err(|e| format!("无效的公钥 {}: {}", recipient.trim(), e))?;
+            recipients.push(Box::new(recipient));
+        }
+        age::Encryptor::with_recipients(recipients)
+            .ok_or_else(|| "没有设置加密密码或公钥".to_string())
+    }
+
+    // 检查选项是否有效，在开始备份前调用，避免备份完成后才发现无法加密
+    pub fn validate(&self) -> Result<(), String> {
+        if self.is_enabled() {
+            self.encryptor()?;
+        }
+        Ok(())
+    }
+}
+
+// 恢复和校验时用于解密的密钥，加密的备份至少需要其中一种
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct DecryptionKey {
+    // 备份时使用的密码
+    pub passphrase: Option<String>,
+    // age格式的X25519私钥（AGE-SECRET-KEY-1...），可以是包含注释的私钥文件内容
+    pub identity: Option<String>,
+}
+
+// 备份文件的写入器，加密时写入age流，结束时必须调用finish
+pub enum ArchiveWriter {
+    Plain(File),
+    Encrypted(age::stream::StreamWriter<File>),
+}
+
+impl ArchiveWriter {
+    // 创建输出文件，需要加密时写入age文件头
+    pub fn create(path: &Path, encryption: &EncryptionOptions) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("创建备份文件失败: {}", e))?;
+        if !encryption.is_enabled() {
+            return Ok(ArchiveWriter::Plain(file));
+        }
+
+        let writer = encryption
+            .encryptor()?
+            .wrap_output(file)
+            .map_err(|e| format!("创建加密流失败: {}", e))?;
+        Ok(ArchiveWriter::Encrypted(writer))
+    }
+
+    // 写入加密流的最后一块并刷新文件
+    pub fn finish(self) -> Result<(), String> {
+        let mut file = match self {
+            ArchiveWriter::Plain(file) => file,
+            ArchiveWriter::Encrypted(writer) => writer
+                .finish()
+                .map_err(|e| format!("完成加密流失败: {}", e))?,
+        };
+        file.flush().map_err(|e| format!("写入备份文件失败: {}", e))
+    }
+}
+
+impl Write for ArchiveWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            ArchiveWriter::Plain(file) => file.write(buf),
+            ArchiveWriter::Encrypted(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            ArchiveWriter::Plain(file) => file.flush(),
+            ArchiveWriter::Encrypted(writer) => writer.flush(),
+        }
+    }
+}
+
+// 把已完成的明文备份文件加密写入输出文件
+pub fn encrypt_file(
+    plain_path: &Path,
+    output_path: &Path,
+    encryption: &EncryptionOptions,
+) -> Result<(), String> {
+    let mut input = File::open(plain_path).map_err(|e| format!("打开暂存文件失败: {}", e))?;
+    let mut writer = ArchiveWriter::create(output_path, encryption)?;
+    io::copy(&mut input, &mut writer).map_err(|e| format!("加密备份文件失败: {}", e))?;
+    writer.finish()
+}
+
+// 创建加密备份的明文暂存目录：放在系统临时目录而不是备份目录中，只有当前用户可以访问，
+// 返回的TempDir释放时删除目录
+pub fn private_staging_dir(prefix: &str) -> Result<TempDir, String> {
+    let dir = tempfile::Builder::new()
+        .prefix(prefix)
+        .tempdir()
+        .map_err(|e| format!("创建临时目录失败: {}", e))?;
+
+    // 目录权限受umask影响，写入任何文件前收紧为0700
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o700))
+            .map_err(|e| format!("设置临时目录权限失败: {}", e))?;
+    }
+
+    Ok(dir)
+}
+
+// 文件是否为age加密文件
+pub fn is_encrypted(path: &Path) -> Result<bool, String> {
+    let mut file = File::open(path).map_err(|e| format!("打开备份文件失败: {}", e))?;
+    let mut magic = [0u8; AGE_MAGIC.len()];
+    match file.read_exact(&mut magic) {
+        Ok(_) => Ok(magic == AGE_MAGIC),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(format!("读取备份文件失败: {}", e)),
+    }
+}
+
+// 去掉加密后缀后的文件名，用于根据扩展名判断格式
+pub fn strip_encrypted_suffix(file_name: &str) -> &str {
+    file_name
+        .strip_suffix(ENCRYPTED_SUFFIX)
+        .unwrap_or(file_name)
+}
+
+// 解密age流，返回的读取器支持定位，可以直接用于读取ZIP
+pub fn decrypt<R: Read + Seek>(
+    reader: R,
+    key: Option<&DecryptionKey>,
+) -> Result<age::stream::StreamReader<R>, String> {
+    let key = key.ok_or_else(|| "备份文件已加密，需要提供密码或私钥".to_string())?;
+    let decryptor =
+        age::Decryptor::new(reader).map_err(|e| format!("读取加密文件头失败: {}", e))?;
+
+    match decryptor {
+        age::Decryptor::Passphrase(decryptor) => {
+            let passphrase = key
+                .passphrase
+                .as_deref()
+                .filter(|p| !p.is_empty())
+                .ok_or_else(|| "备份文件使用密码加密，需要提供密码".to_string())?;
+            decryptor
+                .decrypt(&Secret::new(passphrase.to_string()), None)
+                .map_err(|e| format!("解密备份文件失败: {}", e))
+        }
+        age::Decryptor::Recipients(decryptor) => {
+            let identity = key
+                .identity
+                .as_deref()
+                .ok_or_else(|| "备份文件使用公钥加密，需要提供私钥".to_string())?;
+            let identity = parse_identity(identity)?;
+            decryptor
+                .decrypt(iter::once(&identity as &dyn age::Identity))
+                .map_err(|e| format!("解密备份文件失败: {}", e))
+        }
+    }
+}
+
+// 从私钥或私钥文件内容中解析第一条私钥，忽略注释和空行
+fn parse_identity(content: &str) -> Result<age::x25519::Identity, String> {
+    let line = content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .ok_or_else(|| "私钥为空".to_string())?;
+    line.parse::<age::x25519::Identity>()
+        .map_err(|e| format!("无效的私钥: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use age::secrecy::ExposeSecret;
+    use std::io::Cursor;
+
+    const CONTENT: &[u8] =
+        b"CREATE TABLE `orders` (`id` int);\nINSERT INTO `orders` VALUES (1),(2);\n";
+
+    // 用指定选项加密CONTENT，返回加密后的文件内容
+    fn encrypt(encryption: &EncryptionOptions) -> Vec<u8> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("BACKUP_test.zip.age");
+        let mut writer = ArchiveWriter::create(&path, encryption).unwrap();
+        writer.write_all(CONTENT).unwrap();
+        writer.finish().unwrap();
+
+        assert!(is_encrypted(&path).unwrap());
+        fs::read(&path).unwrap()
+    }
+
+    fn decrypt_all(encrypted: Vec<u8>, key: &DecryptionKey) -> Result<Vec<u8>, String> {
+        let mut reader = decrypt(Cursor::new(encrypted), Some(key))?;
+        let mut content = Vec::new();
+        reader
+            .read_to_end(&mut content)
+            .map_err(|e| e.to_string())?;
+        Ok(content)
+    }
+
+    #[test]
+    fn passphrase_round_trip_and_wrong_passphrase() {
+        let encrypted = encrypt(&EncryptionOptions {
+            passphrase: Some("correct horse".to_string()),
+            recipients: Vec::new(),
+        });
+
+        let key = |passphrase: &str| DecryptionKey {
+            passphrase: Some(passphrase.to_string()),
+            identity: None,
+        };
+        assert_eq!(
+            decrypt_all(encrypted.clone(), &key("correct horse")).unwrap(),
+            CONTENT
+        );
+        assert!(decrypt_all(encrypted.clone(), &key("battery staple"))
+            .unwrap_err()
+            .starts_with("解密备份文件失败"));
+        // 用密码加密的备份只能用密码解密
+        assert_eq!(
+            decrypt_all(
+                encrypted,
+                &DecryptionKey {
+                    passphrase: None,
+                    identity: Some(
+                        age::x25519::Identity::generate()
+                            .to_string()
+                            .expose_secret()
+                            .clone()
+                    ),
+                }
+            )
+            .unwrap_err(),
+            "备份文件使用密码加密，需要提供密码"
+        );
+    }
+
+    #[test]
+    fn x25519_round_trip_and_wrong_identity() {
+        let identity = age::x25519::Identity::generate();
+        let other = age::x25519::Identity::generate();
+        let encrypted = encrypt(&EncryptionOptions {
+            passphrase: None,
+            recipients: vec![
+                other.to_public().to_string(),
+                format!(" {} ", identity.to_public()),
+            ],
+        });
+
+        // 任一公钥对应的私钥都可以解密，私钥文件中的注释被忽略
+        let key = |identity: &age::x25519::Identity| DecryptionKey {
+            passphrase: None,
+            identity: Some(format!(
+                "# created: 2024-01-15\n# public key: {}\n{}\n",
+                identity.to_public(),
+                identity.to_string().expose_secret()
+            )),
+        };
+        assert_eq!(
+            decrypt_all(encrypted.clone(), &key(&identity)).unwrap(),
+            CONTENT
+        );
+        assert_eq!(
+            decrypt_all(encrypted.clone(), &key(&other)).unwrap(),
+            CONTENT
+        );
+
+        let stranger = age::x25519::Identity::generate();
+        assert!(decrypt_all(encrypted.clone(), &key(&stranger))
+            .unwrap_err()
+            .starts_with("解密备份文件失败"));
+        assert_eq!(
+            decrypt(Cursor::new(encrypted), None).err().unwrap(),
+            "备份文件已加密，需要提供密码或私钥"
+        );
+    }
+
+    #[test]
+    fn validate_rejects_invalid_options() {
+        assert!(EncryptionOptions::default().validate().is_ok());
+        assert!(EncryptionOptions {
+            passphrase: Some("secret".to_string()),
+            recipients: vec![age::x25519::Identity::generate().to_public().to_string()],
+        }
+        .validate()
+        .is_err());
+        assert!(EncryptionOptions {
+            passphrase: None,
+            recipients: vec!["age1invalid".to_string()],
+        }
+        .validate()
+        .unwrap_err()
+        .starts_with("无效的公钥 age1invalid"));
+    }
+
+    #[test]
+    fn plain_writer_is_not_encrypted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("BACKUP_test.zip");
+        let mut writer = ArchiveWriter::create(&path, &EncryptionOptions::default()).unwrap();
+        writer.write_all(CONTENT).unwrap();
+        writer.finish().unwrap();
+        assert!(!is_encrypted(&path).unwrap());
+        assert_eq!(fs::read(&path).unwrap(), CONTENT);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_staging_dir_is_owner_only_and_removed_on_drop() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = private_staging_dir(".mysql_backup_test_").unwrap();
+        let path = dir.path().to_path_buf();
+        assert!(path.starts_with(std::env::temp_dir()));
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        drop(dir);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,264 @@
+// 表过滤：按通配符或正则表达式选择要备份的表，并可以为单张表设置WHERE条件只备份部分行。
+// 过滤条件写入备份清单，恢复和校验时据此提示备份只包含部分数据
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// 以此开头的模式按正则表达式匹配表名中的任意位置，否则按通配符（*和?）匹配整个表名
+const REGEX_PREFIX: &str = "re:";
+
+// 表过滤条件，多数据库备份中模式和WHERE条件的表名可以写成“数据库.表”
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct TableFilter {
+    // 只备份匹配任一模式的表，为空时备份所有表
+    pub include: Vec<String>,
+    // 不备份匹配任一模式的表，优先于include
+    pub exclude: Vec<String>,
+    // 表名到WHERE条件的映射，只备份满足条件的行
+    pub where_clauses: BTreeMap<String, String>,
+}
+
+impl TableFilter {
+    pub fn is_empty(&self) -> bool {
+        self.include.iter().all(|p| p.trim().is_empty())
+            && self.exclude.iter().all(|p| p.trim().is_empty())
+            && !self.has_where_clauses()
+    }
+
+    pub fn has_where_clauses(&self) -> bool {
+        self.where_clauses.values().any(|w| !w.trim().is_empty())
+    }
+
+    // 检查模式是否有效
+    pub fn validate(&self) -> Result<(), String> {
+        self.compile().map(|_| ())
+    }
+
+    pub fn compile(&self) -> Result<CompiledFilter, String> {
+        Ok(CompiledFilter {
+            include: compile_patterns(&self.include)?,
+            exclude: compile_patterns(&self.exclude)?,
+        })
+    }
+
+    // 写入清单的过滤条件，没有过滤时为None
+    pub fn recorded(&self) -> Option<TableFilter> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.clone())
+        }
+    }
+
+    // 表的WHERE条件，“数据库.表”优先于只写表名
+    pub fn where_clause(&self, database: &str, table: &str) -> Option<&str> {
+        self.where_clauses
+            .get(&format!("{}.{}", database, table))
+            .or_else(|| self.where_clauses.get(table))
+            .map(|w| w.trim())
+            .filter(|w| !w.is_empty())
+    }
+
+    // 数据库中每张表的WHERE条件
+    pub fn where_clauses_for(&self, database: &str, tables: &[String]) -> BTreeMap<String, String> {
+        tables
+            .iter()
+            .filter_map(|table| {
+                self.where_clause(database, table)
+                    .map(|w| (table.clone(), w.to_string()))
+            })
+            .collect()
+    }
+
+    // 过滤条件的说明，用于提示备份只包含部分数据
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        let include: Vec<&str> = non_empty(&self.include);
+        if !include.is_empty() {
+            parts.push(format!("只包含 {}", include.join(", ")));
+        }
+        let exclude: Vec<&str> = non_empty(&self.exclude);
+        if !exclude.is_empty() {
+            parts.push(format!("排除 {}", exclude.join(", ")));
+        }
+        let tables: Vec<&str> = self
+            .where_clauses
+            .iter()
+            .filter(|(_, w)| !w.trim().is_empty())
+            .map(|(table, _)| table.as_str())
+            .collect();
+        if !tables.is_empty() {
+            parts.push(format!("{} 只包含满足WHERE条件的行", tables.join(", ")));
+        }
+        parts.join("；")
+    }
+}
+
+// 编译后的表名模式
+pub struct CompiledFilter {
+    include: Vec<TablePattern>,
+    exclude: Vec<TablePattern>,
+}
+
+struct TablePattern {
+    regex: Regex,
+    // 是否也与“数据库.表”比较：正则表达式和包含“.”的通配符。
+    // 不含“.”的通配符只比较表名，避免shop*匹配shop库中的所有表
+    qualified: bool,
+}
+
+impl CompiledFilter {
+    // 是否备份该表，模式可以匹配表名或“数据库.表”
+    pub fn matches(&self, database: &str, table: &str) -> bool {
+        let qualified = format!("{}.{}", database, table);
+        let matches_any = |patterns: &[TablePattern]| {
+            patterns
+                .iter()
+                .any(|p| p.regex.is_match(table) || (p.qualified && p.regex.is_match(&qualified)))
+        };
+        (self.include.is_empty() || matches_any(&self.include)) && !matches_any(&self.exclude)
+    }
+
+    // 保留需要备份的表，返回保留的表和被过滤掉的表
+    pub fn partition(&self, database: &str, tables: Vec<String>) -> (Vec<String>, Vec<String>) {
+        tables
+            .into_iter()
+            .partition(|table| self.matches(database, table))
+    }
+}
+
+fn non_empty(patterns: &[String]) -> Vec<&str> {
+    patterns
+        .iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+fn compile_patterns(patterns: &[String]) -> Result<Vec<TablePattern>, String> {
+    non_empty(patterns)
+        .into_iter()
+        .map(|pattern| {
+            Ok(TablePattern {
+                regex: compile_pattern(pattern)?,
+                qualified: pattern.starts_with(REGEX_PREFIX) || pattern.contains('.'),
+            })
+        })
+        .collect()
+}
+
+// 把通配符转换为匹配整个名称的正则表达式，re:开头的模式直接作为正则表达式
+fn compile_pattern(pattern: &str) -> Result<Regex, String> {
+    let source = match pattern.strip_prefix(REGEX_PREFIX) {
+        Some(regex) => regex.to_string(),
+        None => {
+            let mut source = String::from("^");
+            for c in pattern.chars() {
+                match c {
+                    '*' => source.push_str(".*"),
+                    '?' => source.push('.'),
+                    c => source.push_str(&regex::escape(&c.to_string())),
+                }
+            }
+            source.push('$');
+            source
+        }
+    };
+    Regex::new(&source).map_err(|e| format!("表过滤模式 {} 无效: {}", pattern, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> CompiledFilter {
+        TableFilter {
+            include: include.iter().map(|p| p.to_string()).collect(),
+            exclude: exclude.iter().map(|p| p.to_string()).collect(),
+            where_clauses: BTreeMap::new(),
+        }
+        .compile()
+        .unwrap()
+    }
+
+    #[test]
+    fn compile_pattern_matches_whole_name_with_wildcards() {
+        let pattern = compile_pattern("log_*").unwrap();
+        assert!(pattern.is_match("log_2024"));
+        assert!(pattern.is_match("log_"));
+        assert!(!pattern.is_match("old_log_2024"));
+
+        let pattern = compile_pattern("t?").unwrap();
+        assert!(pattern.is_match("t1"));
+        assert!(!pattern.is_match("t12"));
+    }
+
+    #[test]
+    fn compile_pattern_escapes_regex_characters_in_wildcards() {
+        let pattern = compile_pattern("a.b+c").unwrap();
+        assert!(pattern.is_match("a.b+c"));
+        assert!(!pattern.is_match("axbbc"));
+    }
+
+    #[test]
+    fn compile_pattern_uses_regex_after_prefix() {
+        let pattern = compile_pattern("re:_(tmp|bak)$").unwrap();
+        assert!(pattern.is_match("orders_tmp"));
+        assert!(!pattern.is_match("orders_tmp2"));
+
+        let error = compile_pattern("re:(").err().unwrap();
+        assert!(error.contains("re:("));
+    }
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        let filter = filter(&[], &["", "  "]);
+        assert!(filter.matches("shop", "orders"));
+    }
+
+    #[test]
+    fn exclude_wins_over_include() {
+        let filter = filter(&["order*"], &["*_archive"]);
+        assert!(filter.matches("shop", "orders"));
+        assert!(!filter.matches("shop", "orders_archive"));
+        assert!(!filter.matches("shop", "customers"));
+    }
+
+    #[test]
+    fn qualified_patterns_match_database_and_table() {
+        let filter = filter(&["shop.*"], &["shop.audit_?"]);
+        assert!(filter.matches("shop", "orders"));
+        assert!(!filter.matches("crm", "orders"));
+        assert!(!filter.matches("shop", "audit_1"));
+    }
+
+    #[test]
+    fn bare_wildcard_does_not_match_database_name() {
+        let filter = filter(&["shop*"], &[]);
+        assert!(filter.matches("crm", "shop_items"));
+        assert!(!filter.matches("shop", "orders"));
+    }
+
+    #[test]
+    fn regex_patterns_match_table_or_qualified_name() {
+        let filter = filter(&[], &["re:^crm\\."]);
+        assert!(!filter.matches("crm", "contacts"));
+        assert!(filter.matches("shop", "crm"));
+    }
+
+    #[test]
+    fn partition_splits_kept_and_filtered_tables() {
+        let filter = filter(&[], &["tmp_*"]);
+        let (kept, filtered) = filter.partition(
+            "shop",
+            vec![
+                "orders".to_string(),
+                "tmp_1".to_string(),
+                "items".to_string(),
+            ],
+        );
+        assert_eq!(kept, vec!["orders", "items"]);
+        assert_eq!(filtered, vec!["tmp_1"]);
+    }
+}
@@ -0,0 +1,429 @@
+// 备份任务登记：每个备份分配任务编号，记录状态、时间和结果，并按配置限制同时运行的任务数
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::Window;
+use tokio::sync::watch;
+
+// 保留的已结束任务数量，超出时删除最早结束的任务
+const FINISHED_JOBS_KEPT: usize = 100;
+
+// 任务状态
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    // 等待其他任务结束后开始
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(self) -> bool {
+        !matches!(self, JobStatus::Queued | JobStatus::Running)
+    }
+}
+
+// 返回给前端的任务信息
+#[derive(Serialize, Clone)]
+pub struct BackupJob {
+    pub id: u64,
+    pub host: String,
+    pub port: u16,
+    pub database: String,
+    pub output_path: String,
+    pub engine: Option<String>,
+    pub status: JobStatus,
+    // RFC 3339格式的提交、开始和结束时间
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    // 成功时为备份文件路径
+    pub result: Option<String>,
+    pub error: Option<String>,
+}
+
+impl BackupJob {
+    // 已结束任务的结果：成功时为备份文件路径，失败或取消时为错误信息
+    pub fn into_result(self) -> Result<String, String> {
+        match (self.result, self.error) {
+            (Some(path), _) => Ok(path),
+            (None, Some(error)) => Err(error),
+            (None, None) => Err(format!("备份任务 {} 没有结果", self.id)),
+        }
+    }
+}
+
+// 同时运行的任务数限制，0表示不限制
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct JobLimits {
+    // 所有服务器合计
+    pub max_total: usize,
+    // 同一服务器（主机和端口相同）
+    pub max_per_server: usize,
+}
+
+impl Default for JobLimits {
+    fn default() -> Self {
+        JobLimits {
+            max_total: 2,
+            max_per_server: 1,
+        }
+    }
+}
+
+// 带任务编号的窗口，备份进度事件通过它发送，前端据此区分不同任务的进度
+#[derive(Clone)]
+pub struct JobWindow {
+    pub window: Window,
+    pub job_id: u64,
+    // 进度映射到的区间，一次任务备份多个数据库时每个数据库占其中一段
+    progress: (u8, u8),
+    // 加在状态文字前的标签，如正在备份的数据库名
+    label: Option<String>,
+}
+
+impl JobWindow {
+    pub fn new(window: Window, job_id: u64) -> Self {
+        JobWindow {
+            window,
+            job_id,
+            progress: (0, 100),
+            label: None,
+        }
+    }
+
+    // 把进度限制在当前区间的[start, end]部分，并在状态前加上标签
+    pub fn scoped(&self, start: u8, end: u8, label: &str) -> Self {
+        JobWindow {
+            window: self.window.clone(),
+            job_id: self.job_id,
+            progress: (self.percent(start), self.percent(end)),
+            label: Some(label.to_string()),
+        }
+    }
+
+    // 映射到整个任务的进度
+    pub fn percent(&self, percent: u8) -> u8 {
+        let (start, end) = self.progress;
+        start + ((end - start) as u32 * percent.min(100) as u32 / 100) as u8
+    }
+
+    pub fn status(&self, status: &str) -> String {
+        match &self.label {
+            Some(label) => format!("[{}] {}", label, status),
+            None => status.to_string(),
+        }
+    }
+}
+
+struct JobEntry {
+    job: BackupJob,
+    // 用户请求取消任务的标记，传给备份流程
+    cancel_requested: Arc<AtomicBool>,
+    // 任务结束时发送最终的任务信息，供等待任务的命令使用，任务被清理后等待者仍能读到
+    finished: watch::Sender<Option<BackupJob>>,
+}
+
+impl JobEntry {
+    fn server(&self) -> (&str, u16) {
+        (&self.job.host, self.job.port)
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    limits: JobLimits,
+    jobs: BTreeMap<u64, JobEntry>,
+}
+
+impl Registry {
+    fn entry(&self, id: u64) -> Result<&JobEntry, String> {
+        self.jobs
+            .get(&id)
+            .ok_or_else(|| format!("备份任务 {} 不存在", id))
+    }
+
+    // 按提交顺序为排队的任务分配空位，判断指定任务现在能否开始
+    fn can_start(&self, id: u64) -> bool {
+        let mut total = 0;
+        let mut per_server: HashMap<(&str, u16), usize> = HashMap::new();
+        for entry in self.jobs.values() {
+            if entry.job.status == JobStatus::Running {
+                total += 1;
+                *per_server.entry(entry.server()).or_insert(0) += 1;
+            }
+        }
+
+        let limits = self.limits;
+        for (job_id, entry) in &self.jobs {
+            if entry.job.status != JobStatus::Queued
+                || entry.cancel_requested.load(Ordering::SeqCst)
+            {
+                continue;
+            }
+            let server_count = per_server.entry(entry.server()).or_insert(0);
+            let fits = (limits.max_total == 0 || total < limits.max_total)
+                && (limits.max_per_server == 0 || *server_count < limits.max_per_server);
+            if *job_id == id {
+                return fits;
+            }
+            // 排在前面的任务会先占用空位
+            if fits {
+                total += 1;
+                *server_count += 1;
+            }
+        }
+        false
+    }
+
+    // 删除超出保留数量的已结束任务
+    fn prune(&mut self) {
+        let finished: Vec<u64> = self
+            .jobs
+            .iter()
+            .filter(|(_, entry)| entry.job.status.is_finished())
+            .map(|(id, _)| *id)
+            .collect();
+        if finished.len() > FINISHED_JOBS_KEPT {
+            for id in &finished[..finished.len() - FINISHED_JOBS_KEPT] {
+                self.jobs.remove(id);
+            }
+        }
+    }
+}
+
+// 备份任务登记表，作为Tauri状态在所有窗口间共享
+#[derive(Default)]
+pub struct JobRegistry {
+    inner: Mutex<Registry>,
+    // 有任务结束、被取消或限制变化时唤醒排队的任务
+    changed: Condvar,
+}
+
+impl JobRegistry {
+    fn lock(&self) -> Result<MutexGuard<'_, Registry>, String> {
+        self.inner.lock().map_err(|e| e.to_string())
+    }
+
+    // 登记新任务，返回任务编号和取消标记
+    pub fn submit(
+        &self,
+        host: &str,
+        port: u16,
+        database: &str,
+        output_path: &str,
+        engine: Option<&str>,
+    ) -> Result<(u64, Arc<AtomicBool>), String> {
+        let mut registry = self.lock()?;
+        registry.next_id += 1;
+        let id = registry.next_id;
+        let cancel_requested = Arc::new(AtomicBool::new(false));
+        let (finished, _) = watch::channel(None);
+
+        registry.jobs.insert(
+            id,
+            JobEntry {
+                job: BackupJob {
+                    id,
+                    host: host.to_string(),
+                    port,
+                    database: database.to_string(),
+                    output_path: output_path.to_string(),
+                    engine: engine.map(|s| s.to_string()),
+                    status: JobStatus::Queued,
+                    created_at: chrono::Local::now().to_rfc3339(),
+                    started_at: None,
+                    finished_at: None,
+                    result: None,
+                    error: None,
+                },
+                cancel_requested: Arc::clone(&cancel_requested),
+                finished,
+            },
+        );
+        Ok((id, cancel_requested))
+    }
+
+    // 阻塞等待空位，可以开始时把任务标记为运行中。任务在排队时被取消则返回false
+    pub fn wait_for_slot(&self, id: u64) -> Result<bool, String> {
+        let mut registry = self.lock()?;
+        loop {
+            if registry.entry(id)?.cancel_requested.load(Ordering::SeqCst) {
+                return Ok(false);
+            }
+            if registry.can_start(id) {
+                if let Some(entry) = registry.jobs.get_mut(&id) {
+                    entry.job.status = JobStatus::Running;
+                    entry.job.started_at = Some(chrono::Local::now().to_rfc3339());
+                }
+                return Ok(true);
+            }
+            // 定时醒来检查取消标记
+            registry = self
+                .changed
+                .wait_timeout(registry, Duration::from_millis(500))
+                .map_err(|e| e.to_string())?
+                .0;
+        }
+    }
+
+    // 记录任务结果并唤醒排队和等待的任务
+    pub fn finish(&self, id: u64, result: &Result<String, String>, cancelled: bool) {
+        if let Ok(mut registry) = self.lock() {
+            if let Some(entry) = registry.jobs.get_mut(&id) {
+                entry.job.status = match result {
+                    Ok(_) => JobStatus::Succeeded,
+                    Err(_) if cancelled => JobStatus::Cancelled,
+                    Err(_) => JobStatus::Failed,
+                };
+                entry.job.finished_at = Some(chrono::Local::now().to_rfc3339());
+                entry.job.result = result.as_ref().ok().cloned();
+                entry.job.error = result.as_ref().err().cloned();
+                entry.finished.send_replace(Some(entry.job.clone()));
+            }
+            registry.prune();
+        }
+        self.changed.notify_all();
+    }
+
+    // 请求取消任务，job_id为None时取消所有未结束的任务，返回是否有任务被取消
+    pub fn cancel(&self, job_id: Option<u64>) -> Result<bool, String> {
+        let registry = self.lock()?;
+        let mut cancelled = false;
+        for (id, entry) in &registry.jobs {
+            if job_id.map_or(true, |job_id| job_id == *id) && !entry.job.status.is_finished() {
+                entry.cancel_requested.store(true, Ordering::SeqCst);
+                cancelled = true;
+            }
+        }
+        drop(registry);
+        self.changed.notify_all();
+        Ok(cancelled)
+    }
+
+    pub fn list(&self) -> Result<Vec<BackupJob>, String> {
+        Ok(self
+            .lock()?
+            .jobs
+            .values()
+            .map(|entry| entry.job.clone())
+            .collect())
+    }
+
+    pub fn get(&self, id: u64) -> Result<BackupJob, String> {
+        Ok(self.lock()?.entry(id)?.job.clone())
+    }
+
+    // 等待任务结束并返回任务信息。最终信息从通道中读取，不依赖任务是否还在登记表中
+    pub async fn wait(&self, id: u64) -> Result<BackupJob, String> {
+        let mut finished = self.lock()?.entry(id)?.finished.subscribe();
+        loop {
+            if let Some(job) = finished.borrow_and_update().clone() {
+                return Ok(job);
+            }
+            // 发送端在任务结束并发送结果后才会随任务被清理而关闭
+            if finished.changed().await.is_err() {
+                return finished
+                    .borrow()
+                    .clone()
+                    .ok_or_else(|| format!("备份任务 {} 没有结果", id));
+            }
+        }
+    }
+
+    pub fn limits(&self) -> Result<JobLimits, String> {
+        Ok(self.lock()?.limits)
+    }
+
+    pub fn set_limits(&self, limits: JobLimits) -> Result<(), String> {
+        self.lock()?.limits = limits;
+        self.changed.notify_all();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn submit(registry: &JobRegistry) -> u64 {
+        registry
+            .submit("127.0.0.1", 3306, "shop", "/backups/BACKUP_shop.zip", None)
+            .unwrap()
+            .0
+    }
+
+    #[tokio::test]
+    async fn wait_returns_result_after_job_is_pruned() {
+        let registry = Arc::new(JobRegistry::default());
+        let id = submit(&registry);
+        assert!(registry.wait_for_slot(id).unwrap());
+
+        let waiting = {
+            let registry = Arc::clone(&registry);
+            tokio::spawn(async move { registry.wait(id).await })
+        };
+        // 让等待的任务先订阅通道
+        tokio::task::yield_now().await;
+
+        registry.finish(id, &Ok("/backups/BACKUP_shop.zip".to_string()), false);
+        // 之后结束的任务把这个任务挤出保留范围
+        for _ in 0..FINISHED_JOBS_KEPT {
+            let other = submit(&registry);
+            registry.finish(other, &Err("连接数据库失败".to_string()), false);
+        }
+        assert!(registry.get(id).is_err());
+
+        let job = waiting.await.unwrap().unwrap();
+        assert_eq!(job.id, id);
+        assert!(job.status == JobStatus::Succeeded);
+        assert_eq!(job.into_result().unwrap(), "/backups/BACKUP_shop.zip");
+    }
+
+    #[tokio::test]
+    async fn wait_returns_immediately_for_finished_job() {
+        let registry = JobRegistry::default();
+        let id = submit(&registry);
+        registry.cancel(Some(id)).unwrap();
+        assert!(!registry.wait_for_slot(id).unwrap());
+        registry.finish(id, &Err("备份已取消".to_string()), true);
+
+        let job = registry.wait(id).await.unwrap();
+        assert!(job.status == JobStatus::Cancelled);
+        assert_eq!(job.into_result().unwrap_err(), "备份已取消");
+    }
+
+    #[test]
+    fn queued_jobs_start_in_order_within_limits() {
+        let registry = JobRegistry::default();
+        registry
+            .set_limits(JobLimits {
+                max_total: 2,
+                max_per_server: 1,
+            })
+            .unwrap();
+        let first = submit(&registry);
+        let second = submit(&registry);
+        let other_server = registry
+            .submit("10.0.0.2", 3306, "shop", "/backups/BACKUP_shop.zip", None)
+            .unwrap()
+            .0;
+
+        assert!(registry.wait_for_slot(first).unwrap());
+        {
+            let inner = registry.lock().unwrap();
+            // 同一服务器已有运行中的任务，排在后面的其他服务器任务可以先开始
+            assert!(!inner.can_start(second));
+            assert!(inner.can_start(other_server));
+        }
+        registry.finish(first, &Ok(String::new()), false);
+        assert!(registry.lock().unwrap().can_start(second));
+    }
+}
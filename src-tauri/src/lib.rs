@@ -1,52 +1,208 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 use hex;
-use mysql::{prelude::*, OptsBuilder, Pool, PooledConn};
-use serde::Serialize;
+use mysql::consts::ColumnType;
+use mysql::{prelude::*, OptsBuilder, Pool, PoolConstraints, PoolOpts, PooledConn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
 use std::fs;
 use std::fs::File;
-use std::io::Write;
+use std::io::{self, Read, Write};
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
-use std::path::Path;
-use std::process::Command;
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Duration;
 use tauri::command;
-use tauri::{Emitter, Manager, State, Window};
-use tempfile::TempDir;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
 use tokio::sync::mpsc;
 use zip::write::{FileOptions, ZipWriter};
+use zip::ZipArchive;
+
+mod archive;
+mod binlog;
+mod checkpoint;
+mod crypto;
+mod filter;
+mod jobs;
+mod manifest;
+mod pitr;
+mod profiles;
+mod restore;
+mod scheduler;
+mod secrets;
+mod server;
+mod verify;
 
 // 定义进度事件的数据结构
 #[derive(Serialize, Clone)]
 struct BackupProgress {
+    // 进度所属的备份任务编号，恢复进度不属于任何任务，为null
+    job_id: Option<u64>,
     percent: u8,
     status: String,
     current_table: Option<String>,
+    // 并行备份时报告进度的工作线程编号（从1开始）
+    worker: Option<usize>,
 }
 
-// 定义备份状态结构
-#[derive(Default)]
-struct BackupState {
-    is_running: Mutex<bool>,
+// 定义备份选项，前端未传入的字段使用默认值
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+struct BackupOptions {
+    // 在FLUSH TABLES WITH READ LOCK期间备份非事务表（如MyISAM），使其与快照一致
+    lock_non_transactional: bool,
+    // 内置引擎并行备份的工作线程数，0或1表示串行备份，最多MAX_WORKERS个
+    workers: usize,
+    // 大表按唯一键切分的每块行数，0表示不分块
+    chunk_rows: u64,
+    // 在快照内对每张表执行CHECKSUM TABLE并写入清单，供深度校验比较
+    record_checksums: bool,
+    // 备份文件格式
+    format: archive::ArchiveFormat,
+    // ZIP条目的压缩方式，其他格式固定使用格式本身的压缩算法
+    compression: archive::Codec,
+    // 压缩级别，为空时使用算法的默认级别
+    compression_level: Option<i32>,
+    // 加密选项，未设置密码或公钥时不加密
+    encryption: crypto::EncryptionOptions,
+    // 内置引擎的可续传备份：工作单元写入备份目录下的工作目录并记录检查点，中断后可以继续
+    resumable: bool,
+    // 存在同一数据库未完成的检查点时继续备份，否则丢弃检查点重新开始
+    resume: bool,
+    // 备份多个数据库或整个服务器，以及是否备份用户和授权
+    server: server::ServerBackupOptions,
+    // 按表名选择要备份的表，以及按表设置的WHERE条件
+    table_filter: filter::TableFilter,
+    // 完整备份、只备份表结构或只备份数据
+    mode: manifest::BackupMode,
+    // 记录快照对应的binlog位置和GTID集合，需要RELOAD和REPLICATION CLIENT权限
+    record_binlog_position: bool,
+    // 在内置引擎的备份中写入注释掉的CHANGE REPLICATION SOURCE TO语句
+    write_change_source: bool,
+}
+
+impl BackupOptions {
+    // 每个工作线程占用一个连接，限制线程数以免连接池和服务器连接数耗尽
+    fn validate_workers(&self) -> Result<(), String> {
+        if self.workers > MAX_WORKERS {
+            return Err(format!("并行线程数不能超过{}", MAX_WORKERS));
+        }
+        Ok(())
+    }
+
+    // 大表分块的行数，只备份表结构时不需要分块
+    fn chunk_rows(&self) -> u64 {
+        if self.mode.includes_data() {
+            self.chunk_rows
+        } else {
+            0
+        }
+    }
+
+    // 写入ZIP条目的选项，非ZIP格式先以不压缩的ZIP暂存，转换时再压缩
+    fn zip_file_options(&self) -> FileOptions {
+        match self.format {
+            archive::ArchiveFormat::Zip => {
+                archive::zip_file_options(self.compression, self.compression_level)
+            }
+            _ => archive::zip_file_options(archive::Codec::Store, None),
+        }
+    }
+}
+
+// binary字符集的编号，用于区分二进制列和文本列
+const BINARY_CHARSET: u16 = 63;
+
+// 可以通过utf8mb4连接无损读取的字符集
+const UTF8_CHARSETS: &[&str] = &["utf8mb4", "utf8mb3", "utf8", "ascii", "binary"];
+
+// 每个字符至少两个字节的字符集，其中的ASCII字符编码与ASCII不同
+const WIDE_CHARSETS: &[&str] = &["ucs2", "utf16", "utf16le", "utf32"];
+
+// 内置引擎并行备份的最大工作线程数
+const MAX_WORKERS: usize = 16;
+
+// 用户取消备份时返回的错误信息
+const BACKUP_CANCELLED: &str = "备份已取消";
+
+// 内置引擎归档的最后一个条目，恢复文件头修改的会话设置
+const DUMP_FOOTER_ENTRY: &str = "99_session_reset.sql";
+
+// 内置引擎归档的第一个条目，包含文件头和建库语句
+const DATABASE_INFO_ENTRY: &str = "00_database_info.sql";
+
+// mysqldump引擎归档中唯一的SQL条目
+const MYSQLDUMP_ENTRY: &str = "mysqldump_backup.sql";
+
+// 导出表数据时需要的会话信息，并行备份时复制给每个工作线程
+#[derive(Clone)]
+struct DumpContext {
+    // 备份会话启用了NO_BACKSLASH_ESCAPES，字符串中的单引号只能加倍转义
+    no_backslash_escapes: bool,
+    // 备份表结构的同时记录CHECKSUM TABLE结果
+    record_checksums: bool,
+    // 只备份部分行的表及其WHERE条件
+    where_clauses: BTreeMap<String, String>,
+    // 完整备份、只备份表结构或只备份数据
+    mode: manifest::BackupMode,
+    // 用户请求取消备份的标记
+    cancel_requested: Arc<AtomicBool>,
+}
+
+impl DumpContext {
+    // 用户已请求取消时返回取消错误
+    fn check_cancelled(&self) -> Result<(), String> {
+        check_backup_cancelled(&self.cancel_requested)
+    }
+}
+
+// 用户已请求取消时返回取消错误
+fn check_backup_cancelled(cancel_requested: &AtomicBool) -> Result<(), String> {
+    if cancel_requested.load(Ordering::SeqCst) {
+        Err(BACKUP_CANCELLED.to_string())
+    } else {
+        Ok(())
+    }
 }
 
 // 发送进度更新事件
-fn send_progress_update(window: &Window, percent: u8, status: &str, current_table: Option<&str>) {
+fn send_progress_update(
+    window: &jobs::JobWindow,
+    percent: u8,
+    status: &str,
+    current_table: Option<&str>,
+) {
+    send_worker_progress_update(window, percent, status, current_table, None);
+}
+
+// 发送带工作线程编号的进度更新事件
+fn send_worker_progress_update(
+    window: &jobs::JobWindow,
+    percent: u8,
+    status: &str,
+    current_table: Option<&str>,
+    worker: Option<usize>,
+) {
     let progress = BackupProgress {
-        percent,
-        status: status.to_string(),
+        job_id: Some(window.job_id),
+        percent: window.percent(percent),
+        status: window.status(status),
         current_table: current_table.map(|s| s.to_string()),
+        worker,
     };
 
     window
+        .window
         .emit("backup-progress", progress)
         .unwrap_or_else(|e| {
             eprintln!("发送进度更新事件失败: {}", e);
         });
 }
 
-// 修改备份命令为异步命令
+// 执行备份并等待完成，返回备份文件路径
 #[command]
 async fn backup_mysql(
     window: Window,
@@ -57,27 +213,126 @@ async fn backup_mysql(
     database: &str,
     output_path: &str,
     engine: Option<&str>,
-    backup_state: State<'_, BackupState>,
+    options: Option<BackupOptions>,
+    job_registry: State<'_, jobs::JobRegistry>,
 ) -> Result<String, String> {
-    // 检查是否已经有备份在运行
-    {
-        let is_running = backup_state.is_running.lock().map_err(|e| e.to_string())?;
-        if *is_running {
-            return Err("已有备份任务正在运行".to_string());
-        }
-    }
+    let job_id = spawn_backup_job(
+        window,
+        host,
+        port,
+        username,
+        password,
+        database,
+        output_path,
+        engine,
+        options,
+    )?;
+    job_registry.wait(job_id).await?.into_result()
+}
+
+// 提交备份任务，不等待完成，返回任务编号
+#[command]
+fn start_backup(
+    window: Window,
+    host: &str,
+    port: u16,
+    username: &str,
+    password: &str,
+    database: &str,
+    output_path: &str,
+    engine: Option<&str>,
+    options: Option<BackupOptions>,
+) -> Result<u64, String> {
+    spawn_backup_job(
+        window,
+        host,
+        port,
+        username,
+        password,
+        database,
+        output_path,
+        engine,
+        options,
+    )
+}
+
+// 等待备份任务结束，返回备份文件路径
+#[command]
+async fn wait_backup_job(
+    job_id: u64,
+    job_registry: State<'_, jobs::JobRegistry>,
+) -> Result<String, String> {
+    job_registry.wait(job_id).await?.into_result()
+}
+
+// 列出正在运行、排队和最近结束的备份任务
+#[command]
+fn list_backup_jobs(
+    job_registry: State<'_, jobs::JobRegistry>,
+) -> Result<Vec<jobs::BackupJob>, String> {
+    job_registry.list()
+}
+
+// 查询备份任务
+#[command]
+fn get_backup_job(
+    job_id: u64,
+    job_registry: State<'_, jobs::JobRegistry>,
+) -> Result<jobs::BackupJob, String> {
+    job_registry.get(job_id)
+}
+
+// 读取同时运行的备份任务数限制
+#[command]
+fn get_backup_job_limits(
+    job_registry: State<'_, jobs::JobRegistry>,
+) -> Result<jobs::JobLimits, String> {
+    job_registry.limits()
+}
+
+// 设置同时运行的备份任务数限制，排队的任务按新限制开始
+#[command]
+fn set_backup_job_limits(
+    limits: jobs::JobLimits,
+    job_registry: State<'_, jobs::JobRegistry>,
+) -> Result<(), String> {
+    job_registry.set_limits(limits)
+}
+
+// 登记备份任务并在新线程中执行，返回任务编号。任务超出同时运行的限制时先排队
+fn spawn_backup_job(
+    window: Window,
+    host: &str,
+    port: u16,
+    username: &str,
+    password: &str,
+    database: &str,
+    output_path: &str,
+    engine: Option<&str>,
+    options: Option<BackupOptions>,
+) -> Result<u64, String> {
+    let options = options.unwrap_or_default();
+    archive::validate_level(options.format, options.compression_level)
+        .and_then(|_| options.encryption.validate())
+        .and_then(|_| options.table_filter.validate())
+        .and_then(|_| options.validate_workers())?;
+
+    // 多数据库备份在任务列表中显示选择的数据库
+    let label = if options.server.is_enabled() {
+        options.server.label()
+    } else {
+        database.to_string()
+    };
+    let (job_id, cancel_requested) =
+        window
+            .state::<jobs::JobRegistry>()
+            .submit(host, port, &label, output_path, engine)?;
+    let window = jobs::JobWindow::new(window, job_id);
 
     // 首先发送开始事件
     send_progress_update(&window, 0, "正在准备备份...", None);
 
-    // 标记备份已开始
-    {
-        let mut is_running = backup_state.is_running.lock().map_err(|e| e.to_string())?;
-        *is_running = true;
-    }
-
     // 克隆需要的数据以便在线程中使用
-    let window_clone = window.clone();
     let host = host.to_string();
     let username = username.to_string();
     let password = password.to_string();
@@ -85,96 +340,257 @@ async fn backup_mysql(
     let output_path = output_path.to_string();
     let engine = engine.map(|s| s.to_string());
 
-    // 创建通道用于接收结果
-    let (tx, mut rx) = mpsc::channel::<Result<String, String>>(1);
-
     // 创建一个新线程来处理备份
     let _ = thread::spawn(move || {
-        let backup_result = match engine.as_deref() {
-            // 如果明确指定使用mysqldump
-            Some("mysqldump") => {
-                if is_mysqldump_available() {
-                    backup_with_mysqldump(
-                        window_clone.clone(),
-                        &host,
-                        port,
-                        &username,
-                        &password,
-                        &database,
-                        &output_path,
-                    )
-                } else {
-                    // 如果指定了mysqldump但它不可用，返回错误
-                    Err("指定使用mysqldump但系统中没有可用的mysqldump命令".to_string())
-                }
-            }
-            // 如果明确指定使用内置引擎
-            Some("builtin") => backup_with_rust_mysql(
-                window_clone.clone(),
+        let registry = window.window.state::<jobs::JobRegistry>();
+        let backup_result = match registry.wait_for_slot(job_id) {
+            Ok(true) => run_backup(
+                &window,
                 &host,
                 port,
                 &username,
                 &password,
                 &database,
                 &output_path,
+                engine.as_deref(),
+                &options,
+                &cancel_requested,
             ),
-            // 如果没有指定或指定了其他值，使用自动选择逻辑
-            _ => {
-                if is_mysqldump_available() {
-                    backup_with_mysqldump(
-                        window_clone.clone(),
-                        &host,
-                        port,
-                        &username,
-                        &password,
-                        &database,
-                        &output_path,
-                    )
-                } else {
-                    backup_with_rust_mysql(
-                        window_clone.clone(),
-                        &host,
-                        port,
-                        &username,
-                        &password,
-                        &database,
-                        &output_path,
-                    )
-                }
-            }
+            Ok(false) => Err(BACKUP_CANCELLED.to_string()),
+            Err(e) => Err(e),
         };
 
-        // 无论成功或失败，确保发送结果
-        if let Err(e) = tx.blocking_send(backup_result) {
-            eprintln!("无法发送备份结果: {}", e);
+        let cancelled = matches!(&backup_result, Err(e) if e == BACKUP_CANCELLED);
+        if cancelled {
+            send_progress_update(&window, 0, BACKUP_CANCELLED, None);
         }
+
+        // 无论成功或失败，确保记录结果
+        registry.finish(job_id, &backup_result, cancelled);
     });
 
-    // 等待结果
-    let result = match rx.recv().await {
-        Some(r) => r,
-        None => Err("备份过程意外终止".to_string()),
-    };
+    Ok(job_id)
+}
 
-    // 释放备份中标记
-    {
-        let mut is_running = backup_state.is_running.lock().map_err(|e| e.to_string())?;
-        *is_running = false;
+// 确定实际使用的备份引擎：未指定时有mysqldump则使用mysqldump，否则使用内置引擎。
+// mysqldump不支持按表设置WHERE条件，设置了WHERE条件时自动选择内置引擎
+fn resolve_engine(engine: Option<&str>, options: &BackupOptions) -> Result<&'static str, String> {
+    match engine {
+        Some("mysqldump") => {
+            if is_mysqldump_available() {
+                Ok("mysqldump")
+            } else {
+                // 如果指定了mysqldump但它不可用，返回错误
+                Err("指定使用mysqldump但系统中没有可用的mysqldump命令".to_string())
+            }
+        }
+        Some("builtin") => Ok("builtin"),
+        _ => {
+            if is_mysqldump_available() && !options.table_filter.has_where_clauses() {
+                Ok("mysqldump")
+            } else {
+                Ok("builtin")
+            }
+        }
+    }
+}
+
+// 按选择的引擎执行备份，选择了多个数据库时逐个备份
+fn run_backup(
+    window: &jobs::JobWindow,
+    host: &str,
+    port: u16,
+    username: &str,
+    password: &str,
+    database: &str,
+    output_path: &str,
+    engine: Option<&str>,
+    options: &BackupOptions,
+    cancel_requested: &Arc<AtomicBool>,
+) -> Result<String, String> {
+    let engine = resolve_engine(engine, options)?;
+    if options.server.is_enabled() {
+        return server::backup_server(
+            window,
+            host,
+            port,
+            username,
+            password,
+            output_path,
+            engine,
+            options,
+            cancel_requested,
+        );
+    }
+
+    if engine == "mysqldump" {
+        backup_with_mysqldump(
+            window.clone(),
+            host,
+            port,
+            username,
+            password,
+            database,
+            output_path,
+            options,
+            cancel_requested,
+        )
+    } else {
+        backup_with_rust_mysql(
+            window.clone(),
+            host,
+            port,
+            username,
+            password,
+            database,
+            output_path,
+            options,
+            cancel_requested,
+        )
     }
+}
+
+// 请求取消备份任务，不指定任务编号时取消所有未结束的任务，返回是否有任务被取消
+#[command]
+fn cancel_backup(job_id: Option<u64>, job_registry: State<'_, jobs::JobRegistry>) -> bool {
+    job_registry.cancel(job_id).unwrap_or(false)
+}
+
+// 查找备份目录中同一数据库未完成的可续传备份，前端据此询问是否继续
+#[command]
+fn find_resumable_backup(
+    backup_dir: &str,
+    host: &str,
+    port: u16,
+    database: &str,
+) -> Result<Option<checkpoint::ResumableBackup>, String> {
+    checkpoint::find_resumable(Path::new(backup_dir), host, port, database)
+}
+
+// 读取定时备份设置和下次备份时间
+#[command]
+fn get_backup_schedule(
+    scheduler: State<'_, scheduler::Scheduler>,
+) -> Result<scheduler::ScheduleStatus, String> {
+    scheduler.status()
+}
+
+// 更新定时备份设置，从现在开始重新计算下次备份时间
+#[command]
+fn set_backup_schedule(
+    config: scheduler::ScheduleConfig,
+    scheduler: State<'_, scheduler::Scheduler>,
+) -> Result<scheduler::ScheduleStatus, String> {
+    scheduler.configure(config)
+}
+
+// 列出连接配置
+#[command]
+fn list_connection_profiles(
+    profiles: State<'_, profiles::ProfileStore>,
+) -> Result<Vec<profiles::ConnectionProfile>, String> {
+    profiles.profiles()
+}
+
+// 新建（id为0）或修改连接配置，使用该配置的备份作业按新的连接信息运行
+#[command]
+fn save_connection_profile(
+    profile: profiles::ConnectionProfile,
+    profiles: State<'_, profiles::ProfileStore>,
+    scheduler: State<'_, scheduler::Scheduler>,
+) -> Result<profiles::ConnectionProfile, String> {
+    let profile = profiles.save_profile(profile)?;
+    scheduler.sync_saved_jobs(profiles.schedules()?)?;
+    Ok(profile)
+}
+
+// 删除没有被备份作业使用的连接配置
+#[command]
+fn delete_connection_profile(
+    id: u64,
+    profiles: State<'_, profiles::ProfileStore>,
+) -> Result<(), String> {
+    profiles.delete_profile(id)
+}
+
+// 列出备份作业
+#[command]
+fn list_saved_jobs(
+    profiles: State<'_, profiles::ProfileStore>,
+) -> Result<Vec<profiles::SavedJob>, String> {
+    profiles.jobs()
+}
+
+// 新建备份作业
+#[command]
+fn create_saved_job(
+    job: profiles::SavedJob,
+    profiles: State<'_, profiles::ProfileStore>,
+    scheduler: State<'_, scheduler::Scheduler>,
+) -> Result<profiles::SavedJob, String> {
+    let job = profiles.create_job(job)?;
+    scheduler.sync_saved_jobs(profiles.schedules()?)?;
+    Ok(job)
+}
+
+// 修改备份作业
+#[command]
+fn update_saved_job(
+    job: profiles::SavedJob,
+    profiles: State<'_, profiles::ProfileStore>,
+    scheduler: State<'_, scheduler::Scheduler>,
+) -> Result<profiles::SavedJob, String> {
+    let job = profiles.update_job(job)?;
+    scheduler.sync_saved_jobs(profiles.schedules()?)?;
+    Ok(job)
+}
+
+// 删除备份作业，已经在运行的备份任务不受影响
+#[command]
+fn delete_saved_job(
+    id: u64,
+    profiles: State<'_, profiles::ProfileStore>,
+    scheduler: State<'_, scheduler::Scheduler>,
+) -> Result<(), String> {
+    profiles.delete_job(id)?;
+    scheduler.sync_saved_jobs(profiles.schedules()?)
+}
+
+// 立即运行备份作业，为每个数据库提交一个备份任务，返回任务编号
+#[command]
+fn run_saved_job(
+    app: AppHandle,
+    id: u64,
+    profiles: State<'_, profiles::ProfileStore>,
+) -> Result<Vec<u64>, String> {
+    scheduler::run_saved_job(&app, id, profiles.job_targets(id)?)
+}
 
-    result
+// 预览cron表达式接下来的备份时间
+#[command]
+fn preview_backup_schedule(
+    cron: &str,
+    timezone: &str,
+    count: Option<usize>,
+) -> Result<Vec<String>, String> {
+    scheduler::preview(cron, timezone, count.unwrap_or(5))
 }
 
 // 检查系统中是否有mysqldump可用
 fn is_mysqldump_available() -> bool {
-    // 根据操作系统不同，执行不同的命令检查mysqldump是否可用
+    is_command_available("mysqldump")
+}
+
+// 检查系统PATH中是否有指定的命令行工具
+fn is_command_available(command: &str) -> bool {
+    // 根据操作系统不同，执行不同的命令检查工具是否可用
     #[cfg(target_os = "windows")]
     {
-        // Windows下检查mysqldump.exe是否存在于PATH中
+        // Windows下检查可执行文件是否存在于PATH中
         // 使用.creation_flags(0x08000000)来隐藏窗口
         // 0x08000000是CREATE_NO_WINDOW标志，防止显示命令行窗口
         let result = Command::new("where")
-            .arg("mysqldump")
+            .arg(command)
             .creation_flags(0x08000000)
             .output();
         match result {
@@ -186,7 +602,7 @@ fn is_mysqldump_available() -> bool {
     #[cfg(not(target_os = "windows"))]
     {
         // Linux/macOS下使用which命令检查
-        let result = Command::new("which").arg("mysqldump").output();
+        let result = Command::new("which").arg(command).output();
         match result {
             Ok(output) => output.status.success(),
             Err(_) => false,
@@ -196,13 +612,15 @@ fn is_mysqldump_available() -> bool {
 
 // 使用系统中的mysqldump命令进行备份
 fn backup_with_mysqldump(
-    window: Window,
+    window: jobs::JobWindow,
     host: &str,
     port: u16,
     username: &str,
     password: &str,
     database: &str,
     output_path: &str,
+    options: &BackupOptions,
+    cancel_requested: &Arc<AtomicBool>,
 ) -> Result<String, String> {
     // 确保输出目录存在
     if let Some(parent) = Path::new(output_path).parent() {
@@ -214,18 +632,104 @@ fn backup_with_mysqldump(
     }
 
     send_progress_update(&window, 5, "准备使用系统mysqldump工具备份...", None);
+    let started_at = chrono::Local::now().to_rfc3339();
 
-    // 创建临时目录用于存放mysqldump生成的SQL文件
-    let temp_dir = match TempDir::new() {
-        Ok(dir) => dir,
-        Err(e) => return Err(format!("创建临时目录失败: {}", e)),
+    // 创建ZIP文件，mysqldump的输出直接压缩写入，不再落地为临时SQL文件
+    let (zip_path, _staging_dir) = staging_zip_path(Path::new(output_path), options)?;
+    let zip_file = match File::create(&zip_path) {
+        Ok(file) => file,
+        Err(e) => return Err(format!("创建ZIP文件失败: {}", e)),
     };
+    let mut zip = ZipWriter::new(zip_file);
 
-    // 获取临时SQL文件路径
-    let sql_file_path = temp_dir.path().join("full_backup.sql");
-    let sql_file_path_str = sql_file_path.to_string_lossy().to_string();
+    let result = write_mysqldump_backup(
+        &window,
+        &mut zip,
+        host,
+        port,
+        username,
+        password,
+        database,
+        options,
+        cancel_requested,
+    )
+    .and_then(|server_info| {
+        send_progress_update(&window, 90, "正在完成ZIP文件...", None);
+        zip.finish()
+            .map_err(|e| format!("完成ZIP文件失败: {}", e))?;
+
+        check_backup_cancelled(cancel_requested)?;
+        send_progress_update(&window, 95, "正在生成备份清单...", None);
+        manifest::append_manifest(
+            &zip_path,
+            "mysqldump",
+            database,
+            server_info,
+            started_at,
+            options.encryption.scheme(),
+        )?;
+
+        check_backup_cancelled(cancel_requested)?;
+        convert_staging_zip(&window, &zip_path, Path::new(output_path), options)
+    });
 
-    send_progress_update(&window, 10, "连接数据库...", None);
+    // 备份失败时删除不完整的备份文件
+    if let Err(e) = result {
+        drop(zip);
+        remove_incomplete_backup(&zip_path, Path::new(output_path));
+        return Err(e);
+    }
+
+    send_progress_update(&window, 100, "备份完成", None);
+    Ok(output_path.to_string())
+}
+
+// 执行mysqldump并把标准输出流式写入ZIP条目，返回写入清单所需的服务器信息
+fn write_mysqldump_backup(
+    window: &jobs::JobWindow,
+    zip: &mut ZipWriter<File>,
+    host: &str,
+    port: u16,
+    username: &str,
+    password: &str,
+    database: &str,
+    options: &BackupOptions,
+    cancel_requested: &Arc<AtomicBool>,
+) -> Result<manifest::ServerInfo, String> {
+    let table_filter = &options.table_filter;
+    // mysqldump的--where对所有表生效，无法按表设置
+    if table_filter.has_where_clauses() {
+        return Err("mysqldump不支持按表设置WHERE条件，请使用内置引擎".to_string());
+    }
+
+    send_progress_update(window, 10, "连接数据库...", None);
+
+    // 读取服务器版本、字符集和表列表，写入备份清单
+    let opts = OptsBuilder::new()
+        .ip_or_hostname(Some(host))
+        .tcp_port(port)
+        .user(Some(username))
+        .pass(Some(password))
+        .db_name(Some(database));
+    let mut conn = mysql::Conn::new(opts).map_err(|e| format!("连接数据库失败: {}", e))?;
+    let mut server_info = manifest::read_server_info(&mut conn, database)?;
+    // 服务器未启用binlog时mysqldump无法记录位置并会报错
+    let record_binlog_position =
+        options.record_binlog_position && binlog::binlog_enabled(&mut conn)?;
+    if options.record_binlog_position && !record_binlog_position {
+        send_progress_update(window, 10, "服务器未启用binlog，不记录binlog位置", None);
+    }
+    drop(conn);
+
+    // 过滤掉的表和视图通过--ignore-table排除
+    let compiled_filter = table_filter.compile()?;
+    let (tables, mut ignored) = compiled_filter.partition(database, server_info.tables);
+    let (views, ignored_views) = compiled_filter.partition(database, server_info.views);
+    ignored.extend(ignored_views);
+    server_info.tables = tables;
+    server_info.views = views;
+    server_info.table_filter = table_filter.recorded();
+    server_info.mode = options.mode;
 
     // 构建 mysqldump 命令
     let mut cmd = Command::new("mysqldump");
@@ -243,82 +747,201 @@ fn backup_with_mysqldump(
         cmd.arg(format!("--password={}", password));
     }
 
+    // 只备份数据时不导出建库建表语句和触发器，只备份结构时不导出行
+    if options.mode.includes_schema() {
+        cmd.arg("--add-drop-database")
+            .arg("--add-drop-table")
+            .arg("--triggers")
+            .arg("--routines")
+            .arg("--events");
+    } else {
+        cmd.arg("--no-create-db")
+            .arg("--no-create-info")
+            .arg("--skip-triggers");
+    }
+    if !options.mode.includes_data() {
+        cmd.arg("--no-data");
+    }
+
+    // binlog位置以注释的形式写在导出文件开头，同时记录在清单中
+    if record_binlog_position {
+        cmd.arg(binlog::mysqldump_source_data_arg());
+    }
+
     // 添加其他有用的参数
-    cmd.arg("--add-drop-database")
-        .arg("--add-drop-table")
-        .arg("--triggers")
-        .arg("--routines")
-        .arg("--events")
-        .arg("--single-transaction")
-        .arg("--databases")
+    cmd.arg("--single-transaction")
+        // 二进制列以十六进制导出，恢复时内置执行器也能处理
+        .arg("--hex-blob");
+    for table in &ignored {
+        cmd.arg(format!("--ignore-table={}.{}", database, table));
+    }
+    cmd.arg("--databases")
         .arg(database)
-        .arg("--result-file")
-        .arg(&sql_file_path_str);
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
 
-    send_progress_update(&window, 20, "正在使用mysqldump导出数据库...", None);
+    // 添加SQL文件到ZIP
+    if let Err(e) = zip.start_file(MYSQLDUMP_ENTRY, options.zip_file_options()) {
+        return Err(format!("添加备份文件到ZIP失败: {}", e));
+    }
+
+    send_progress_update(window, 20, "正在使用mysqldump导出并压缩数据库...", None);
 
     // 执行命令
-    match cmd.output() {
-        Ok(output) => {
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                return Err(format!("备份失败: {}", stderr));
-            }
-        }
-        Err(e) => {
-            return Err(format!("执行mysqldump命令失败: {}", e));
-        }
-    }
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => return Err(format!("执行mysqldump命令失败: {}", e)),
+    };
 
-    send_progress_update(&window, 60, "导出完成，正在创建ZIP文件...", None);
+    // 在单独的线程中读取错误输出，避免管道写满导致mysqldump阻塞
+    let stderr = child.stderr.take();
+    let stdout = child.stdout.take();
+    let stderr_reader = thread::spawn(move || {
+        let mut message = String::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_string(&mut message);
+        }
+        message
+    });
 
-    // 创建ZIP文件
-    let zip_file = match File::create(output_path) {
-        Ok(file) => file,
-        Err(e) => return Err(format!("创建ZIP文件失败: {}", e)),
+    // mysqldump可能长时间没有输出，由单独的线程检查取消标记并结束进程
+    let child = Arc::new(Mutex::new(child));
+    let copy_finished = Arc::new(AtomicBool::new(false));
+    let cancel_watcher = {
+        let child = Arc::clone(&child);
+        let copy_finished = Arc::clone(&copy_finished);
+        let cancel_requested = Arc::clone(cancel_requested);
+        thread::spawn(move || {
+            while !copy_finished.load(Ordering::SeqCst) {
+                if cancel_requested.load(Ordering::SeqCst) {
+                    if let Ok(mut child) = child.lock() {
+                        let _ = child.kill();
+                    }
+                    break;
+                }
+                thread::sleep(Duration::from_millis(200));
+            }
+        })
     };
 
-    let mut zip = ZipWriter::new(zip_file);
-    let options = FileOptions::default()
-        .compression_method(zip::CompressionMethod::Deflated)
-        .unix_permissions(0o755);
+    // 保留输出的开头部分，从中解析binlog位置；写入时同时计算校验值并统计行数
+    let mut output = binlog::HeaderCapture::new(manifest::HashingWriter::new(&mut *zip));
+    let copy_result = match stdout {
+        Some(stdout) => {
+            manifest::copy_counting_rows(stdout, &mut output, &mut server_info.row_counts)
+        }
+        None => Err(io::Error::new(
+            io::ErrorKind::Other,
+            "无法读取mysqldump输出",
+        )),
+    };
+    copy_finished.store(true, Ordering::SeqCst);
+    let _ = cancel_watcher.join();
 
-    send_progress_update(&window, 70, "正在压缩备份数据...", None);
+    let mut child = child
+        .lock()
+        .map_err(|_| "等待mysqldump结束失败".to_string())?;
 
-    // 添加SQL文件到ZIP
-    if let Err(e) = zip.start_file("mysqldump_backup.sql", options) {
-        return Err(format!("添加备份文件到ZIP失败: {}", e));
+    // 写入失败时mysqldump可能阻塞在输出上，需要先结束进程
+    if copy_result.is_err() {
+        let _ = child.kill();
     }
 
-    let sql_content = match fs::read(&sql_file_path) {
-        Ok(content) => content,
-        Err(e) => return Err(format!("读取备份文件失败: {}", e)),
-    };
+    let status = child
+        .wait()
+        .map_err(|e| format!("等待mysqldump结束失败: {}", e))?;
+    let stderr_message = stderr_reader.join().unwrap_or_default();
 
-    if let Err(e) = zip.write_all(&sql_content) {
+    check_backup_cancelled(cancel_requested)?;
+    if !status.success() {
+        return Err(format!("备份失败: {}", stderr_message));
+    }
+    if let Err(e) = copy_result {
         return Err(format!("写入备份数据到ZIP失败: {}", e));
     }
 
-    send_progress_update(&window, 90, "正在完成ZIP文件...", None);
+    if record_binlog_position {
+        server_info.binlog = binlog::parse_dump_header(output.header());
+        if server_info.binlog.is_none() {
+            send_progress_update(window, 90, "警告：mysqldump的输出中没有binlog位置", None);
+        }
+    }
+    server_info
+        .entries
+        .push(output.into_inner().into_entry(MYSQLDUMP_ENTRY));
 
-    // 完成ZIP文件
-    if let Err(e) = zip.finish() {
-        return Err(format!("完成ZIP文件失败: {}", e));
+    Ok(server_info)
+}
+
+// 备份引擎写入的ZIP路径，非ZIP格式先写入输出文件旁边的暂存ZIP。需要加密时暂存ZIP是明文，
+// 放在私有临时目录中，返回的目录在转换完成前不能释放
+fn staging_zip_path(
+    output_path: &Path,
+    options: &BackupOptions,
+) -> Result<(PathBuf, Option<tempfile::TempDir>), String> {
+    if options.encryption.is_enabled() {
+        let dir = crypto::private_staging_dir(".mysql_backup_staging_")?;
+        return Ok((dir.path().join("staging.zip"), Some(dir)));
+    }
+    if options.format == archive::ArchiveFormat::Zip {
+        return Ok((output_path.to_path_buf(), None));
     }
+    let mut path = output_path.as_os_str().to_os_string();
+    path.push(".staging.zip");
+    Ok((PathBuf::from(path), None))
+}
 
-    send_progress_update(&window, 100, "备份完成", None);
-    Ok(output_path.to_string())
+// 把暂存ZIP转换为选择的格式并按需加密，完成后删除暂存文件
+fn convert_staging_zip(
+    window: &jobs::JobWindow,
+    zip_path: &Path,
+    output_path: &Path,
+    options: &BackupOptions,
+) -> Result<(), String> {
+    if zip_path == output_path {
+        return Ok(());
+    }
+
+    if options.format == archive::ArchiveFormat::Zip {
+        send_progress_update(window, 97, "正在加密备份文件...", None);
+        crypto::encrypt_file(zip_path, output_path, &options.encryption)?;
+    } else {
+        send_progress_update(
+            window,
+            97,
+            &format!("正在转换为{}格式...", options.format.extension()),
+            None,
+        );
+        archive::convert_staging_zip(
+            zip_path,
+            output_path,
+            options.format,
+            options.compression_level,
+            &options.encryption,
+        )?;
+    }
+
+    fs::remove_file(zip_path).map_err(|e| format!("删除暂存文件失败: {}", e))
+}
+
+// 删除备份失败时留下的暂存文件、备份文件和清单
+fn remove_incomplete_backup(zip_path: &Path, output_path: &Path) {
+    let _ = fs::remove_file(zip_path);
+    let _ = fs::remove_file(output_path);
+    let _ = fs::remove_file(archive::sidecar_manifest_path(output_path));
 }
 
 // 使用Rust MySQL库进行备份（内置备份方式）
 fn backup_with_rust_mysql(
-    window: Window,
+    window: jobs::JobWindow,
     host: &str,
     port: u16,
     username: &str,
     password: &str,
     database: &str,
     output_path: &str,
+    options: &BackupOptions,
+    cancel_requested: &Arc<AtomicBool>,
 ) -> Result<String, String> {
     // 检查输出路径
     let output_file_path = Path::new(output_path);
@@ -333,14 +956,84 @@ fn backup_with_rust_mysql(
     }
 
     send_progress_update(&window, 5, "准备使用内置工具备份...", None);
+    let mut started_at = chrono::Local::now().to_rfc3339();
 
-    // 创建临时目录用于存放每个表的备份文件
-    let temp_dir = match TempDir::new() {
-        Ok(dir) => dir,
-        Err(e) => return Err(format!("创建临时目录失败: {}", e)),
+    // 创建ZIP文件，每张表的数据边读取边压缩写入，不再经过临时目录
+    let (zip_path, _staging_dir) = staging_zip_path(output_file_path, options)?;
+    let zip_file = match File::create(&zip_path) {
+        Ok(file) => file,
+        Err(e) => return Err(format!("创建ZIP文件失败: {}", e)),
     };
+    let mut zip = ZipWriter::new(zip_file);
+
+    let result = write_rust_mysql_backup(
+        &window,
+        &mut zip,
+        &zip_path,
+        host,
+        port,
+        username,
+        password,
+        database,
+        options,
+        cancel_requested,
+        &mut started_at,
+    )
+    .and_then(|server_info| {
+        send_progress_update(&window, 93, "正在完成ZIP文件...", None);
+        zip.finish()
+            .map_err(|e| format!("完成ZIP文件失败: {}", e))?;
+
+        check_backup_cancelled(cancel_requested)?;
+        send_progress_update(&window, 95, "正在生成备份清单...", None);
+        manifest::append_manifest(
+            &zip_path,
+            "builtin",
+            database,
+            server_info,
+            started_at,
+            options.encryption.scheme(),
+        )?;
+
+        check_backup_cancelled(cancel_requested)?;
+        convert_staging_zip(&window, &zip_path, output_file_path, options)
+    });
+
+    // 备份失败时删除不完整的备份文件，可续传备份的工作目录保留到下次继续
+    if let Err(e) = result {
+        drop(zip);
+        remove_incomplete_backup(&zip_path, output_file_path);
+        return Err(e);
+    }
+
+    // 可续传备份已完成，删除工作目录
+    if options.resumable {
+        let backup_dir = output_file_path.parent().unwrap_or_else(|| Path::new("."));
+        if let Err(e) = checkpoint::discard(backup_dir, database) {
+            eprintln!("{}", e);
+        }
+    }
+
+    send_progress_update(&window, 100, "备份完成", None);
+    Ok(output_path.to_string())
+}
 
-    send_progress_update(&window, 10, "连接数据库...", None);
+// 内置引擎的备份流程，所有内容直接写入传入的ZIP，返回写入清单所需的服务器信息。
+// 继续可续传备份时started_at改为第一次开始备份的时间
+fn write_rust_mysql_backup(
+    window: &jobs::JobWindow,
+    zip: &mut ZipWriter<File>,
+    output_path: &Path,
+    host: &str,
+    port: u16,
+    username: &str,
+    password: &str,
+    database: &str,
+    options: &BackupOptions,
+    cancel_requested: &Arc<AtomicBool>,
+    started_at: &mut String,
+) -> Result<manifest::ServerInfo, String> {
+    send_progress_update(window, 10, "连接数据库...", None);
 
     // 构建连接选项
     let opts = OptsBuilder::new()
@@ -348,165 +1041,1120 @@ fn backup_with_rust_mysql(
         .tcp_port(port)
         .user(Some(username))
         .pass(Some(password))
-        .db_name(Some(database));
+        .db_name(Some(database))
+        // 明确使用utf8mb4读取元数据和UTF-8文本列，其他字符集的列按原始字节读取
+        .init(vec!["SET NAMES utf8mb4"])
+        // 主连接加上每个工作线程一个连接，所有连接都能立即取得，不会在持有全局读锁时等待
+        .pool_opts(PoolOpts::default().with_constraints(
+            PoolConstraints::new(1, options.workers.max(1) + 1).unwrap_or_default(),
+        ));
 
     // 创建数据库连接
     let pool = match Pool::new(opts) {
         Ok(pool) => pool,
         Err(e) => {
-            send_progress_update(&window, 0, "连接数据库失败", None);
+            send_progress_update(window, 0, "连接数据库失败", None);
             return Err(format!("连接数据库失败: {}", e));
         }
     };
 
-    // 获取连接
-    let mut conn = match pool.get_conn() {
-        Ok(conn) => conn,
-        Err(e) => {
-            send_progress_update(&window, 0, "获取数据库连接失败", None);
-            return Err(format!("获取数据库连接失败: {}", e));
+    // 获取连接
+    let mut conn = match pool.get_conn() {
+        Ok(conn) => conn,
+        Err(e) => {
+            send_progress_update(window, 0, "获取数据库连接失败", None);
+            return Err(format!("获取数据库连接失败: {}", e));
+        }
+    };
+
+    send_progress_update(window, 15, "分析数据库结构...", None);
+
+    // 非事务引擎的表无法包含在一致性快照中
+    let non_transactional_tables = find_non_transactional_tables(&mut conn, database)?;
+    let lock_non_transactional =
+        options.lock_non_transactional && !non_transactional_tables.is_empty();
+    if !non_transactional_tables.is_empty() {
+        let warning = if lock_non_transactional {
+            format!(
+                "以下表使用非事务引擎，将在全局读锁期间备份: {}",
+                non_transactional_tables.join(", ")
+            )
+        } else {
+            format!(
+                "警告：以下表使用非事务引擎，备份结果可能与其他表不一致: {}",
+                non_transactional_tables.join(", ")
+            )
+        };
+        eprintln!("{}", warning);
+        send_progress_update(window, 15, &warning, None);
+    }
+
+    // 需要锁定非事务表或记录binlog位置时，先加全局读锁再开启快照，保证两者处于同一时间点
+    let mut global_lock_held = false;
+    if lock_non_transactional {
+        if let Err(e) = conn.query_drop("FLUSH TABLES WITH READ LOCK") {
+            return Err(format!("获取全局读锁失败: {}", e));
+        }
+        global_lock_held = true;
+    } else if options.record_binlog_position {
+        match conn.query_drop("FLUSH TABLES WITH READ LOCK") {
+            Ok(_) => global_lock_held = true,
+            Err(e) => {
+                let warning = format!("警告：无法获取全局读锁（{}），不记录binlog位置", e);
+                eprintln!("{}", warning);
+                send_progress_update(window, 15, &warning, None);
+            }
+        }
+    }
+
+    // 开启一致性快照事务，之后所有表都通过这一连接读取
+    start_consistent_snapshot(&mut conn)?;
+
+    // 持有全局读锁时没有新的提交，此时的binlog位置与快照对应
+    let binlog = if options.record_binlog_position && global_lock_held {
+        match binlog::read_binlog_position(&mut conn) {
+            Ok(Some(position)) => Some(position),
+            Ok(None) => {
+                send_progress_update(window, 15, "服务器未启用binlog，不记录binlog位置", None);
+                None
+            }
+            Err(e) => {
+                let warning = format!("警告：{}，不记录binlog位置", e);
+                eprintln!("{}", warning);
+                send_progress_update(window, 15, &warning, None);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // 只为记录binlog位置加的锁在串行备份时可以立即释放，并行备份在各工作线程开启快照后释放
+    if global_lock_held && !lock_non_transactional && options.workers <= 1 {
+        release_global_read_lock(&mut conn)?;
+        global_lock_held = false;
+    }
+
+    // 在快照内读取服务器信息和表列表，与导出的数据保持一致
+    let mut server_info = manifest::read_server_info(&mut conn, database)?;
+    server_info.binlog = binlog;
+
+    // 只备份通过过滤的表和视图
+    let table_filter = options.table_filter.compile()?;
+    server_info.tables = table_filter.partition(database, server_info.tables).0;
+    server_info.views = table_filter.partition(database, server_info.views).0;
+    server_info.table_filter = options.table_filter.recorded();
+    server_info.mode = options.mode;
+
+    // 字符串的转义方式取决于会话的sql_mode
+    let context = DumpContext {
+        no_backslash_escapes: server_info
+            .sql_mode
+            .to_uppercase()
+            .contains("NO_BACKSLASH_ESCAPES"),
+        // 只备份结构或数据时无法用校验和核对恢复结果
+        record_checksums: options.record_checksums && options.mode == manifest::BackupMode::Full,
+        where_clauses: options
+            .table_filter
+            .where_clauses_for(database, &server_info.tables),
+        mode: options.mode,
+        cancel_requested: Arc::clone(cancel_requested),
+    };
+
+    let file_options = options.zip_file_options();
+
+    let mut tables = server_info.tables.clone();
+    let views = server_info.views.clone();
+
+    // 持有全局读锁时先备份非事务表，备份完即释放锁，尽量缩短锁定时间
+    if lock_non_transactional {
+        tables.sort_by_key(|table| !non_transactional_tables.contains(table));
+    }
+
+    // 可续传备份时沿用或新建检查点，工作单元的规划保存在检查点中
+    let mut resume = if options.resumable {
+        let backup_dir = output_path.parent().unwrap_or_else(|| Path::new("."));
+        Some(prepare_checkpoint(
+            window, &mut conn, backup_dir, host, port, database, &tables, options, started_at,
+        )?)
+    } else {
+        None
+    };
+
+    // 继续完成的备份不属于同一个快照，没有对应的binlog位置
+    if let Some((_, checkpoint)) = &resume {
+        if !checkpoint.resumed_at.is_empty() {
+            server_info.binlog = None;
+        }
+    }
+
+    // 写入数据库信息
+    if let Err(e) = zip.start_file(DATABASE_INFO_ENTRY, file_options) {
+        return Err(format!("添加数据库信息到ZIP失败: {}", e));
+    }
+    let mut info_output = manifest::HashingWriter::new(&mut *zip);
+    if let Err(e) = writeln!(info_output, "-- MySQL dump by Rust mysql-client") {
+        return Err(format!("写入文件失败: {}", e));
+    }
+    if let Err(e) = writeln!(info_output, "-- Database: {}", database) {
+        return Err(format!("写入文件失败: {}", e));
+    }
+    write_dump_header(&mut info_output, context.no_backslash_escapes)?;
+    if options.write_change_source {
+        if let Some(position) = &server_info.binlog {
+            if let Err(e) = write!(
+                info_output,
+                "{}",
+                position.change_source_comment(&server_info.version)
+            ) {
+                return Err(format!("写入文件失败: {}", e));
+            }
+        }
+    }
+    // 按服务器上的定义建库，保留原有的字符集和排序规则
+    let (create_database, _) = show_create(
+        &mut conn,
+        &format!(
+            "SHOW CREATE DATABASE IF NOT EXISTS {}",
+            quote_identifier(database)
+        ),
+        "Create Database",
+    )?;
+    if let Err(e) = writeln!(
+        info_output,
+        "\n-- 创建数据库\n{};\nUSE {};\n",
+        create_database,
+        quote_identifier(database)
+    ) {
+        return Err(format!("写入文件失败: {}", e));
+    }
+    server_info
+        .entries
+        .push(info_output.into_entry(DATABASE_INFO_ENTRY));
+
+    // 把每张表拆分成备份工作单元，启用分块时大表按键范围切成多个数据分块。
+    // 每个工作单元带有编号，用于计算进度和命名分片文件；可续传备份只包含未完成的工作单元
+    let mut work_items: Vec<(usize, TableWorkItem)> = Vec::new();
+    let total_items = match &resume {
+        Some((_, checkpoint)) => {
+            work_items = checkpoint
+                .items
+                .iter()
+                .filter(|item| !item.done)
+                .map(|item| (item.id, item.work.clone()))
+                .collect();
+            if lock_non_transactional {
+                work_items.sort_by_key(|(_, item)| !non_transactional_tables.contains(&item.table));
+            }
+            checkpoint
+                .items
+                .iter()
+                .map(|item| item.id + 1)
+                .max()
+                .unwrap_or(0)
+        }
+        None => {
+            for table in &tables {
+                for item in
+                    plan_table_work_items(&mut conn, table, options.chunk_rows(), options.mode)?
+                {
+                    work_items.push((work_items.len(), item));
+                }
+            }
+            work_items.len()
+        }
+    };
+
+    let mut locked_item_count = 0;
+    if lock_non_transactional {
+        locked_item_count = work_items
+            .iter()
+            .filter(|(_, item)| non_transactional_tables.contains(&item.table))
+            .count();
+    }
+
+    // 没有需要在锁内备份的表，且不需要为并行备份的工作线程保持锁时释放全局读锁
+    let workers = options.workers.min(work_items.len());
+    if global_lock_held && locked_item_count == 0 && workers <= 1 {
+        release_global_read_lock(&mut conn)?;
+        global_lock_held = false;
+    }
+
+    if total_items == 0 {
+        send_progress_update(window, 20, "数据库中没有表", None);
+    } else {
+        send_progress_update(window, 20, "开始备份表结构和数据...", None);
+    }
+
+    if workers > 1 {
+        // 在全局读锁期间为每个工作线程开启快照，使所有线程看到同一时间点的数据
+        let global_lock_held = if global_lock_held {
+            true
+        } else {
+            match conn.query_drop("FLUSH TABLES WITH READ LOCK") {
+                Ok(_) => true,
+                Err(e) => {
+                    let warning = format!(
+                        "警告：无法获取全局读锁（{}），各工作线程的快照时间点可能不一致",
+                        e
+                    );
+                    eprintln!("{}", warning);
+                    send_progress_update(window, 20, &warning, None);
+                    false
+                }
+            }
+        };
+
+        let mut worker_conns = Vec::with_capacity(workers);
+        for _ in 0..workers {
+            let mut worker_conn = pool
+                .get_conn()
+                .map_err(|e| format!("获取数据库连接失败: {}", e))?;
+            start_consistent_snapshot(&mut worker_conn)?;
+            worker_conns.push(worker_conn);
+        }
+
+        // 非事务表需要在锁内备份完成，其余情况快照建立后即可释放锁
+        if global_lock_held && locked_item_count == 0 {
+            release_global_read_lock(&mut conn)?;
+        }
+
+        // 分片文件放在输出目录下，避免系统临时目录空间不足；需要加密时分片是明文，放在私有临时目录中。
+        // 可续传备份直接写入工作目录
+        let temp_part_dir;
+        let part_dir = match &resume {
+            Some((work_dir, _)) => work_dir.clone(),
+            None => {
+                temp_part_dir = if options.encryption.is_enabled() {
+                    crypto::private_staging_dir(".mysql_backup_parts_")?
+                } else {
+                    match output_path.parent() {
+                        Some(parent) => tempfile::Builder::new()
+                            .prefix(".mysql_backup_parts_")
+                            .tempdir_in(parent),
+                        None => tempfile::Builder::new()
+                            .prefix(".mysql_backup_parts_")
+                            .tempdir(),
+                    }
+                    .map_err(|e| format!("创建临时目录失败: {}", e))?
+                };
+                temp_part_dir.path().to_path_buf()
+            }
+        };
+
+        let item_ids: Vec<usize> = work_items.iter().map(|(id, _)| *id).collect();
+        let item_tables: Vec<String> = work_items
+            .iter()
+            .map(|(_, item)| item.table.clone())
+            .collect();
+
+        // 分片按工作单元的顺序交给这里：可续传备份记录到检查点，否则立即合并进ZIP
+        backup_tables_in_parallel(
+            window,
+            &mut conn,
+            file_options,
+            work_items,
+            &context,
+            worker_conns,
+            locked_item_count,
+            total_items,
+            &part_dir,
+            &mut |position, part_path, written| match &mut resume {
+                Some((work_dir, checkpoint)) => {
+                    checkpoint.mark_done(item_ids[position], written);
+                    checkpoint.save(work_dir)
+                }
+                None => {
+                    let progress = 20 + ((position as f32) / (total_items as f32) * 70.0) as u8;
+                    send_progress_update(
+                        window,
+                        progress,
+                        "正在合并表数据...",
+                        Some(&item_tables[position]),
+                    );
+                    merge_part_into_zip(zip, part_path)?;
+                    let _ = fs::remove_file(part_path);
+                    written.record(&mut server_info, &item_tables[position]);
+                    Ok(())
+                }
+            },
+        )?;
+    } else {
+        // 依次备份每个工作单元，结构和数据直接写入ZIP条目，可续传备份写入工作目录中的分片
+        for (position, (item_index, item)) in work_items.iter().enumerate() {
+            context.check_cancelled()?;
+
+            match &mut resume {
+                Some((work_dir, checkpoint)) => {
+                    let (_, written) = backup_work_item_to_part(
+                        window,
+                        &mut conn,
+                        work_dir,
+                        file_options,
+                        item,
+                        &context,
+                        *item_index,
+                        total_items,
+                        None,
+                    )?;
+                    checkpoint.mark_done(*item_index, written);
+                    checkpoint.save(work_dir)?;
+                }
+                None => {
+                    if let Err(e) = zip.start_file(item.entry_name.as_str(), file_options) {
+                        return Err(format!("添加表文件到ZIP失败: {}", e));
+                    }
+                    let mut output = manifest::HashingWriter::new(&mut *zip);
+                    let (rows, checksum) = backup_work_item(
+                        window,
+                        &mut conn,
+                        &mut output,
+                        item,
+                        &context,
+                        *item_index,
+                        total_items,
+                        None,
+                    )?;
+                    WrittenItem {
+                        entry: output.into_entry(&item.entry_name),
+                        rows,
+                        checksum,
+                    }
+                    .record(&mut server_info, &item.table);
+                }
+            }
+
+            if lock_non_transactional && position + 1 == locked_item_count {
+                release_global_read_lock(&mut conn)?;
+            }
+        }
+    }
+
+    // 快照内的读取已全部完成
+    if let Err(e) = conn.query_drop("COMMIT") {
+        return Err(format!("结束快照事务失败: {}", e));
+    }
+
+    // 可续传备份的工作单元全部完成后，按检查点中的顺序把分片合并进ZIP
+    if let Some((work_dir, checkpoint)) = &resume {
+        send_progress_update(window, 90, "正在合并已完成的工作单元...", None);
+        for item in &checkpoint.items {
+            context.check_cancelled()?;
+            merge_part_into_zip(zip, &checkpoint::part_path(work_dir, item.id))?;
+            if let Some(written) = &item.written {
+                written.clone().record(&mut server_info, &item.work.table);
+            }
+        }
+        server_info.resumed_at = checkpoint.resumed_at.clone();
+    }
+
+    send_progress_update(window, 90, "正在备份视图、存储过程、触发器和事件...", None);
+
+    // 备份视图、存储过程、触发器和事件，每类对象写入单独的ZIP条目
+    if options.mode.includes_schema() {
+        backup_database_objects(
+            &mut conn,
+            zip,
+            file_options,
+            database,
+            &server_info.tables,
+            &views,
+            &mut server_info.entries,
+        )?;
+    }
+
+    // 恢复会话变量，放在最后一个条目中
+    if let Err(e) = zip.start_file(DUMP_FOOTER_ENTRY, file_options) {
+        return Err(format!("添加会话恢复语句到ZIP失败: {}", e));
+    }
+    let mut footer_output = manifest::HashingWriter::new(&mut *zip);
+    write_dump_footer(&mut footer_output)?;
+    server_info
+        .entries
+        .push(footer_output.into_entry(DUMP_FOOTER_ENTRY));
+
+    Ok(server_info)
+}
+
+// 准备可续传备份的工作目录和检查点：选择继续且存在同一备份任务的检查点时沿用，
+// 否则丢弃旧的工作目录并重新规划所有表
+fn prepare_checkpoint(
+    window: &jobs::JobWindow,
+    conn: &mut PooledConn,
+    backup_dir: &Path,
+    host: &str,
+    port: u16,
+    database: &str,
+    tables: &[String],
+    options: &BackupOptions,
+    started_at: &mut String,
+) -> Result<(PathBuf, checkpoint::Checkpoint), String> {
+    // 加密备份只能继续同样放在私有临时目录中的工作目录
+    let private = options.encryption.is_enabled();
+    let mut work_dir = checkpoint::work_dir(backup_dir, database);
+    let existing = if options.resume
+        && checkpoint::private_work_dir(backup_dir, database).is_some() == private
+    {
+        checkpoint::Checkpoint::load(&work_dir)?.filter(|checkpoint| {
+            checkpoint.matches(host, port, database) && checkpoint.mode == options.mode
+        })
+    } else {
+        None
+    };
+
+    let checkpoint = match existing {
+        Some(mut checkpoint) => {
+            // 已完成的工作单元来自之前的快照，与本次备份的数据不属于同一时间点
+            let summary = checkpoint.summary();
+            let warning = format!(
+                "继续开始于 {} 的备份（已完成 {}/{} 个工作单元），注意：继续完成的备份中各表数据不属于同一个快照",
+                summary.started_at, summary.done_items, summary.total_items
+            );
+            eprintln!("{}", warning);
+            send_progress_update(window, 20, &warning, None);
+
+            checkpoint
+                .resumed_at
+                .push(chrono::Local::now().to_rfc3339());
+            *started_at = checkpoint.started_at.clone();
+
+            // 中断期间新建的表追加到末尾，已删除的表不再备份
+            for table in checkpoint.reconcile(&work_dir, tables) {
+                checkpoint.append(plan_table_work_items(
+                    conn,
+                    &table,
+                    options.chunk_rows(),
+                    options.mode,
+                )?);
+            }
+            checkpoint
+        }
+        None => {
+            work_dir = checkpoint::create_work_dir(backup_dir, database, private)?;
+
+            let mut checkpoint =
+                checkpoint::Checkpoint::new(host, port, database, options.mode, started_at);
+            for table in tables {
+                checkpoint.append(plan_table_work_items(
+                    conn,
+                    table,
+                    options.chunk_rows(),
+                    options.mode,
+                )?);
+            }
+            checkpoint
+        }
+    };
+
+    checkpoint.save(&work_dir)?;
+    Ok((work_dir, checkpoint))
+}
+
+// 表的备份工作单元，可续传备份时保存在检查点中
+#[derive(Serialize, Deserialize, Clone)]
+struct TableWorkItem {
+    table: String,
+    // 写入ZIP的条目名
+    entry_name: String,
+    kind: TableWorkKind,
+}
+
+// 已写入ZIP条目的工作单元，可续传备份时保存在检查点中
+#[derive(Serialize, Deserialize, Clone)]
+struct WrittenItem {
+    // 条目的大小和校验值
+    entry: manifest::ManifestEntry,
+    // 写入的数据行数
+    rows: u64,
+    // 表的CHECKSUM TABLE结果，只在负责表结构的工作单元中记录
+    checksum: Option<u64>,
+}
+
+impl WrittenItem {
+    // 记录到写入清单所需的服务器信息中
+    fn record(self, server_info: &mut manifest::ServerInfo, table: &str) {
+        server_info.entries.push(self.entry);
+        if self.rows > 0 {
+            *server_info.row_counts.entry(table.to_string()).or_insert(0) += self.rows;
+        }
+        if let Some(checksum) = self.checksum {
+            server_info.checksums.insert(table.to_string(), checksum);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+enum TableWorkKind {
+    // 整张表的结构和数据
+    Full,
+    // 只有表结构，数据由后续的分块负责
+    Structure,
+    // 按键范围切分的一个数据分块
+    Chunk(KeyRange),
+}
+
+// 按唯一键切分的数据范围：lower <= key < upper，边界为None表示不设限
+#[derive(Serialize, Deserialize, Clone)]
+struct KeyRange {
+    columns: Vec<String>,
+    #[serde(with = "checkpoint::key_values")]
+    lower: Option<Vec<mysql::Value>>,
+    #[serde(with = "checkpoint::key_values")]
+    upper: Option<Vec<mysql::Value>>,
+    // 分块序号，从1开始
+    part: usize,
+}
+
+impl KeyRange {
+    // 生成WHERE条件和对应的参数，多列键使用行构造器比较
+    fn condition(&self) -> (String, Vec<mysql::Value>) {
+        let key = format!(
+            "({})",
+            self.columns
+                .iter()
+                .map(|c| quote_identifier(c))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let placeholders = format!("({})", vec!["?"; self.columns.len()].join(", "));
+
+        let mut conditions = Vec::new();
+        let mut params = Vec::new();
+        if let Some(lower) = &self.lower {
+            conditions.push(format!("{} >= {}", key, placeholders));
+            params.extend(lower.iter().cloned());
+        }
+        if let Some(upper) = &self.upper {
+            conditions.push(format!("{} < {}", key, placeholders));
+            params.extend(upper.iter().cloned());
+        }
+        if conditions.is_empty() {
+            conditions.push("1 = 1".to_string());
+        }
+
+        (conditions.join(" AND "), params)
+    }
+
+    fn order_by(&self) -> String {
+        self.columns
+            .iter()
+            .map(|c| quote_identifier(c))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+// 为一张表规划备份工作单元。chunk_rows为0、表没有可用的唯一键或数据不足一个分块时整表备份
+fn plan_table_work_items(
+    conn: &mut PooledConn,
+    table: &str,
+    chunk_rows: u64,
+    mode: manifest::BackupMode,
+) -> Result<Vec<TableWorkItem>, String> {
+    let full = vec![TableWorkItem {
+        table: table.to_string(),
+        entry_name: format!("table_{}.sql", table),
+        kind: TableWorkKind::Full,
+    }];
+
+    if chunk_rows == 0 {
+        return Ok(full);
+    }
+    let key_columns = match find_chunk_key(conn, table)? {
+        Some(columns) => columns,
+        None => return Ok(full),
+    };
+
+    let boundaries = find_chunk_boundaries(conn, table, &key_columns, chunk_rows)?;
+    if boundaries.is_empty() {
+        return Ok(full);
+    }
+
+    Ok(chunk_work_items(table, &key_columns, boundaries, mode))
+}
+
+// 按分块边界生成表结构和各分块的工作单元，只备份数据时没有表结构的工作单元
+fn chunk_work_items(
+    table: &str,
+    key_columns: &[String],
+    boundaries: Vec<Vec<mysql::Value>>,
+    mode: manifest::BackupMode,
+) -> Vec<TableWorkItem> {
+    let mut items = Vec::with_capacity(boundaries.len() + 2);
+    if mode.includes_schema() {
+        items.push(TableWorkItem {
+            table: table.to_string(),
+            entry_name: format!("table_{}.sql", table),
+            kind: TableWorkKind::Structure,
+        });
+    }
+
+    // N个边界把键空间切成N+1段，首段没有下界，末段没有上界
+    let mut lower: Option<Vec<mysql::Value>> = None;
+    for (index, boundary) in boundaries
+        .into_iter()
+        .map(Some)
+        .chain(std::iter::once(None))
+        .enumerate()
+    {
+        let part = index + 1;
+        items.push(TableWorkItem {
+            table: table.to_string(),
+            entry_name: format!("table_{}.part{:04}.sql", table, part),
+            kind: TableWorkKind::Chunk(KeyRange {
+                columns: key_columns.to_vec(),
+                lower: lower.take(),
+                upper: boundary.clone(),
+                part,
+            }),
+        });
+        lower = boundary;
+    }
+
+    items
+}
+
+// 查找可用于分块的键：优先主键，其次所有列都非空的唯一索引
+fn find_chunk_key(conn: &mut PooledConn, table: &str) -> Result<Option<Vec<String>>, String> {
+    let index_columns: Vec<(String, String, String)> = conn
+        .exec(
+            "SELECT INDEX_NAME, COLUMN_NAME, NULLABLE FROM information_schema.STATISTICS \
+             WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? AND NON_UNIQUE = 0 \
+             ORDER BY INDEX_NAME = 'PRIMARY' DESC, INDEX_NAME, SEQ_IN_INDEX",
+            (table,),
+        )
+        .map_err(|e| format!("获取表 {} 的索引信息失败: {}", table, e))?;
+
+    let mut current_index: Option<String> = None;
+    let mut columns = Vec::new();
+    let mut usable = true;
+    for (index_name, column_name, nullable) in index_columns {
+        if current_index.as_deref() != Some(index_name.as_str()) {
+            if current_index.is_some() && usable && !columns.is_empty() {
+                return Ok(Some(columns));
+            }
+            current_index = Some(index_name);
+            columns = Vec::new();
+            usable = true;
+        }
+        usable &= nullable.is_empty();
+        columns.push(column_name);
+    }
+
+    if usable && !columns.is_empty() {
+        Ok(Some(columns))
+    } else {
+        Ok(None)
+    }
+}
+
+// 沿着键的顺序每隔chunk_rows行取一个边界值，只扫描索引
+fn find_chunk_boundaries(
+    conn: &mut PooledConn,
+    table: &str,
+    key_columns: &[String],
+    chunk_rows: u64,
+) -> Result<Vec<Vec<mysql::Value>>, String> {
+    let mut boundaries: Vec<Vec<mysql::Value>> = Vec::new();
+
+    loop {
+        let range = KeyRange {
+            columns: key_columns.to_vec(),
+            lower: boundaries.last().cloned(),
+            upper: None,
+            part: 0,
+        };
+        let (condition, params) = range.condition();
+        let sql = format!(
+            "SELECT {} FROM {} WHERE {} ORDER BY {} LIMIT 1 OFFSET {}",
+            range.order_by(),
+            quote_identifier(table),
+            condition,
+            range.order_by(),
+            chunk_rows
+        );
+
+        let row: Option<mysql::Row> = conn
+            .exec_first(sql, mysql::Params::Positional(params))
+            .map_err(|e| format!("计算表 {} 的分块边界失败: {}", table, e))?;
+        match row {
+            Some(row) => boundaries.push(row.unwrap()),
+            None => break,
+        }
+    }
+
+    Ok(boundaries)
+}
+
+// 备份一个工作单元的内容，返回写入的数据行数和表的校验和
+fn backup_work_item(
+    window: &jobs::JobWindow,
+    conn: &mut PooledConn,
+    output_file: &mut dyn Write,
+    item: &TableWorkItem,
+    context: &DumpContext,
+    item_index: usize,
+    total_items: usize,
+    worker: Option<usize>,
+) -> Result<(u64, Option<u64>), String> {
+    context.check_cancelled()?;
+    let progress = 20 + ((item_index as f32) / (total_items as f32) * 70.0) as u8;
+
+    let rows = match &item.kind {
+        TableWorkKind::Full => {
+            send_worker_progress_update(
+                window,
+                progress,
+                "正在备份表...",
+                Some(&item.table),
+                worker,
+            );
+            if context.mode.includes_schema() {
+                backup_table_structure(conn, output_file, &item.table)?;
+            }
+            if context.mode.includes_data() {
+                backup_table_data(
+                    window,
+                    conn,
+                    output_file,
+                    &item.table,
+                    None,
+                    context,
+                    item_index,
+                    total_items,
+                    worker,
+                )?
+            } else {
+                0
+            }
+        }
+        TableWorkKind::Structure => {
+            send_worker_progress_update(
+                window,
+                progress,
+                "正在备份表结构...",
+                Some(&item.table),
+                worker,
+            );
+            backup_table_structure(conn, output_file, &item.table)?;
+            0
+        }
+        TableWorkKind::Chunk(range) => {
+            send_worker_progress_update(
+                window,
+                progress,
+                &format!("正在备份表数据分块 {}...", range.part),
+                Some(&item.table),
+                worker,
+            );
+            backup_table_data(
+                window,
+                conn,
+                output_file,
+                &item.table,
+                Some(range),
+                context,
+                item_index,
+                total_items,
+                worker,
+            )?
+        }
+    };
+
+    // 校验和在负责表结构的工作单元中计算，与该表的数据使用同一个快照。
+    // 按WHERE条件只备份部分行的表与整表的校验和不同，不记录
+    let partial = context.where_clauses.contains_key(&item.table);
+    let checksum = match &item.kind {
+        TableWorkKind::Full | TableWorkKind::Structure if context.record_checksums && !partial => {
+            checksum_table(conn, &item.table)?
         }
+        _ => None,
     };
+    Ok((rows, checksum))
+}
 
-    send_progress_update(&window, 15, "分析数据库结构...", None);
-
-    // 创建数据库信息文件
-    let db_info_path = temp_dir.path().join("00_database_info.sql");
-    let mut db_info_file = match File::create(&db_info_path) {
-        Ok(file) => file,
-        Err(e) => return Err(format!("创建数据库信息文件失败: {}", e)),
-    };
+// 对表执行CHECKSUM TABLE，表不存在时服务器返回NULL
+fn checksum_table(conn: &mut PooledConn, table: &str) -> Result<Option<u64>, String> {
+    let row: Option<(String, Option<u64>)> = conn
+        .query_first(format!("CHECKSUM TABLE {}", quote_identifier(table)))
+        .map_err(|e| format!("计算表 {} 的校验和失败: {}", table, e))?;
+    Ok(row.and_then(|(_, checksum)| checksum))
+}
 
-    // 写入数据库信息
-    if let Err(e) = writeln!(db_info_file, "-- MySQL dump by Rust mysql-client") {
-        return Err(format!("写入文件失败: {}", e));
-    }
-    if let Err(e) = writeln!(db_info_file, "-- Database: {}", database) {
-        return Err(format!("写入文件失败: {}", e));
-    }
-    if let Err(e) = writeln!(db_info_file, "\n-- 创建数据库\nCREATE DATABASE IF NOT EXISTS `{}` DEFAULT CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci;\nUSE `{}`;\n", database, database) {
-        return Err(format!("写入文件失败: {}", e));
-    }
+// 多个工作线程从共享队列领取工作单元并行备份，每个线程使用独立的连接和快照，
+// 先写入各自的分片ZIP，再按工作单元的顺序把分片交给on_item_done处理。
+// work_items中的编号用于命名分片文件和计算进度，on_item_done收到的是工作单元在work_items中的位置
+fn backup_tables_in_parallel(
+    window: &jobs::JobWindow,
+    control_conn: &mut PooledConn,
+    file_options: FileOptions,
+    work_items: Vec<(usize, TableWorkItem)>,
+    context: &DumpContext,
+    worker_conns: Vec<PooledConn>,
+    locked_item_count: usize,
+    total_items: usize,
+    part_dir: &Path,
+    on_item_done: &mut dyn FnMut(usize, &Path, WrittenItem) -> Result<(), String>,
+) -> Result<(), String> {
+    let item_count = work_items.len();
+    let queue: Arc<Mutex<VecDeque<(usize, (usize, TableWorkItem))>>> =
+        Arc::new(Mutex::new(work_items.into_iter().enumerate().collect()));
+    let (tx, rx) = std::sync::mpsc::channel::<(usize, Result<(PathBuf, WrittenItem), String>)>();
+
+    let mut handles = Vec::with_capacity(worker_conns.len());
+    for (worker_index, mut worker_conn) in worker_conns.into_iter().enumerate() {
+        let queue = Arc::clone(&queue);
+        let tx = tx.clone();
+        let window = window.clone();
+        let context = context.clone();
+        let part_dir = part_dir.to_path_buf();
+
+        handles.push(thread::spawn(move || {
+            loop {
+                let next = match queue.lock() {
+                    Ok(mut queue) => queue.pop_front(),
+                    Err(_) => None,
+                };
+                let (position, (item_index, item)) = match next {
+                    Some(next) => next,
+                    None => break,
+                };
 
-    // 获取所有表名
-    let tables: Vec<String> = match conn.query("SHOW TABLES") {
-        Ok(result) => result,
-        Err(e) => {
-            send_progress_update(&window, 0, "获取表列表失败", None);
-            return Err(format!("获取表列表失败: {}", e));
-        }
-    };
+                let result = backup_work_item_to_part(
+                    &window,
+                    &mut worker_conn,
+                    &part_dir,
+                    file_options,
+                    &item,
+                    &context,
+                    item_index,
+                    total_items,
+                    Some(worker_index + 1),
+                );
+                let failed = result.is_err();
+                if tx.send((position, result)).is_err() || failed {
+                    break;
+                }
+            }
 
-    let total_tables = tables.len();
-    if total_tables == 0 {
-        send_progress_update(&window, 20, "数据库中没有表", None);
-    } else {
-        send_progress_update(&window, 20, "开始备份表结构和数据...", None);
+            let _ = worker_conn.query_drop("COMMIT");
+        }));
     }
+    drop(tx);
 
-    // 遍历每张表进行备份
-    for (table_index, table) in tables.iter().enumerate() {
-        let progress = 20 + ((table_index as f32) / (total_tables as f32) * 50.0) as u8;
-        send_progress_update(&window, progress, "正在备份表...", Some(table));
+    // 先完成的分片等待排在前面的工作单元，保证ZIP中的条目顺序稳定
+    let mut finished: BTreeMap<usize, (PathBuf, WrittenItem)> = BTreeMap::new();
+    let mut next_position = 0;
+    let mut locked_items_done = 0;
+    let mut error = None;
 
-        let table_file_name = format!("table_{}.sql", table);
-        let table_file_path = temp_dir.path().join(&table_file_name);
-
-        // 创建表备份文件
-        let mut table_file = match File::create(&table_file_path) {
-            Ok(file) => file,
-            Err(e) => return Err(format!("创建表备份文件失败: {}", e)),
+    for (position, result) in rx.iter() {
+        let part = match result {
+            Ok(part) => part,
+            Err(e) => {
+                error = Some(e);
+                break;
+            }
         };
 
-        // 备份表结构
-        if let Err(e) = backup_table_structure(&mut conn, &mut table_file, table) {
-            return Err(e);
+        // 非事务表全部备份完成后释放全局读锁
+        if position < locked_item_count {
+            locked_items_done += 1;
+            if locked_items_done == locked_item_count {
+                if let Err(e) = release_global_read_lock(control_conn) {
+                    error = Some(e);
+                    break;
+                }
+            }
         }
 
-        // 备份表数据
-        if let Err(e) = backup_table_data(
-            &window,
-            &mut conn,
-            &mut table_file,
-            table,
-            table_index,
-            total_tables,
-        ) {
-            return Err(e);
+        finished.insert(position, part);
+        while let Some((part_path, written)) = finished.remove(&next_position) {
+            if let Err(e) = on_item_done(next_position, &part_path, written) {
+                error = Some(e);
+                break;
+            }
+            next_position += 1;
+        }
+        if error.is_some() {
+            break;
         }
     }
 
-    send_progress_update(&window, 70, "表备份完成，正在创建ZIP文件...", None);
+    // 出错时清空队列，让其他工作线程完成手头的工作后退出
+    if error.is_some() {
+        if let Ok(mut queue) = queue.lock() {
+            queue.clear();
+        }
+    }
+    drop(rx);
+    for handle in handles {
+        let _ = handle.join();
+    }
 
-    // 创建ZIP文件
-    let zip_file = match File::create(output_path) {
-        Ok(file) => file,
-        Err(e) => return Err(format!("创建ZIP文件失败: {}", e)),
-    };
+    if let Some(e) = error {
+        return Err(e);
+    }
+    if next_position < item_count {
+        return Err("备份工作线程意外退出，部分表没有完成备份".to_string());
+    }
 
-    let mut zip = ZipWriter::new(zip_file);
-    let options = FileOptions::default()
-        .compression_method(zip::CompressionMethod::Deflated)
-        .unix_permissions(0o755);
+    Ok(())
+}
 
-    // 首先添加数据库信息文件
-    send_progress_update(&window, 75, "正在压缩数据库信息...", None);
+// 备份一个工作单元，内容写入以编号命名的独立分片ZIP，返回分片文件路径和写入的条目
+fn backup_work_item_to_part(
+    window: &jobs::JobWindow,
+    conn: &mut PooledConn,
+    part_dir: &Path,
+    file_options: FileOptions,
+    item: &TableWorkItem,
+    context: &DumpContext,
+    item_index: usize,
+    total_items: usize,
+    worker: Option<usize>,
+) -> Result<(PathBuf, WrittenItem), String> {
+    let part_path = checkpoint::part_path(part_dir, item_index);
+    let part_file = File::create(&part_path).map_err(|e| format!("创建分片文件失败: {}", e))?;
+    let mut part_zip = ZipWriter::new(part_file);
+
+    if let Err(e) = part_zip.start_file(item.entry_name.as_str(), file_options) {
+        return Err(format!("添加表文件到ZIP失败: {}", e));
+    }
 
-    if let Err(e) = zip.start_file("00_database_info.sql", options) {
-        return Err(format!("添加数据库信息到ZIP失败: {}", e));
+    // 分片中的条目原样合并进最终的ZIP，校验值在写入分片时计算
+    let mut output = manifest::HashingWriter::new(&mut part_zip);
+    let (rows, checksum) = backup_work_item(
+        window,
+        conn,
+        &mut output,
+        item,
+        context,
+        item_index,
+        total_items,
+        worker,
+    )?;
+    let entry = output.into_entry(&item.entry_name);
+
+    if let Err(e) = part_zip.finish() {
+        return Err(format!("完成分片文件失败: {}", e));
     }
 
-    let db_info_content = match fs::read(&db_info_path) {
-        Ok(content) => content,
-        Err(e) => return Err(format!("读取数据库信息文件失败: {}", e)),
-    };
+    Ok((
+        part_path,
+        WrittenItem {
+            entry,
+            rows,
+            checksum,
+        },
+    ))
+}
 
-    if let Err(e) = zip.write_all(&db_info_content) {
-        return Err(format!("写入数据库信息到ZIP失败: {}", e));
+// 把分片ZIP中的条目原样拷贝进最终的ZIP（无需重新压缩）
+fn merge_part_into_zip(zip: &mut ZipWriter<File>, part_path: &Path) -> Result<(), String> {
+    let part_file = File::open(part_path).map_err(|e| format!("打开分片文件失败: {}", e))?;
+    let mut part_archive =
+        ZipArchive::new(part_file).map_err(|e| format!("读取分片文件失败: {}", e))?;
+
+    for index in 0..part_archive.len() {
+        let entry = part_archive
+            .by_index_raw(index)
+            .map_err(|e| format!("读取分片条目失败: {}", e))?;
+        zip.raw_copy_file(entry)
+            .map_err(|e| format!("合并分片到ZIP失败: {}", e))?;
     }
 
-    // 添加所有表文件到ZIP
-    for (idx, table) in tables.iter().enumerate() {
-        let progress = 75 + ((idx as f32) / (total_tables as f32) * 20.0) as u8;
-        send_progress_update(&window, progress, "正在压缩表数据...", Some(table));
-
-        let table_file_name = format!("table_{}.sql", table);
-        let table_file_path = temp_dir.path().join(&table_file_name);
+    Ok(())
+}
 
-        // 添加到ZIP
-        if let Err(e) = zip.start_file(&table_file_name, options) {
-            return Err(format!("添加表文件到ZIP失败: {}", e));
-        }
+// 在当前连接上开启REPEATABLE READ级别的一致性快照事务
+fn start_consistent_snapshot(conn: &mut PooledConn) -> Result<(), String> {
+    // TIMESTAMP列按UTC导出，与备份文件头中的TIME_ZONE='+00:00'对应
+    if let Err(e) = conn.query_drop("SET SESSION time_zone = '+00:00'") {
+        return Err(format!("设置会话时区失败: {}", e));
+    }
+    if let Err(e) = conn.query_drop("SET SESSION TRANSACTION ISOLATION LEVEL REPEATABLE READ") {
+        return Err(format!("设置事务隔离级别失败: {}", e));
+    }
+    if let Err(e) = conn.query_drop("START TRANSACTION WITH CONSISTENT SNAPSHOT") {
+        return Err(format!("开启一致性快照失败: {}", e));
+    }
 
-        let table_content = match fs::read(&table_file_path) {
-            Ok(content) => content,
-            Err(e) => return Err(format!("读取表备份文件失败: {}", e)),
-        };
+    Ok(())
+}
 
-        if let Err(e) = zip.write_all(&table_content) {
-            return Err(format!("写入表数据到ZIP失败: {}", e));
-        }
-    }
+// 释放FLUSH TABLES WITH READ LOCK获取的全局读锁
+fn release_global_read_lock(conn: &mut PooledConn) -> Result<(), String> {
+    conn.query_drop("UNLOCK TABLES")
+        .map_err(|e| format!("释放全局读锁失败: {}", e))
+}
 
-    send_progress_update(&window, 95, "正在完成ZIP文件...", None);
+// 查询数据库中使用非事务引擎（如MyISAM）的表
+fn find_non_transactional_tables(
+    conn: &mut PooledConn,
+    database: &str,
+) -> Result<Vec<String>, String> {
+    conn.exec(
+        "SELECT TABLE_NAME FROM information_schema.TABLES \
+         WHERE TABLE_SCHEMA = ? AND TABLE_TYPE = 'BASE TABLE' \
+         AND UPPER(ENGINE) NOT IN ('INNODB', 'NDBCLUSTER', 'NDB', 'ROCKSDB', 'TOKUDB') \
+         ORDER BY TABLE_NAME",
+        (database,),
+    )
+    .map_err(|e| format!("获取表引擎信息失败: {}", e))
+}
 
-    // 完成ZIP文件
-    if let Err(e) = zip.finish() {
-        return Err(format!("完成ZIP文件失败: {}", e));
-    }
+// 写入与mysqldump相同的文件头：保存恢复会话的原有设置，关闭外键和唯一性检查，
+// 并固定字符集、时区和sql_mode，使恢复结果不受恢复会话设置的影响
+fn write_dump_header(
+    output_file: &mut dyn Write,
+    no_backslash_escapes: bool,
+) -> Result<(), String> {
+    // 备份时的字符串转义方式，恢复时需要相同的sql_mode才能正确解析
+    let sql_mode = if no_backslash_escapes {
+        "NO_AUTO_VALUE_ON_ZERO,NO_BACKSLASH_ESCAPES"
+    } else {
+        "NO_AUTO_VALUE_ON_ZERO"
+    };
+    let header = format!(
+        "\n/*!40101 SET @OLD_CHARACTER_SET_CLIENT=@@CHARACTER_SET_CLIENT */;\n\
+         /*!40101 SET @OLD_CHARACTER_SET_RESULTS=@@CHARACTER_SET_RESULTS */;\n\
+         /*!40101 SET @OLD_COLLATION_CONNECTION=@@COLLATION_CONNECTION */;\n\
+         /*!50503 SET NAMES utf8mb4 */;\n\
+         /*!40103 SET @OLD_TIME_ZONE=@@TIME_ZONE */;\n\
+         /*!40103 SET TIME_ZONE='+00:00' */;\n\
+         /*!40014 SET @OLD_UNIQUE_CHECKS=@@UNIQUE_CHECKS, UNIQUE_CHECKS=0 */;\n\
+         /*!40014 SET @OLD_FOREIGN_KEY_CHECKS=@@FOREIGN_KEY_CHECKS, FOREIGN_KEY_CHECKS=0 */;\n\
+         /*!40101 SET @OLD_SQL_MODE=@@SQL_MODE, SQL_MODE='{}' */;\n\
+         /*!40111 SET @OLD_SQL_NOTES=@@SQL_NOTES, SQL_NOTES=0 */;",
+        sql_mode
+    );
+    writeln!(output_file, "{}", header).map_err(|e| format!("写入文件失败: {}", e))
+}
 
-    send_progress_update(&window, 100, "备份完成", None);
-    Ok(output_path.to_string())
+// 写入文件尾，恢复文件头保存的会话设置
+fn write_dump_footer(output_file: &mut dyn Write) -> Result<(), String> {
+    let footer = "-- 恢复会话设置\n\
+                  /*!40103 SET TIME_ZONE=@OLD_TIME_ZONE */;\n\
+                  /*!40101 SET SQL_MODE=@OLD_SQL_MODE */;\n\
+                  /*!40014 SET FOREIGN_KEY_CHECKS=@OLD_FOREIGN_KEY_CHECKS */;\n\
+                  /*!40014 SET UNIQUE_CHECKS=@OLD_UNIQUE_CHECKS */;\n\
+                  /*!40101 SET CHARACTER_SET_CLIENT=@OLD_CHARACTER_SET_CLIENT */;\n\
+                  /*!40101 SET CHARACTER_SET_RESULTS=@OLD_CHARACTER_SET_RESULTS */;\n\
+                  /*!40101 SET COLLATION_CONNECTION=@OLD_COLLATION_CONNECTION */;\n\
+                  /*!40111 SET SQL_NOTES=@OLD_SQL_NOTES */;";
+    writeln!(output_file, "{}", footer).map_err(|e| format!("写入文件失败: {}", e))
 }
 
 // 备份表结构
 fn backup_table_structure(
     conn: &mut PooledConn,
-    output_file: &mut fs::File,
+    output_file: &mut dyn Write,
     table: &str,
 ) -> Result<(), String> {
     // 获取表结构
-    let row = match conn.query_first::<mysql::Row, _>(format!("SHOW CREATE TABLE {}", table)) {
+    let sql = format!("SHOW CREATE TABLE {}", quote_identifier(table));
+    let row = match conn.query_first::<mysql::Row, _>(sql) {
         Ok(Some(row)) => row,
         Ok(None) => return Err(format!("获取表结构失败: 表 {} 不存在", table)),
         Err(e) => return Err(format!("获取表结构失败: {}", e)),
@@ -522,7 +2170,11 @@ fn backup_table_structure(
     if let Err(e) = writeln!(output_file, "\n-- 表结构: {}\n", table) {
         return Err(format!("写入文件失败: {}", e));
     }
-    if let Err(e) = writeln!(output_file, "DROP TABLE IF EXISTS `{}`;\n", table) {
+    if let Err(e) = writeln!(
+        output_file,
+        "DROP TABLE IF EXISTS {};\n",
+        quote_identifier(table)
+    ) {
         return Err(format!("写入文件失败: {}", e));
     }
     if let Err(e) = writeln!(output_file, "{};\n", create_table) {
@@ -532,28 +2184,52 @@ fn backup_table_structure(
     Ok(())
 }
 
-// 备份表数据
+// 备份表数据，返回写入的行数
 fn backup_table_data(
-    window: &Window,
+    window: &jobs::JobWindow,
     conn: &mut PooledConn,
-    output_file: &mut fs::File,
+    output_file: &mut dyn Write,
     table: &str,
+    key_range: Option<&KeyRange>,
+    context: &DumpContext,
     table_index: usize,
     total_tables: usize,
-) -> Result<(), String> {
+    worker: Option<usize>,
+) -> Result<u64, String> {
     // 写入表数据开始标记
-    if let Err(e) = writeln!(output_file, "\n-- 表数据: {}\n", table) {
+    let header = match key_range {
+        Some(range) => format!("\n-- 表数据: {} (第{}部分)\n", table, range.part),
+        None => format!("\n-- 表数据: {}\n", table),
+    };
+    if let Err(e) = writeln!(output_file, "{}", header) {
         return Err(format!("写入文件失败: {}", e));
     }
 
-    if let Err(e) = writeln!(output_file, "LOCK TABLES `{}` WRITE;", table) {
+    if let Err(e) = writeln!(
+        output_file,
+        "LOCK TABLES {} WRITE;",
+        quote_identifier(table)
+    ) {
+        return Err(format!("写入文件失败: {}", e));
+    }
+    // 批量插入期间暂停更新非唯一索引（MyISAM），插入完成后一次性重建
+    if let Err(e) = writeln!(
+        output_file,
+        "/*!40000 ALTER TABLE {} DISABLE KEYS */;",
+        quote_identifier(table)
+    ) {
         return Err(format!("写入文件失败: {}", e));
     }
 
-    // 获取列信息，以便正确处理数据类型
+    // 获取列信息，以便正确处理数据类型。
+    // 连接字符集为utf8mb4，非UTF-8字符集的文本列按原始字节读取，避免转换时丢失字符
     let mut column_names = Vec::new();
+    let mut raw_charsets: Vec<Option<String>> = Vec::new();
     {
-        let columns = match conn.query_iter(format!("SHOW COLUMNS FROM {}", table)) {
+        let columns = match conn.query_iter(format!(
+            "SHOW FULL COLUMNS FROM {}",
+            quote_identifier(table)
+        )) {
             Ok(cols) => cols,
             Err(e) => return Err(format!("获取列信息失败: {}", e)),
         };
@@ -571,20 +2247,65 @@ fn backup_table_data(
                 None => return Err("无法获取列名".to_string()),
             };
 
+            // 生成列的值由表达式计算，不能出现在INSERT中
+            let extra: String = col
+                .get::<Option<String>, _>("Extra")
+                .flatten()
+                .unwrap_or_default();
+            if is_generated_column(&extra) {
+                continue;
+            }
+
+            // 排序规则以字符集名开头，例如latin1_swedish_ci
+            let collation: Option<String> = col.get::<Option<String>, _>("Collation").flatten();
+            let charset = collation
+                .as_deref()
+                .and_then(|collation| collation.split('_').next())
+                .filter(|charset| !UTF8_CHARSETS.contains(charset))
+                .map(|charset| charset.to_string());
+
             column_names.push(col_name);
+            raw_charsets.push(charset);
         }
     }
 
-    // 获取表数据行数
-    let row_count: u64 = match conn.query_first(format!("SELECT COUNT(*) as count FROM {}", table))
-    {
-        Ok(Some(count)) => count,
-        Ok(None) => 0,
-        Err(_) => 0,
+    // 获取表数据，分块时按键范围读取并按键排序。
+    // 使用二进制协议读取，数值和日期以原始类型返回，浮点数不会因文本格式化丢失精度
+    let select_columns = column_names
+        .iter()
+        .zip(&raw_charsets)
+        .map(|(c, charset)| match charset {
+            Some(_) => format!("CAST({} AS BINARY)", quote_identifier(c)),
+            None => quote_identifier(c),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    // 设置了WHERE条件的表只读取满足条件的行
+    let where_clause = context.where_clauses.get(table);
+    let (sql, params) = match key_range {
+        Some(range) => {
+            let (mut condition, params) = range.condition();
+            if let Some(where_clause) = where_clause {
+                condition = format!("({}) AND {}", where_clause, condition);
+            }
+            let sql = format!(
+                "SELECT {} FROM {} WHERE {} ORDER BY {}",
+                select_columns,
+                quote_identifier(table),
+                condition,
+                range.order_by()
+            );
+            (sql, params)
+        }
+        None => {
+            let mut sql = format!("SELECT {} FROM {}", select_columns, quote_identifier(table));
+            if let Some(where_clause) = where_clause {
+                sql.push_str(&format!(" WHERE {}", where_clause));
+            }
+            (sql, Vec::new())
+        }
     };
-
-    // 获取表数据
-    let rows = match conn.query_iter(format!("SELECT * FROM {}", table)) {
+    let rows = match conn.exec_iter(sql, mysql::Params::Positional(params)) {
         Ok(rows) => rows,
         Err(e) => return Err(format!("获取表数据失败: {}", e)),
     };
@@ -594,6 +2315,7 @@ fn backup_table_data(
     let mut rows_processed = 0;
 
     for row_result in rows {
+        context.check_cancelled()?;
         let row = match row_result {
             Ok(row) => row,
             Err(e) => return Err(format!("读取行数据失败: {}", e)),
@@ -602,8 +2324,13 @@ fn backup_table_data(
         // 处理一行数据
         let mut value_strings = Vec::new();
 
-        for col_name in &column_names {
-            let value = match get_escaped_value(&row, col_name) {
+        for index in 0..column_names.len() {
+            let value = match get_escaped_value(
+                &row,
+                index,
+                raw_charsets[index].as_deref(),
+                context.no_backslash_escapes,
+            ) {
                 Ok(v) => v,
                 Err(e) => return Err(e),
             };
@@ -618,13 +2345,13 @@ fn backup_table_data(
         rows_processed += 1;
 
         // 每1000行发送一次进度更新
-        if rows_processed % 1000 == 0 && row_count > 0 {
-            // 计算总体进度 (20-70%的范围用于表备份)
-            let base_progress = 20 + (table_index as f32 / total_tables as f32 * 50.0) as u8;
+        if rows_processed % 1000 == 0 {
+            // 计算总体进度 (20-90%的范围用于表备份)
+            let base_progress = 20 + (table_index as f32 / total_tables as f32 * 70.0) as u8;
 
             // 发送详细的进度更新
             let status = format!("正在备份表数据...");
-            send_progress_update(window, base_progress, &status, Some(table));
+            send_worker_progress_update(window, base_progress, &status, Some(table), worker);
         }
 
         // 每1000行写入一次
@@ -632,11 +2359,11 @@ fn backup_table_data(
             // 写入INSERT语句
             if let Err(e) = writeln!(
                 output_file,
-                "INSERT INTO `{}` ({}) VALUES",
-                table,
+                "INSERT INTO {} ({}) VALUES",
+                quote_identifier(table),
                 column_names
                     .iter()
-                    .map(|s| format!("`{}`", s))
+                    .map(|s| quote_identifier(s))
                     .collect::<Vec<_>>()
                     .join(", ")
             ) {
@@ -666,11 +2393,11 @@ fn backup_table_data(
         // 写入INSERT语句
         if let Err(e) = writeln!(
             output_file,
-            "INSERT INTO `{}` ({}) VALUES",
-            table,
+            "INSERT INTO {} ({}) VALUES",
+            quote_identifier(table),
             column_names
                 .iter()
-                .map(|s| format!("`{}`", s))
+                .map(|s| quote_identifier(s))
                 .collect::<Vec<_>>()
                 .join(", ")
         ) {
@@ -692,100 +2419,444 @@ fn backup_table_data(
         }
     }
 
+    if let Err(e) = writeln!(
+        output_file,
+        "/*!40000 ALTER TABLE {} ENABLE KEYS */;",
+        quote_identifier(table)
+    ) {
+        return Err(format!("写入文件失败: {}", e));
+    }
     if let Err(e) = writeln!(output_file, "UNLOCK TABLES;") {
         return Err(format!("写入文件失败: {}", e));
     }
 
+    Ok(rows_processed)
+}
+
+// 根据SHOW COLUMNS的Extra判断是否为生成列：VIRTUAL GENERATED、STORED GENERATED，
+// 以及MariaDB 10.2之前的VIRTUAL、PERSISTENT。
+// MySQL 8中带表达式默认值的列（如DEFAULT CURRENT_TIMESTAMP）显示为DEFAULT_GENERATED，仍需备份
+fn is_generated_column(extra: &str) -> bool {
+    extra.split_whitespace().any(|word| {
+        ["VIRTUAL", "STORED", "PERSISTENT"]
+            .iter()
+            .any(|kind| word.eq_ignore_ascii_case(kind))
+    })
+}
+
+// 为标识符加上反引号，并转义其中的反引号
+fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+// 备份视图、存储过程/函数、触发器和事件，每类对象写入单独的ZIP条目（按恢复顺序），
+// 写入的条目记录到entries
+fn backup_database_objects(
+    conn: &mut PooledConn,
+    zip: &mut ZipWriter<File>,
+    file_options: FileOptions,
+    database: &str,
+    tables: &[String],
+    views: &[String],
+    entries: &mut Vec<manifest::ManifestEntry>,
+) -> Result<(), String> {
+    // 存储过程和函数放在视图之前，视图可能引用存储函数
+    let routines: Vec<(String, String)> = conn
+        .exec(
+            "SELECT ROUTINE_NAME, ROUTINE_TYPE FROM information_schema.ROUTINES \
+             WHERE ROUTINE_SCHEMA = ? ORDER BY ROUTINE_TYPE, ROUTINE_NAME",
+            (database,),
+        )
+        .map_err(|e| format!("获取存储过程列表失败: {}", e))?;
+    if !routines.is_empty() {
+        zip.start_file("routines.sql", file_options)
+            .map_err(|e| format!("添加存储过程文件到ZIP失败: {}", e))?;
+        let mut output = manifest::HashingWriter::new(&mut *zip);
+        backup_routines(conn, &mut output, &routines)?;
+        entries.push(output.into_entry("routines.sql"));
+    }
+
+    if !views.is_empty() {
+        zip.start_file("views.sql", file_options)
+            .map_err(|e| format!("添加视图文件到ZIP失败: {}", e))?;
+        let mut output = manifest::HashingWriter::new(&mut *zip);
+        backup_views(conn, &mut output, views)?;
+        entries.push(output.into_entry("views.sql"));
+    }
+
+    // 触发器放在所有表数据之后，避免导入数据时触发；过滤掉的表上的触发器不备份
+    let triggers: Vec<(String, String)> = conn
+        .exec(
+            "SELECT TRIGGER_NAME, EVENT_OBJECT_TABLE FROM information_schema.TRIGGERS \
+             WHERE TRIGGER_SCHEMA = ? ORDER BY EVENT_OBJECT_TABLE, ACTION_ORDER",
+            (database,),
+        )
+        .map_err(|e| format!("获取触发器列表失败: {}", e))?;
+    let triggers: Vec<String> = triggers
+        .into_iter()
+        .filter(|(_, table)| tables.contains(table))
+        .map(|(trigger, _)| trigger)
+        .collect();
+    if !triggers.is_empty() {
+        zip.start_file("triggers.sql", file_options)
+            .map_err(|e| format!("添加触发器文件到ZIP失败: {}", e))?;
+        let mut output = manifest::HashingWriter::new(&mut *zip);
+        for trigger in &triggers {
+            let (create_sql, sql_mode) = show_create(
+                conn,
+                &format!("SHOW CREATE TRIGGER {}", quote_identifier(trigger)),
+                "SQL Original Statement",
+            )?;
+            write_compound_object(
+                &mut output,
+                &format!("触发器: {}", trigger),
+                &format!("DROP TRIGGER IF EXISTS {}", quote_identifier(trigger)),
+                &create_sql,
+                sql_mode.as_deref(),
+            )?;
+        }
+        entries.push(output.into_entry("triggers.sql"));
+    }
+
+    let events: Vec<String> = conn
+        .exec(
+            "SELECT EVENT_NAME FROM information_schema.EVENTS \
+             WHERE EVENT_SCHEMA = ? ORDER BY EVENT_NAME",
+            (database,),
+        )
+        .map_err(|e| format!("获取事件列表失败: {}", e))?;
+    if !events.is_empty() {
+        zip.start_file("events.sql", file_options)
+            .map_err(|e| format!("添加事件文件到ZIP失败: {}", e))?;
+        let mut output = manifest::HashingWriter::new(&mut *zip);
+        for event in &events {
+            let (create_sql, sql_mode) = show_create(
+                conn,
+                &format!("SHOW CREATE EVENT {}", quote_identifier(event)),
+                "Create Event",
+            )?;
+            write_compound_object(
+                &mut output,
+                &format!("事件: {}", event),
+                &format!("DROP EVENT IF EXISTS {}", quote_identifier(event)),
+                &create_sql,
+                sql_mode.as_deref(),
+            )?;
+        }
+        entries.push(output.into_entry("events.sql"));
+    }
+
     Ok(())
 }
 
-// 处理不同类型的MySQL数据，转换为SQL格式的字符串值
-fn get_escaped_value(row: &mysql::Row, column_name: &str) -> Result<String, String> {
-    if let Some(val) = row.get_opt(column_name) {
-        match val {
-            // 处理NULL值
-            Ok(mysql::Value::NULL) => {
-                return Ok("NULL".to_string());
-            }
-            // 处理字符串值 - 需要转义
-            Ok(mysql::Value::Bytes(bytes)) => {
-                let s = match String::from_utf8(bytes.clone()) {
-                    Ok(s) => s,
-                    Err(_) => {
-                        // 处理二进制数据
-                        return Ok(format!("0x{}", hex::encode(&bytes)));
-                    }
-                };
+// 备份存储过程和函数
+fn backup_routines(
+    conn: &mut PooledConn,
+    output_file: &mut dyn Write,
+    routines: &[(String, String)],
+) -> Result<(), String> {
+    for (name, routine_type) in routines {
+        let (keyword, column, label) = if routine_type == "FUNCTION" {
+            ("FUNCTION", "Create Function", "函数")
+        } else {
+            ("PROCEDURE", "Create Procedure", "存储过程")
+        };
 
-                // 转义MySQL字符串
-                let escaped = s.replace("'", "''").replace("\\", "\\\\");
-                return Ok(format!("'{}'", escaped));
-            }
-            // 处理整数值
-            Ok(mysql::Value::Int(i)) => {
-                return Ok(i.to_string());
-            }
-            // 处理无符号整数值
-            Ok(mysql::Value::UInt(u)) => {
-                return Ok(u.to_string());
-            }
-            // 处理浮点数值
-            Ok(mysql::Value::Float(f)) => {
-                return Ok(f.to_string());
-            }
-            // 处理日期时间值
-            Ok(mysql::Value::Date(year, month, day, hour, minute, second, micros)) => {
-                if hour == 0 && minute == 0 && second == 0 && micros == 0 {
-                    // 只有日期
-                    return Ok(format!("'{:04}-{:02}-{:02}'", year, month, day));
-                } else if micros == 0 {
-                    // 日期时间，无微秒
-                    return Ok(format!(
-                        "'{:04}-{:02}-{:02} {:02}:{:02}:{:02}'",
-                        year, month, day, hour, minute, second
-                    ));
-                } else {
-                    // 完整日期时间，带微秒
-                    return Ok(format!(
-                        "'{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:06}'",
-                        year, month, day, hour, minute, second, micros
-                    ));
-                }
+        let (create_sql, sql_mode) = show_create(
+            conn,
+            &format!("SHOW CREATE {} {}", keyword, quote_identifier(name)),
+            column,
+        )?;
+        write_compound_object(
+            output_file,
+            &format!("{}: {}", label, name),
+            &format!("DROP {} IF EXISTS {}", keyword, quote_identifier(name)),
+            &create_sql,
+            sql_mode.as_deref(),
+        )?;
+    }
+
+    Ok(())
+}
+
+// 备份视图定义，按依赖关系排序，被引用的视图先创建
+fn backup_views(
+    conn: &mut PooledConn,
+    output_file: &mut dyn Write,
+    views: &[String],
+) -> Result<(), String> {
+    let mut definitions = Vec::new();
+    for view in views {
+        let (create_sql, _) = show_create(
+            conn,
+            &format!("SHOW CREATE VIEW {}", quote_identifier(view)),
+            "Create View",
+        )?;
+        definitions.push((view.clone(), create_sql));
+    }
+
+    for (view, create_sql) in sort_views_by_dependency(definitions) {
+        if let Err(e) = writeln!(
+            output_file,
+            "\n-- 视图: {}\n\nDROP TABLE IF EXISTS {};\nDROP VIEW IF EXISTS {};\n{};\n",
+            view,
+            quote_identifier(&view),
+            quote_identifier(&view),
+            create_sql
+        ) {
+            return Err(format!("写入文件失败: {}", e));
+        }
+    }
+
+    Ok(())
+}
+
+// 对视图做拓扑排序：定义中引用了其他未输出视图的，延后输出；出现循环时按原顺序输出剩余视图
+fn sort_views_by_dependency(mut pending: Vec<(String, String)>) -> Vec<(String, String)> {
+    let mut sorted = Vec::with_capacity(pending.len());
+
+    while !pending.is_empty() {
+        let ready = pending.iter().position(|(name, definition)| {
+            !pending
+                .iter()
+                .any(|(other, _)| other != name && definition.contains(&quote_identifier(other)))
+        });
+
+        match ready {
+            Some(index) => sorted.push(pending.remove(index)),
+            None => {
+                sorted.append(&mut pending);
+            }
+        }
+    }
+
+    sorted
+}
+
+// 执行SHOW CREATE语句，返回指定列的建立语句及对象创建时的sql_mode
+fn show_create(
+    conn: &mut PooledConn,
+    sql: &str,
+    column: &str,
+) -> Result<(String, Option<String>), String> {
+    let row = match conn.query_first::<mysql::Row, _>(sql) {
+        Ok(Some(row)) => row,
+        Ok(None) => return Err(format!("执行 {} 没有返回结果", sql)),
+        Err(e) => return Err(format!("执行 {} 失败: {}", sql, e)),
+    };
+
+    // 没有权限查看定义时该列为NULL
+    let create_sql = match row.get::<Option<String>, _>(column) {
+        Some(Some(create_sql)) => create_sql,
+        _ => return Err(format!("无法获取 {} 的定义，请检查账号权限", sql)),
+    };
+    let sql_mode = row.get::<Option<String>, _>("sql_mode").flatten();
+
+    Ok((create_sql, sql_mode))
+}
+
+// 写入包含语句体的对象（存储过程、触发器、事件），使用DELIMITER包裹以便mysql客户端正确解析
+fn write_compound_object(
+    output_file: &mut dyn Write,
+    label: &str,
+    drop_sql: &str,
+    create_sql: &str,
+    sql_mode: Option<&str>,
+) -> Result<(), String> {
+    let mut content = format!("\n-- {}\n\n{};\n", label, drop_sql);
+    if let Some(sql_mode) = sql_mode {
+        content.push_str(&format!(
+            "SET @saved_sql_mode = @@sql_mode;\nSET sql_mode = '{}';\n",
+            sql_mode
+        ));
+    }
+    content.push_str(&format!("DELIMITER ;;\n{} ;;\nDELIMITER ;\n", create_sql));
+    if sql_mode.is_some() {
+        content.push_str("SET sql_mode = @saved_sql_mode;\n");
+    }
+
+    if let Err(e) = write!(output_file, "{}", content) {
+        return Err(format!("写入文件失败: {}", e));
+    }
+
+    Ok(())
+}
+
+// 根据结果集元数据中列的真实类型，把一行中指定列的值转换为SQL字面量
+fn get_escaped_value(
+    row: &mysql::Row,
+    index: usize,
+    raw_charset: Option<&str>,
+    no_backslash_escapes: bool,
+) -> Result<String, String> {
+    let column = match row.columns_ref().get(index) {
+        Some(column) => column,
+        None => return Err(format!("列 {} 不存在", index)),
+    };
+    let value = match row.as_ref(index) {
+        Some(value) => value,
+        None => return Err(format!("列 {} 的值不可用", column.name_str())),
+    };
+
+    let literal = match value {
+        // 处理NULL值
+        mysql::Value::NULL => "NULL".to_string(),
+        // 处理整数值
+        mysql::Value::Int(i) => i.to_string(),
+        mysql::Value::UInt(u) => u.to_string(),
+        // 浮点数使用科学计数法输出最短的可往返表示，恢复后与原值逐位一致
+        mysql::Value::Float(f) => format!("{:e}", f),
+        mysql::Value::Double(d) => format!("{:e}", d),
+        // 处理日期时间值，DATE列只输出日期部分
+        mysql::Value::Date(year, month, day, hour, minute, second, micros) => {
+            let date_only = matches!(
+                column.column_type(),
+                ColumnType::MYSQL_TYPE_DATE | ColumnType::MYSQL_TYPE_NEWDATE
+            );
+            if date_only {
+                format!("'{:04}-{:02}-{:02}'", year, month, day)
+            } else if *micros == 0 {
+                format!(
+                    "'{:04}-{:02}-{:02} {:02}:{:02}:{:02}'",
+                    year, month, day, hour, minute, second
+                )
+            } else {
+                format!(
+                    "'{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:06}'",
+                    year, month, day, hour, minute, second, micros
+                )
+            }
+        }
+        // 处理时间值，天数折算为小时
+        mysql::Value::Time(neg, days, hours, minutes, seconds, micros) => {
+            let sign = if *neg { "-" } else { "" };
+            let hours = *hours as u32 + (*days * 24);
+            if *micros == 0 {
+                format!("'{}{:02}:{:02}:{:02}'", sign, hours, minutes, seconds)
+            } else {
+                format!(
+                    "'{}{:02}:{:02}:{:02}.{:06}'",
+                    sign, hours, minutes, seconds, micros
+                )
+            }
+        }
+        mysql::Value::Bytes(bytes) => match raw_charset {
+            // 按原始字节读取的文本列
+            Some(charset) => format_raw_text_value(bytes, charset, no_backslash_escapes),
+            None => format_bytes_value(bytes, column, no_backslash_escapes),
+        },
+    };
+
+    Ok(literal)
+}
+
+// 非UTF-8字符集的文本值：纯ASCII内容直接输出，否则带字符集引导符按十六进制输出，
+// 恢复时由服务器按原字符集解释，不经过客户端字符集转换
+fn format_raw_text_value(bytes: &[u8], charset: &str, no_backslash_escapes: bool) -> String {
+    if bytes.is_empty() {
+        return "''".to_string();
+    }
+    if bytes.is_ascii() && !WIDE_CHARSETS.contains(&charset) {
+        return escape_string_literal(&String::from_utf8_lossy(bytes), no_backslash_escapes);
+    }
+    format!("_{} 0x{}", charset, hex::encode(bytes))
+}
+
+// 按列类型转换以字节形式返回的值
+fn format_bytes_value(bytes: &[u8], column: &mysql::Column, no_backslash_escapes: bool) -> String {
+    match column.column_type() {
+        // DECIMAL保持服务器返回的精确十进制文本
+        ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL => {
+            let is_numeric = !bytes.is_empty()
+                && bytes
+                    .iter()
+                    .all(|&b| b.is_ascii_digit() || matches!(b, b'.' | b'-' | b'+' | b'e' | b'E'));
+            if is_numeric {
+                String::from_utf8_lossy(bytes).to_string()
+            } else {
+                escape_string_literal(&String::from_utf8_lossy(bytes), no_backslash_escapes)
+            }
+        }
+        // 文本协议下的数值列直接输出
+        ColumnType::MYSQL_TYPE_TINY
+        | ColumnType::MYSQL_TYPE_SHORT
+        | ColumnType::MYSQL_TYPE_LONG
+        | ColumnType::MYSQL_TYPE_INT24
+        | ColumnType::MYSQL_TYPE_LONGLONG
+        | ColumnType::MYSQL_TYPE_YEAR
+        | ColumnType::MYSQL_TYPE_FLOAT
+        | ColumnType::MYSQL_TYPE_DOUBLE => String::from_utf8_lossy(bytes).to_string(),
+        // BIT输出为位串字面量
+        ColumnType::MYSQL_TYPE_BIT => {
+            let bits: String = bytes.iter().map(|b| format!("{:08b}", b)).collect();
+            let trimmed = bits.trim_start_matches('0');
+            format!("b'{}'", if trimmed.is_empty() { "0" } else { trimmed })
+        }
+        // JSON的元数据字符集为binary，但内容是UTF-8文本
+        ColumnType::MYSQL_TYPE_JSON => {
+            escape_string_literal(&String::from_utf8_lossy(bytes), no_backslash_escapes)
+        }
+        // GEOMETRY的内部格式为4字节小端SRID加WKB
+        ColumnType::MYSQL_TYPE_GEOMETRY if bytes.len() >= 4 => {
+            let srid = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            let wkb = hex::encode(&bytes[4..]);
+            if srid == 0 {
+                format!("ST_GeomFromWKB(0x{}, 0)", wkb)
+            } else {
+                // 内部格式按经度-纬度存储，MySQL 8.0对地理坐标系默认按纬度-经度解析
+                format!(
+                    "ST_GeomFromWKB(0x{}, {} /*!80011 , 'axis-order=long-lat' */)",
+                    wkb, srid
+                )
             }
-            // 处理时间值
-            Ok(mysql::Value::Time(neg, days, hours, minutes, seconds, micros)) => {
-                let sign = if neg { "-" } else { "" };
-                if micros == 0 {
-                    return Ok(format!(
-                        "'{}{:02}:{:02}:{:02}'",
-                        sign,
-                        hours as u32 + (days as u32 * 24),
-                        minutes,
-                        seconds
-                    ));
+        }
+        _ => {
+            // 字符集为binary的列（BINARY、VARBINARY、BLOB）一律按十六进制输出
+            if column.character_set() == BINARY_CHARSET {
+                if bytes.is_empty() {
+                    "''".to_string()
                 } else {
-                    return Ok(format!(
-                        "'{}{:02}:{:02}:{:02}.{:06}'",
-                        sign,
-                        hours as u32 + (days as u32 * 24),
-                        minutes,
-                        seconds,
-                        micros
-                    ));
+                    format!("0x{}", hex::encode(bytes))
+                }
+            } else {
+                match std::str::from_utf8(bytes) {
+                    Ok(s) => escape_string_literal(s, no_backslash_escapes),
+                    Err(_) => format!("_binary 0x{}", hex::encode(bytes)),
                 }
             }
-            // 其他类型，转为字符串处理
-            Ok(_) => {
-                return Ok("NULL".to_string());
-            }
-            // 错误处理
-            Err(e) => {
-                return Err(format!("获取列值失败: {}", e));
+        }
+    }
+}
+
+// 生成带单引号的字符串字面量。NO_BACKSLASH_ESCAPES模式下反斜杠没有转义含义，只能把单引号加倍
+fn escape_string_literal(s: &str, no_backslash_escapes: bool) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('\'');
+
+    for c in s.chars() {
+        if no_backslash_escapes {
+            if c == '\'' {
+                escaped.push_str("''");
+            } else {
+                escaped.push(c);
             }
+            continue;
+        }
+
+        match c {
+            '\0' => escaped.push_str("\\0"),
+            '\'' => escaped.push_str("\\'"),
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\x1a' => escaped.push_str("\\Z"),
+            _ => escaped.push(c),
         }
-    } else {
-        return Err(format!("列 {} 不存在", column_name));
     }
+
+    escaped.push('\'');
+    escaped
 }
 
 #[command]
@@ -846,14 +2917,14 @@ fn cleanup_old_backups_impl(backup_dir: &str, keep_days: i32) -> Result<usize, S
         let entry = entry_result.map_err(|e| format!("读取目录项失败: {}", e))?;
         let file_path = entry.path();
 
-        // 只处理文件且文件名以BACKUP_开头且以.zip结尾的文件
+        // 只处理文件名以BACKUP_开头、扩展名为支持的备份格式的文件
         if file_path.is_file() {
             let file_name = match file_path.file_name() {
                 Some(name) => name.to_string_lossy().to_string(),
                 None => continue,
             };
 
-            if file_name.starts_with("BACKUP_") && file_name.ends_with(".zip") {
+            if archive::is_backup_file_name(&file_name) {
                 // 获取文件修改时间
                 let metadata = match fs::metadata(&file_path) {
                     Ok(meta) => meta,
@@ -871,6 +2942,12 @@ fn cleanup_old_backups_impl(backup_dir: &str, keep_days: i32) -> Result<usize, S
                         Ok(_) => {
                             println!("已删除过期备份文件: {:?}", file_path);
                             deleted_count += 1;
+
+                            // 单文件SQL格式的清单与备份文件一起删除
+                            let manifest_path = archive::sidecar_manifest_path(&file_path);
+                            if manifest_path.exists() {
+                                let _ = fs::remove_file(&manifest_path);
+                            }
                         }
                         Err(e) => {
                             eprintln!("删除文件 {:?} 失败: {}", file_path, e);
@@ -878,6 +2955,27 @@ fn cleanup_old_backups_impl(backup_dir: &str, keep_days: i32) -> Result<usize, S
                     }
                 }
             }
+        } else if file_path.is_dir() {
+            // 多数据库分别归档时每次备份保存在一个以BACKUP_开头的目录中
+            let is_backup_dir = file_path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("BACKUP_"));
+            let modified_time = match fs::metadata(&file_path).and_then(|meta| meta.modified()) {
+                Ok(time) => time,
+                Err(_) => continue,
+            };
+
+            if is_backup_dir && modified_time <= cutoff_time {
+                match fs::remove_dir_all(&file_path) {
+                    Ok(_) => {
+                        println!("已删除过期备份目录: {:?}", file_path);
+                        deleted_count += 1;
+                    }
+                    Err(e) => {
+                        eprintln!("删除目录 {:?} 失败: {}", file_path, e);
+                    }
+                }
+            }
         }
     }
 
@@ -887,7 +2985,21 @@ fn cleanup_old_backups_impl(backup_dir: &str, keep_days: i32) -> Result<usize, S
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 #[allow(deprecated)]
 pub fn run() {
-    let mut builder = tauri::Builder::default().manage(BackupState::default()); // 注册备份状态管理
+    let mut builder = tauri::Builder::default()
+        .manage(jobs::JobRegistry::default()) // 注册备份任务管理
+        .manage(restore::RestoreState::default()) // 注册恢复状态管理
+        .manage(scheduler::Scheduler::default()) // 注册定时备份调度器
+        .manage(profiles::ProfileStore::default()) // 注册连接配置和备份作业
+        .setup(|app| {
+            if let Ok(dir) = app.path().app_data_dir() {
+                if let Err(e) = app.state::<profiles::ProfileStore>().load(&dir) {
+                    eprintln!("{}", e);
+                }
+            }
+            // 启动后端的定时备份，不依赖前端页面
+            scheduler::start(app.handle().clone());
+            Ok(())
+        });
 
     #[cfg(desktop)]
     {
@@ -910,11 +3022,253 @@ pub fn run() {
             .plugin(tauri_plugin_process::init())
             .invoke_handler(tauri::generate_handler![
                 backup_mysql,
+                start_backup,
+                wait_backup_job,
+                list_backup_jobs,
+                get_backup_job,
+                get_backup_job_limits,
+                set_backup_job_limits,
+                cancel_backup,
+                find_resumable_backup,
+                get_backup_schedule,
+                set_backup_schedule,
+                preview_backup_schedule,
+                list_connection_profiles,
+                save_connection_profile,
+                delete_connection_profile,
+                list_saved_jobs,
+                create_saved_job,
+                update_saved_job,
+                delete_saved_job,
+                run_saved_job,
                 check_mysqldump_availability,
-                cleanup_old_backups
+                cleanup_old_backups,
+                restore::restore_mysql,
+                restore::cancel_restore,
+                pitr::preview_point_in_time_recovery,
+                pitr::point_in_time_recovery,
+                verify::verify_backup,
+                verify::deep_verify_backup
             ])
     }
     builder
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(column_type: ColumnType, character_set: u16) -> mysql::Column {
+        mysql::Column::new(column_type).with_character_set(character_set)
+    }
+
+    #[test]
+    fn quote_identifier_doubles_backticks() {
+        assert_eq!(quote_identifier("orders"), "`orders`");
+        assert_eq!(quote_identifier("a`b"), "`a``b`");
+    }
+
+    #[test]
+    fn chunk_work_items_skip_structure_in_data_only_mode() {
+        let columns = vec!["id".to_string()];
+        let boundaries = vec![vec![mysql::Value::Int(1001)]];
+        let items = chunk_work_items(
+            "orders",
+            &columns,
+            boundaries,
+            manifest::BackupMode::DataOnly,
+        );
+
+        assert_eq!(
+            items
+                .iter()
+                .map(|item| item.entry_name.as_str())
+                .collect::<Vec<_>>(),
+            ["table_orders.part0001.sql", "table_orders.part0002.sql"]
+        );
+        assert!(items
+            .iter()
+            .all(|item| matches!(item.kind, TableWorkKind::Chunk(_))));
+    }
+
+    #[test]
+    fn chunk_work_items_cover_key_space_without_gaps() {
+        let columns = vec!["id".to_string()];
+        let boundaries = vec![vec![mysql::Value::Int(1001)], vec![mysql::Value::Int(2001)]];
+        let items = chunk_work_items("orders", &columns, boundaries, manifest::BackupMode::Full);
+
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[0].entry_name, "table_orders.sql");
+        assert!(matches!(items[0].kind, TableWorkKind::Structure));
+
+        // 两个边界切成三段：首段没有下界，末段没有上界，相邻分块的上下界相同
+        let ranges: Vec<&KeyRange> = items[1..]
+            .iter()
+            .map(|item| match &item.kind {
+                TableWorkKind::Chunk(range) => range,
+                _ => panic!("应为数据分块"),
+            })
+            .collect();
+        assert_eq!(
+            items[1..]
+                .iter()
+                .map(|item| item.entry_name.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "table_orders.part0001.sql",
+                "table_orders.part0002.sql",
+                "table_orders.part0003.sql"
+            ]
+        );
+        assert_eq!(ranges[0].lower, None);
+        assert_eq!(ranges[0].upper, Some(vec![mysql::Value::Int(1001)]));
+        assert_eq!(ranges[1].lower, ranges[0].upper);
+        assert_eq!(ranges[1].upper, Some(vec![mysql::Value::Int(2001)]));
+        assert_eq!(ranges[2].lower, ranges[1].upper);
+        assert_eq!(ranges[2].upper, None);
+        assert_eq!(
+            ranges.iter().map(|range| range.part).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn key_range_condition_uses_row_constructor() {
+        let range = KeyRange {
+            columns: vec!["tenant".to_string(), "id".to_string()],
+            lower: Some(vec![mysql::Value::Int(1), mysql::Value::Int(500)]),
+            upper: Some(vec![mysql::Value::Int(2), mysql::Value::Int(10)]),
+            part: 2,
+        };
+        let (condition, params) = range.condition();
+        assert_eq!(
+            condition,
+            "(`tenant`, `id`) >= (?, ?) AND (`tenant`, `id`) < (?, ?)"
+        );
+        assert_eq!(
+            params,
+            [
+                mysql::Value::Int(1),
+                mysql::Value::Int(500),
+                mysql::Value::Int(2),
+                mysql::Value::Int(10)
+            ]
+        );
+        assert_eq!(range.order_by(), "`tenant`, `id`");
+
+        let unbounded = KeyRange {
+            columns: vec!["id".to_string()],
+            lower: None,
+            upper: None,
+            part: 1,
+        };
+        assert_eq!(unbounded.condition(), ("1 = 1".to_string(), Vec::new()));
+    }
+
+    #[test]
+    fn validate_workers_limits_connection_count() {
+        let mut options = BackupOptions::default();
+        assert!(options.validate_workers().is_ok());
+        options.workers = MAX_WORKERS;
+        assert!(options.validate_workers().is_ok());
+        options.workers = MAX_WORKERS + 1;
+        assert!(options.validate_workers().is_err());
+    }
+
+    #[test]
+    fn is_generated_column_skips_only_generated_columns() {
+        assert!(is_generated_column("VIRTUAL GENERATED"));
+        assert!(is_generated_column("STORED GENERATED"));
+        assert!(is_generated_column("PERSISTENT"));
+        // 表达式默认值和自增列需要备份
+        assert!(!is_generated_column("DEFAULT_GENERATED"));
+        assert!(!is_generated_column(
+            "DEFAULT_GENERATED on update CURRENT_TIMESTAMP"
+        ));
+        assert!(!is_generated_column("auto_increment"));
+        assert!(!is_generated_column(""));
+    }
+
+    #[test]
+    fn escape_string_literal_uses_backslash_escapes() {
+        assert_eq!(
+            escape_string_literal("it's \\ \"q\"\n\r\0\x1a", false),
+            "'it\\'s \\\\ \\\"q\\\"\\n\\r\\0\\Z'"
+        );
+        assert_eq!(escape_string_literal("中文", false), "'中文'");
+    }
+
+    #[test]
+    fn escape_string_literal_doubles_quotes_without_backslash_escapes() {
+        assert_eq!(escape_string_literal("it's a\\b\n", true), "'it''s a\\b\n'");
+    }
+
+    #[test]
+    fn format_bytes_value_keeps_exact_decimals() {
+        let decimal = column(ColumnType::MYSQL_TYPE_NEWDECIMAL, BINARY_CHARSET);
+        assert_eq!(format_bytes_value(b"-123.450", &decimal, false), "-123.450");
+        assert_eq!(format_bytes_value(b"1e5", &decimal, false), "1e5");
+        assert_eq!(format_bytes_value(b"1;x", &decimal, false), "'1;x'");
+    }
+
+    #[test]
+    fn format_bytes_value_writes_numbers_and_bits() {
+        let long = column(ColumnType::MYSQL_TYPE_LONG, BINARY_CHARSET);
+        assert_eq!(format_bytes_value(b"42", &long, false), "42");
+
+        let bit = column(ColumnType::MYSQL_TYPE_BIT, BINARY_CHARSET);
+        assert_eq!(format_bytes_value(&[0x00, 0x05], &bit, false), "b'101'");
+        assert_eq!(format_bytes_value(&[0x00], &bit, false), "b'0'");
+    }
+
+    #[test]
+    fn format_bytes_value_writes_json_as_text() {
+        let json = column(ColumnType::MYSQL_TYPE_JSON, BINARY_CHARSET);
+        assert_eq!(
+            format_bytes_value(br#"{"a": "b'c"}"#, &json, false),
+            r#"'{\"a\": \"b\'c\"}'"#
+        );
+    }
+
+    #[test]
+    fn format_bytes_value_writes_geometry_with_srid() {
+        let geometry = column(ColumnType::MYSQL_TYPE_GEOMETRY, BINARY_CHARSET);
+        assert_eq!(
+            format_bytes_value(&[0, 0, 0, 0, 0x01, 0x02], &geometry, false),
+            "ST_GeomFromWKB(0x0102, 0)"
+        );
+        assert_eq!(
+            format_bytes_value(&[0xE6, 0x10, 0, 0, 0x01], &geometry, false),
+            "ST_GeomFromWKB(0x01, 4326 /*!80011 , 'axis-order=long-lat' */)"
+        );
+    }
+
+    #[test]
+    fn format_bytes_value_writes_binary_as_hex() {
+        let blob = column(ColumnType::MYSQL_TYPE_BLOB, BINARY_CHARSET);
+        assert_eq!(format_bytes_value(&[0xFF, 0x00], &blob, false), "0xff00");
+        assert_eq!(format_bytes_value(&[], &blob, false), "''");
+
+        // utf8mb4_general_ci
+        let text = column(ColumnType::MYSQL_TYPE_VAR_STRING, 45);
+        assert_eq!(format_bytes_value(b"a'b", &text, true), "'a''b'");
+        assert_eq!(format_bytes_value(&[0xFF], &text, false), "_binary 0xff");
+    }
+
+    #[test]
+    fn format_raw_text_value_keeps_original_charset() {
+        assert_eq!(format_raw_text_value(&[], "latin1", false), "''");
+        assert_eq!(format_raw_text_value(b"it's", "latin1", false), "'it\\'s'");
+        assert_eq!(
+            format_raw_text_value(&[0xE9], "latin1", false),
+            "_latin1 0xe9"
+        );
+        // 双字节字符集中的ASCII字符也不是单字节文本
+        assert_eq!(
+            format_raw_text_value(&[0x00, 0x41], "ucs2", false),
+            "_ucs2 0x0041"
+        );
+    }
+}
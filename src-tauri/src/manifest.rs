@@ -0,0 +1,475 @@
+// 备份清单：记录备份的来源、内容和每个条目的SHA-256，作为manifest.json写入归档
+use crate::binlog::BinlogPosition;
+use crate::filter::TableFilter;
+use crate::quote_identifier;
+use crate::restore::{
+    classify_statement, count_insert_rows, SqlStatementReader, StatementKind, TableAction,
+};
+use mysql::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::{self, BufReader, Read, Seek, Write};
+use std::path::Path;
+use zip::write::{FileOptions, ZipWriter};
+use zip::ZipArchive;
+
+// 清单在归档中的条目名
+pub const MANIFEST_ENTRY: &str = "manifest.json";
+
+// 清单格式版本，字段发生不兼容变化时递增
+const MANIFEST_FORMAT_VERSION: u32 = 1;
+
+// 备份包含的内容
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupMode {
+    // 表结构、数据以及视图、存储过程、触发器和事件
+    #[default]
+    Full,
+    // 只有表结构以及视图、存储过程、触发器和事件
+    SchemaOnly,
+    // 只有表数据，恢复到已有的表结构中
+    DataOnly,
+}
+
+impl BackupMode {
+    pub fn includes_schema(self) -> bool {
+        self != BackupMode::DataOnly
+    }
+
+    pub fn includes_data(self) -> bool {
+        self != BackupMode::SchemaOnly
+    }
+
+    // 不是完整备份时的说明，用于恢复和校验时提示
+    pub fn description(self) -> Option<&'static str> {
+        match self {
+            BackupMode::Full => None,
+            BackupMode::SchemaOnly => Some("备份只包含表结构，没有数据"),
+            BackupMode::DataOnly => Some("备份只包含数据，需要恢复到已有的表结构中"),
+        }
+    }
+}
+
+// 备份清单
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BackupManifest {
+    pub format_version: u32,
+    // 生成备份的工具版本
+    pub tool_version: String,
+    // 备份引擎："builtin"或"mysqldump"
+    pub engine: String,
+    pub database: String,
+    pub server_version: String,
+    pub sql_mode: String,
+    // 数据库的默认字符集和排序规则
+    pub character_set: String,
+    pub collation: String,
+    // RFC 3339格式的开始和完成时间
+    pub started_at: String,
+    pub finished_at: String,
+    // 加密方式："age-scrypt"（密码）或"age-x25519"（公钥），未加密时为null
+    #[serde(default)]
+    pub encryption: Option<String>,
+    // 可续传备份每次继续的时间，不为空时各表数据不属于同一个快照
+    #[serde(default)]
+    pub resumed_at: Vec<String>,
+    // 多数据库备份中的数据库，每个数据库的条目在同名目录中，表名带数据库名前缀
+    #[serde(default)]
+    pub databases: Vec<String>,
+    // 备份时使用的表过滤条件，不为空时备份只包含部分表或部分行
+    #[serde(default)]
+    pub table_filter: Option<TableFilter>,
+    // 备份包含的内容，旧版本的备份都是完整备份
+    #[serde(default)]
+    pub mode: BackupMode,
+    // 快照对应的binlog位置和GTID集合，未记录时为null
+    #[serde(default)]
+    pub binlog: Option<BinlogPosition>,
+    pub tables: Vec<ManifestTable>,
+    // 除清单外每个条目的大小和校验值
+    pub entries: Vec<ManifestEntry>,
+}
+
+// 清单中的表或视图
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ManifestTable {
+    pub name: String,
+    // "table"或"view"
+    pub kind: String,
+    // 备份中INSERT语句包含的行数，视图为null
+    pub rows: Option<u64>,
+    // 备份时在快照内记录的CHECKSUM TABLE结果，未记录时为null
+    #[serde(default)]
+    pub checksum: Option<u64>,
+}
+
+// 清单中的归档条目
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ManifestEntry {
+    pub name: String,
+    // 解压后的大小
+    pub size: u64,
+    // 解压后内容的SHA-256，十六进制小写
+    pub sha256: String,
+}
+
+// 备份时从服务器读取的信息
+pub struct ServerInfo {
+    pub version: String,
+    pub sql_mode: String,
+    pub character_set: String,
+    pub collation: String,
+    pub tables: Vec<String>,
+    pub views: Vec<String>,
+    // 备份时记录的每张表的CHECKSUM TABLE结果
+    pub checksums: BTreeMap<String, u64>,
+    // 可续传备份每次继续的时间
+    pub resumed_at: Vec<String>,
+    // 多数据库备份中的数据库
+    pub databases: Vec<String>,
+    // 备份时使用的表过滤条件
+    pub table_filter: Option<TableFilter>,
+    pub mode: BackupMode,
+    // 快照对应的binlog位置
+    pub binlog: Option<BinlogPosition>,
+    // 写入归档时记录的条目大小和校验值，按写入顺序排列
+    pub entries: Vec<ManifestEntry>,
+    // 写入归档时统计的每张表INSERT语句中的行数
+    pub row_counts: BTreeMap<String, u64>,
+}
+
+// 读取服务器版本、会话sql_mode、数据库字符集以及表和视图列表
+pub fn read_server_info<C: Queryable>(conn: &mut C, database: &str) -> Result<ServerInfo, String> {
+    let (version, sql_mode): (String, String) = conn
+        .query_first("SELECT VERSION(), @@SESSION.sql_mode")
+        .map_err(|e| format!("获取服务器信息失败: {}", e))?
+        .ok_or_else(|| "获取服务器信息失败".to_string())?;
+
+    let charset: Option<(String, String)> = conn
+        .exec_first(
+            "SELECT DEFAULT_CHARACTER_SET_NAME, DEFAULT_COLLATION_NAME \
+             FROM information_schema.SCHEMATA WHERE SCHEMA_NAME = ?",
+            (database,),
+        )
+        .map_err(|e| format!("获取数据库字符集失败: {}", e))?;
+    let (character_set, collation) = charset.unwrap_or_default();
+
+    // 获取所有表和视图，视图只导出定义，不导出数据
+    let full_tables: Vec<(String, String)> = conn
+        .query(format!(
+            "SHOW FULL TABLES FROM {}",
+            quote_identifier(database)
+        ))
+        .map_err(|e| format!("获取表列表失败: {}", e))?;
+
+    let mut tables = Vec::new();
+    let mut views = Vec::new();
+    for (name, table_type) in full_tables {
+        if table_type == "VIEW" {
+            views.push(name);
+        } else {
+            tables.push(name);
+        }
+    }
+
+    Ok(ServerInfo {
+        version,
+        sql_mode,
+        character_set,
+        collation,
+        tables,
+        views,
+        checksums: BTreeMap::new(),
+        resumed_at: Vec::new(),
+        databases: Vec::new(),
+        table_filter: None,
+        mode: BackupMode::Full,
+        binlog: None,
+        entries: Vec::new(),
+        row_counts: BTreeMap::new(),
+    })
+}
+
+// 多数据库备份中带数据库名前缀的表名
+pub fn qualified_table_name(database: &str, table: &str) -> String {
+    format!("{}.{}", database, table)
+}
+
+// 清单和校验中使用的表名：多数据库备份的条目在数据库目录中，表名加上目录名前缀
+pub fn table_key(entry_name: &str, table: &str) -> String {
+    match entry_name.rsplit_once('/') {
+        Some((database, _)) => qualified_table_name(database, table),
+        None => table.to_string(),
+    }
+}
+
+// 用写入时记录的条目校验值和行数生成清单，追加到已完成的归档末尾
+pub fn append_manifest(
+    output_path: &Path,
+    engine: &str,
+    database: &str,
+    server_info: ServerInfo,
+    started_at: String,
+    encryption: Option<&str>,
+) -> Result<BackupManifest, String> {
+    let finished_at = chrono::Local::now().to_rfc3339();
+    let row_counts = server_info.row_counts;
+
+    let mut tables = Vec::with_capacity(server_info.tables.len() + server_info.views.len());
+    for table in server_info.tables {
+        let rows = row_counts.get(&table).copied().unwrap_or(0);
+        let checksum = server_info.checksums.get(&table).copied();
+        tables.push(ManifestTable {
+            name: table,
+            kind: "table".to_string(),
+            rows: Some(rows),
+            checksum,
+        });
+    }
+    for view in server_info.views {
+        tables.push(ManifestTable {
+            name: view,
+            kind: "view".to_string(),
+            rows: None,
+            checksum: None,
+        });
+    }
+
+    let manifest = BackupManifest {
+        format_version: MANIFEST_FORMAT_VERSION,
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        engine: engine.to_string(),
+        database: database.to_string(),
+        server_version: server_info.version,
+        sql_mode: server_info.sql_mode,
+        character_set: server_info.character_set,
+        collation: server_info.collation,
+        started_at,
+        finished_at,
+        encryption: encryption.map(|s| s.to_string()),
+        resumed_at: server_info.resumed_at,
+        databases: server_info.databases,
+        table_filter: server_info.table_filter,
+        mode: server_info.mode,
+        binlog: server_info.binlog,
+        tables,
+        entries: server_info.entries,
+    };
+
+    let json =
+        serde_json::to_vec_pretty(&manifest).map_err(|e| format!("生成备份清单失败: {}", e))?;
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(output_path)
+        .map_err(|e| format!("打开ZIP文件失败: {}", e))?;
+    let mut zip = ZipWriter::new_append(file).map_err(|e| format!("打开ZIP文件失败: {}", e))?;
+
+    let options = FileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .unix_permissions(0o644);
+    zip.start_file(MANIFEST_ENTRY, options)
+        .map_err(|e| format!("添加备份清单到ZIP失败: {}", e))?;
+    zip.write_all(&json)
+        .map_err(|e| format!("写入备份清单失败: {}", e))?;
+    zip.finish()
+        .map_err(|e| format!("完成ZIP文件失败: {}", e))?;
+
+    Ok(manifest)
+}
+
+// 把SQL从reader复制到writer，同时统计每张表INSERT语句中的行数，累加到row_counts
+pub fn copy_counting_rows<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    row_counts: &mut BTreeMap<String, u64>,
+) -> io::Result<()> {
+    let mut statements = SqlStatementReader::new(BufReader::new(TeeReader {
+        inner: reader,
+        output: writer,
+    }));
+    while let Some(statement) = statements.next_statement()? {
+        if let StatementKind::Table {
+            name,
+            action: TableAction::Data,
+        } = classify_statement(&statement)
+        {
+            let rows = count_insert_rows(&statement, statements.statement_no_backslash_escapes());
+            if rows > 0 {
+                *row_counts.entry(name).or_insert(0) += rows;
+            }
+        }
+    }
+    Ok(())
+}
+
+// 读取的内容同时写入output
+struct TeeReader<R, W> {
+    inner: R,
+    output: W,
+}
+
+impl<R: Read, W: Write> Read for TeeReader<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.output.write_all(&buf[..n])?;
+        Ok(n)
+    }
+}
+
+// 边读取边计算SHA-256的读取器
+pub struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl<R> HashingReader<R> {
+    pub fn new(inner: R) -> Self {
+        HashingReader {
+            inner,
+            hasher: Sha256::new(),
+            size: 0,
+        }
+    }
+
+    // 已读取的字节数
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    // 已读取内容的SHA-256，十六进制小写
+    pub fn hex_digest(self) -> String {
+        hex::encode(self.hasher.finalize())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.size += n as u64;
+        Ok(n)
+    }
+}
+
+// 边写入边计算SHA-256的写入器
+pub struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl<W> HashingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        HashingWriter {
+            inner,
+            hasher: Sha256::new(),
+            size: 0,
+        }
+    }
+
+    // 已写入的字节数
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    // 已写入内容的SHA-256，十六进制小写
+    pub fn hex_digest(self) -> String {
+        hex::encode(self.hasher.finalize())
+    }
+
+    // 以已写入的内容作为名为name的归档条目
+    pub fn into_entry(self, name: &str) -> ManifestEntry {
+        ManifestEntry {
+            name: name.to_string(),
+            size: self.size,
+            sha256: self.hex_digest(),
+        }
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.size += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl BackupManifest {
+    // 内置引擎在备份会话的sql_mode包含NO_BACKSLASH_ESCAPES时只用加倍单引号转义字符串，
+    // mysqldump的连接总是清空sql_mode，输出使用反斜杠转义
+    pub fn no_backslash_escapes(&self) -> bool {
+        self.engine == "builtin" && self.sql_mode.contains("NO_BACKSLASH_ESCAPES")
+    }
+
+    // 备份不完整时的说明：只有结构或数据，或者只包含部分表或部分行
+    pub fn partial_notes(&self) -> Vec<String> {
+        let mut notes = Vec::new();
+        if let Some(description) = self.mode.description() {
+            notes.push(description.to_string());
+        }
+        if let Some(filter) = &self.table_filter {
+            notes.push(format!("备份只包含部分数据：{}", filter.describe()));
+        }
+        notes
+    }
+}
+
+// 读取归档中的清单，旧版本生成的归档没有清单时返回None
+pub fn read_manifest<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+) -> Result<Option<BackupManifest>, String> {
+    let mut entry = match archive.by_name(MANIFEST_ENTRY) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(format!("读取备份清单失败: {}", e)),
+    };
+
+    let mut content = Vec::new();
+    entry
+        .read_to_end(&mut content)
+        .map_err(|e| format!("读取备份清单失败: {}", e))?;
+    parse_manifest(&content).map(Some)
+}
+
+// 解析清单内容
+pub fn parse_manifest(content: &[u8]) -> Result<BackupManifest, String> {
+    serde_json::from_slice(content).map_err(|e| format!("解析备份清单失败: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_counting_rows_copies_input_and_counts_insert_rows() {
+        let sql = "-- MySQL dump\n\
+                   CREATE TABLE `t1` (`id` int);\n\
+                   INSERT INTO `t1` VALUES (1,'a;b'),(2,'c');\n\
+                   INSERT INTO `t1` VALUES (3,'d');\n\
+                   INSERT INTO `t2` VALUES (1,')');\n\
+                   /*!40000 ALTER TABLE `t2` ENABLE KEYS */;\n";
+        let mut output = HashingWriter::new(Vec::new());
+        let mut row_counts = BTreeMap::new();
+        copy_counting_rows(sql.as_bytes(), &mut output, &mut row_counts).unwrap();
+
+        let entry = output.into_entry("dump.sql");
+        assert_eq!(entry.name, "dump.sql");
+        assert_eq!(entry.size, sql.len() as u64);
+        assert_eq!(entry.sha256, hex::encode(Sha256::digest(sql.as_bytes())));
+        assert_eq!(row_counts.get("t1"), Some(&3));
+        assert_eq!(row_counts.get("t2"), Some(&1));
+        assert_eq!(row_counts.len(), 2);
+    }
+}
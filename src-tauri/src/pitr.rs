@@ -0,0 +1,1653 @@
+// 按时间点恢复：恢复备份后用mysqlbinlog读取备份记录的binlog位置之后的事件，
+// 重放到指定的时间、GTID或位置之前。binlog可以通过复制协议从服务器读取，也可以读取本地文件
+use crate::archive;
+use crate::binlog::BinlogPosition;
+use crate::crypto::DecryptionKey;
+use crate::is_command_available;
+use crate::manifest::{BackupManifest, BackupMode};
+use crate::restore::{
+    restore_archive, send_restore_progress, ConnectionInfo, RestoreExecutor, RestoreOptions,
+    RestoreState, SqlStatementReader, RESTORE_CANCELLED,
+};
+use chrono::{NaiveDate, NaiveDateTime};
+use mysql::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp;
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use tauri::{command, State, Window};
+use tokio::sync::mpsc;
+
+// 预览中最多列出的被跳过的事务数
+const PREVIEW_TRANSACTIONS: usize = 50;
+
+// 预览中每个事务最多列出的语句数
+const PREVIEW_STATEMENTS: usize = 20;
+
+// 预览中每条语句最多保留的字符数
+const PREVIEW_STATEMENT_CHARS: usize = 1000;
+
+// 不属于任何事务的binlog事件，例如文件头、GTID列表和文件切换
+const META_EVENTS: &[&str] = &[
+    "Start",
+    "Format_desc",
+    "Previous-GTIDs",
+    "Rotate",
+    "Stop",
+    "Gtid",
+    "Binlog",
+    "Heartbeat",
+];
+
+// 按时间点恢复的选项
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct PitrOptions {
+    // 本地binlog文件所在的目录，为空时通过复制协议从恢复目标服务器读取
+    binlog_dir: Option<String>,
+    // 重放的终点
+    target: PitrTarget,
+    // 恢复备份的选项，按时间点恢复不能只恢复部分表
+    restore: RestoreOptions,
+}
+
+// 重放的终点，只能设置一种，重放到第一个满足条件的事务之前
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct PitrTarget {
+    // 本地时间，格式为YYYY-MM-DD HH:MM:SS，停在这一时间及之后的第一个事务之前
+    datetime: Option<String>,
+    // 停在这个GTID的事务之前
+    gtid: Option<String>,
+    // 停在包含该位置的事务之前，文件为空时使用备份位置所在的文件
+    file: Option<String>,
+    position: Option<u64>,
+}
+
+impl PitrTarget {
+    fn condition(&self, start: &BinlogPosition) -> Result<StopCondition, String> {
+        let datetime = self
+            .datetime
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty());
+        let gtid = self
+            .gtid
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty());
+        let set_count = [datetime.is_some(), gtid.is_some(), self.position.is_some()]
+            .iter()
+            .filter(|set| **set)
+            .count();
+        if set_count == 0 {
+            return Err("请设置重放的终点：时间、GTID或binlog位置".to_string());
+        }
+        if set_count > 1 {
+            return Err("重放的终点只能设置时间、GTID或binlog位置中的一种".to_string());
+        }
+
+        if let Some(datetime) = datetime {
+            return [
+                "%Y-%m-%d %H:%M:%S",
+                "%Y-%m-%dT%H:%M:%S",
+                "%Y-%m-%d %H:%M",
+                "%Y-%m-%dT%H:%M",
+            ]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(datetime, format).ok())
+            .map(StopCondition::Datetime)
+            .ok_or_else(|| format!("无法识别的时间 {}，请使用YYYY-MM-DD HH:MM:SS格式", datetime));
+        }
+        if let Some(gtid) = gtid {
+            return Ok(StopCondition::Gtid(gtid.to_lowercase()));
+        }
+
+        let file = self
+            .file
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .unwrap_or(&start.file);
+        Ok(StopCondition::Position {
+            file: file.to_string(),
+            position: self.position.unwrap_or_default(),
+        })
+    }
+}
+
+// 停止重放的条件
+enum StopCondition {
+    Datetime(NaiveDateTime),
+    Gtid(String),
+    Position { file: String, position: u64 },
+}
+
+impl StopCondition {
+    fn describe(&self) -> String {
+        match self {
+            StopCondition::Datetime(datetime) => datetime.format("%Y-%m-%d %H:%M:%S").to_string(),
+            StopCondition::Gtid(gtid) => format!("GTID {}", gtid),
+            StopCondition::Position { file, position } => format!("{}:{}", file, position),
+        }
+    }
+}
+
+// 读取binlog的位置
+enum BinlogSource {
+    // 通过复制协议从服务器读取，需要REPLICATION SLAVE权限
+    Remote,
+    // 本地目录中的binlog文件
+    Local(PathBuf),
+}
+
+// 恢复前的预览：重放的范围以及被跳过的事务
+#[derive(Serialize)]
+pub struct PitrPreview {
+    // 备份记录的binlog位置，从这里开始重放
+    start: BinlogPosition,
+    // 重放到这个文件中的这个位置之前
+    stop_file: String,
+    stop_position: u64,
+    // 是否找到了终点，没有找到时时间终点重放到现有binlog的末尾，GTID和位置终点不能恢复
+    stop_found: bool,
+    // 将要重放的事务数
+    applied_transactions: usize,
+    // 从终点开始被跳过的事务
+    skipped: Vec<SkippedTransaction>,
+    // 被跳过的事务超过预览数量，没有全部列出
+    more_skipped: bool,
+}
+
+// 预览中被跳过的事务
+#[derive(Serialize)]
+pub struct SkippedTransaction {
+    file: String,
+    position: u64,
+    // 事务开始的本地时间
+    timestamp: Option<String>,
+    gtid: Option<String>,
+    // 语句，行事件为mysqlbinlog解码出的伪SQL
+    statements: Vec<String>,
+}
+
+// binlog中的一个事务，或不在事务中的单条语句
+struct Transaction {
+    file: String,
+    start: u64,
+    end: u64,
+    timestamp: Option<NaiveDateTime>,
+    gtid: Option<String>,
+    statements: Vec<String>,
+    // 超过预览数量没有保留的语句数
+    omitted_statements: usize,
+    // 由BEGIN或START TRANSACTION开始，需要等到提交才结束
+    explicit: bool,
+}
+
+impl Transaction {
+    fn stops_at(&self, condition: &StopCondition) -> bool {
+        match condition {
+            StopCondition::Datetime(datetime) => self
+                .timestamp
+                .is_some_and(|timestamp| timestamp >= *datetime),
+            StopCondition::Gtid(gtid) => self.gtid.as_deref() == Some(gtid.as_str()),
+            StopCondition::Position { file, position } => {
+                match compare_binlog_files(&self.file, file) {
+                    cmp::Ordering::Greater => true,
+                    cmp::Ordering::Equal => self.end > *position,
+                    cmp::Ordering::Less => false,
+                }
+            }
+        }
+    }
+
+    fn add_statement(&mut self, statement: String) {
+        if self.statements.len() >= PREVIEW_STATEMENTS {
+            self.omitted_statements += 1;
+            return;
+        }
+        let mut preview: String = statement.chars().take(PREVIEW_STATEMENT_CHARS).collect();
+        if preview.len() < statement.len() {
+            preview.push_str("...");
+        }
+        self.statements.push(preview);
+    }
+
+    fn into_preview(mut self) -> SkippedTransaction {
+        if self.omitted_statements > 0 {
+            self.statements
+                .push(format!("...（另有{}条语句）", self.omitted_statements));
+        }
+        SkippedTransaction {
+            file: self.file,
+            position: self.start,
+            timestamp: self
+                .timestamp
+                .map(|timestamp| timestamp.format("%Y-%m-%d %H:%M:%S").to_string()),
+            gtid: self.gtid,
+            statements: self.statements,
+        }
+    }
+}
+
+// mysqlbinlog输出中的一个事件
+struct BinlogEvent {
+    position: u64,
+    end: u64,
+    timestamp: Option<NaiveDateTime>,
+    // 事件类型，例如GTID、Query、Write_rows、Xid
+    kind: String,
+    // MariaDB的GTID写在事件头中
+    gtid: Option<String>,
+    lines: Vec<String>,
+}
+
+// 解析mysqlbinlog的文本输出，按事件组合成事务
+struct EventParser {
+    file: String,
+    next_position: Option<u64>,
+    event: Option<BinlogEvent>,
+    transaction: Option<Transaction>,
+}
+
+impl EventParser {
+    fn new(file: &str) -> Self {
+        EventParser {
+            file: file.to_string(),
+            next_position: None,
+            event: None,
+            transaction: None,
+        }
+    }
+
+    // 处理一行输出，返回已经结束的事务
+    fn feed(&mut self, line: &str) -> Vec<Transaction> {
+        let mut finished = Vec::new();
+
+        // 每个事件以“# at 位置”开始，下一行是事件头
+        if let Some(position) = line.strip_prefix("# at ") {
+            if let Some(event) = self.event.take() {
+                self.add_event(event, &mut finished);
+            }
+            self.next_position = position.trim().parse().ok();
+            return finished;
+        }
+        if self.event.is_none() {
+            if let Some(position) = self.next_position.take() {
+                self.event = parse_event_header(line, position);
+            }
+            return finished;
+        }
+        if let Some(event) = self.event.as_mut() {
+            event.lines.push(line.to_string());
+        }
+        finished
+    }
+
+    // 输出结束，返回剩余的事务
+    fn finish(&mut self) -> Vec<Transaction> {
+        let mut finished = Vec::new();
+        if let Some(event) = self.event.take() {
+            self.add_event(event, &mut finished);
+        }
+        finished.extend(self.transaction.take());
+        finished
+    }
+
+    // 把事件加入当前事务，事务结束时放入finished
+    fn add_event(&mut self, event: BinlogEvent, finished: &mut Vec<Transaction>) {
+        if META_EVENTS.contains(&event.kind.as_str()) {
+            return;
+        }
+
+        // GTID事件开始新的事务，没有GTID时第一个事件开始新的事务
+        let starts_transaction = event.kind == "GTID" || event.kind == "Anonymous_GTID";
+        if starts_transaction || self.transaction.is_none() {
+            finished.extend(self.transaction.take());
+            self.transaction = Some(Transaction {
+                file: self.file.clone(),
+                start: event.position,
+                end: event.end,
+                timestamp: event.timestamp,
+                gtid: event.gtid.clone(),
+                statements: Vec::new(),
+                omitted_statements: 0,
+                explicit: false,
+            });
+        }
+        let transaction = match self.transaction.as_mut() {
+            Some(transaction) => transaction,
+            None => return,
+        };
+        transaction.end = event.end;
+
+        let mut last_statement = None;
+        for statement in event_statements(&event.lines) {
+            let upper = statement.to_uppercase();
+            if let Some(gtid) = gtid_next(&statement) {
+                transaction.gtid = Some(gtid);
+            } else if upper == "BEGIN" || upper.starts_with("START TRANSACTION") {
+                transaction.explicit = true;
+            } else if !is_session_statement(&upper) {
+                last_statement = Some(upper);
+                if !matches!(last_statement.as_deref(), Some("COMMIT") | Some("ROLLBACK")) {
+                    transaction.add_statement(statement);
+                }
+            }
+        }
+
+        // Xid和COMMIT结束事务，不在事务中的语句（如DDL）自身就是一个事务
+        let ends_transaction = match event.kind.as_str() {
+            "Xid" => true,
+            "Query" => match last_statement.as_deref() {
+                Some("COMMIT") | Some("ROLLBACK") => true,
+                Some(_) => !transaction.explicit,
+                None => false,
+            },
+            _ => false,
+        };
+        if ends_transaction {
+            finished.extend(self.transaction.take());
+        }
+    }
+}
+
+// 解析事件头，例如：#240101 10:00:00 server id 1  end_log_pos 236 CRC32 0x1a2b3c4d	Query	...
+fn parse_event_header(line: &str, position: u64) -> Option<BinlogEvent> {
+    let rest = line.strip_prefix('#')?;
+    let mut fields = rest.split('\t');
+    let head: Vec<&str> = fields.next()?.split_whitespace().collect();
+    let description = fields.next().unwrap_or_default().trim();
+
+    let end = head
+        .iter()
+        .position(|word| *word == "end_log_pos")
+        .and_then(|index| head.get(index + 1))
+        .and_then(|end| end.parse().ok())?;
+    let kind = description
+        .split([' ', ':'])
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    // MariaDB：GTID 0-1-5 trans
+    let gtid = if kind == "GTID" {
+        description
+            .split_whitespace()
+            .nth(1)
+            .map(|gtid| gtid.to_string())
+    } else {
+        None
+    };
+
+    Some(BinlogEvent {
+        position,
+        end,
+        timestamp: parse_header_timestamp(head.first()?, head.get(1)?),
+        kind,
+        gtid,
+        lines: Vec::new(),
+    })
+}
+
+// 事件头中的时间为两位年份的本地时间，小时不足两位时用空格补齐
+fn parse_header_timestamp(date: &str, time: &str) -> Option<NaiveDateTime> {
+    if date.len() != 6 {
+        return None;
+    }
+    let year: i32 = date[0..2].parse().ok()?;
+    let month: u32 = date[2..4].parse().ok()?;
+    let day: u32 = date[4..6].parse().ok()?;
+    let parts: Vec<u32> = time
+        .split(':')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    if parts.len() != 3 {
+        return None;
+    }
+    NaiveDate::from_ymd_opt(2000 + year, month, day)?.and_hms_opt(parts[0], parts[1], parts[2])
+}
+
+// 事件中以/*!*/;结束的语句，以及解码行事件得到的伪SQL（以###开头的注释）
+fn event_statements(lines: &[String]) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    for line in lines {
+        if let Some(row) = line.strip_prefix("### ") {
+            // 每个行变更以INSERT、UPDATE或DELETE开始，之后是列的值
+            let upper = row.trim_start().to_uppercase();
+            if upper.starts_with("INSERT")
+                || upper.starts_with("UPDATE")
+                || upper.starts_with("DELETE")
+            {
+                statements.push(row.trim().to_string());
+            } else if let Some(last) = statements.last_mut() {
+                last.push('\n');
+                last.push_str(row.trim());
+            }
+            continue;
+        }
+        if line.starts_with('#') || line.starts_with("DELIMITER") {
+            continue;
+        }
+        match line.strip_suffix("/*!*/;") {
+            Some(end) => {
+                current.push_str(end);
+                let statement = current.trim().to_string();
+                if !statement.is_empty() {
+                    statements.push(statement);
+                }
+                current.clear();
+            }
+            None => {
+                current.push_str(line);
+                current.push('\n');
+            }
+        }
+    }
+    statements
+}
+
+// SET @@SESSION.GTID_NEXT= 'uuid:5'中的GTID
+fn gtid_next(statement: &str) -> Option<String> {
+    if !statement.to_uppercase().contains("GTID_NEXT") {
+        return None;
+    }
+    let start = statement.find('\'')? + 1;
+    let end = start + statement[start..].find('\'')?;
+    let gtid = statement[start..end].trim().to_lowercase();
+    match gtid.as_str() {
+        "anonymous" | "automatic" => None,
+        _ => Some(gtid),
+    }
+}
+
+// mysqlbinlog为每个事件设置会话状态的语句，不在预览中列出
+fn is_session_statement(upper: &str) -> bool {
+    upper.starts_with("SET ") || upper.starts_with("USE ") || upper.starts_with("/*!")
+}
+
+// 开始重放的位置，备份只包含部分数据时不能重放
+fn replay_start(manifest: &BackupManifest) -> Result<BinlogPosition, String> {
+    if !manifest.databases.is_empty() {
+        return Err("多数据库备份不支持按时间点恢复".to_string());
+    }
+    if manifest.table_filter.is_some() {
+        return Err("备份只包含部分表或部分行，不能在此基础上重放binlog".to_string());
+    }
+    if manifest.mode == BackupMode::SchemaOnly {
+        return Err("备份只包含表结构，不能在此基础上重放binlog".to_string());
+    }
+    manifest.binlog.clone().ok_or_else(|| {
+        "备份没有记录binlog位置，无法按时间点恢复，请在备份设置中开启记录binlog位置".to_string()
+    })
+}
+
+// binlog文件名的扩展名编号，例如binlog.000012为12
+fn binlog_file_number(file: &str) -> Option<u64> {
+    file.rsplit_once('.')?.1.parse().ok()
+}
+
+// 按编号比较binlog文件的先后：编号超过999999后位数会增加，不能按字符串比较
+fn compare_binlog_files(a: &str, b: &str) -> cmp::Ordering {
+    match (binlog_file_number(a), binlog_file_number(b)) {
+        (Some(a_number), Some(b_number)) => a_number.cmp(&b_number),
+        _ => a.cmp(b),
+    }
+}
+
+// 从备份位置所在的文件开始的binlog文件列表
+fn list_binlog_files(
+    connection: &ConnectionInfo,
+    source: &BinlogSource,
+    start: &BinlogPosition,
+) -> Result<Vec<String>, String> {
+    // 服务器按binlog索引的顺序返回文件，本地文件按编号排序
+    let mut files: Vec<String> = match source {
+        BinlogSource::Remote => {
+            let mut conn = connection.connect(None)?;
+            let rows: Vec<mysql::Row> = conn
+                .query("SHOW BINARY LOGS")
+                .map_err(|e| format!("读取binlog文件列表失败: {}", e))?;
+            rows.iter()
+                .filter_map(|row| row.get::<String, _>("Log_name"))
+                .collect()
+        }
+        BinlogSource::Local(dir) => {
+            // 与备份位置所在的文件同名、扩展名为编号的文件，例如binlog.000012
+            let prefix = match start.file.rsplit_once('.') {
+                Some((prefix, _)) => format!("{}.", prefix),
+                None => return Err(format!("无法识别的binlog文件名 {}", start.file)),
+            };
+            let mut files: Vec<String> = fs::read_dir(dir)
+                .map_err(|e| format!("读取binlog目录失败: {}", e))?
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|name| {
+                    name.strip_prefix(&prefix).is_some_and(|number| {
+                        !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit())
+                    })
+                })
+                .collect();
+            files.sort_by(|a, b| compare_binlog_files(a, b));
+            files
+        }
+    };
+
+    let start_index = match files.iter().position(|file| *file == start.file) {
+        Some(index) => index,
+        None => {
+            return Err(match source {
+                BinlogSource::Remote => format!(
+                    "服务器上已没有备份位置所在的binlog文件 {}，可能已被清理",
+                    start.file
+                ),
+                BinlogSource::Local(_) => {
+                    format!("目录中没有备份位置所在的binlog文件 {}", start.file)
+                }
+            })
+        }
+    };
+    Ok(files.split_off(start_index))
+}
+
+// 构建mysqlbinlog命令，远程读取时添加连接参数
+fn mysqlbinlog_command(connection: &ConnectionInfo, source: &BinlogSource) -> Command {
+    let mut cmd = Command::new("mysqlbinlog");
+
+    // 在Windows平台上添加无窗口标志
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(0x08000000);
+
+    if let BinlogSource::Remote = source {
+        cmd.arg("--read-from-remote-server")
+            .arg(format!("--host={}", connection.host))
+            .arg(format!("--port={}", connection.port))
+            .arg(format!("--user={}", connection.username));
+
+        // 如果密码不为空，则添加密码参数
+        if !connection.password.is_empty() {
+            cmd.arg(format!("--password={}", connection.password));
+        }
+    }
+    cmd
+}
+
+// 命令行中的binlog文件，本地读取时为完整路径
+fn binlog_file_arg(source: &BinlogSource, file: &str) -> PathBuf {
+    match source {
+        BinlogSource::Remote => PathBuf::from(file),
+        BinlogSource::Local(dir) => dir.join(file),
+    }
+}
+
+// 启动mysqlbinlog并在单独的线程中读取错误输出，避免管道写满导致阻塞
+fn spawn_mysqlbinlog(mut cmd: Command) -> Result<(Child, ChildStdout, JoinHandle<String>), String> {
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("执行mysqlbinlog命令失败: {}", e))?;
+
+    let stderr = child.stderr.take();
+    let stderr_reader = thread::spawn(move || {
+        let mut message = String::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_string(&mut message);
+        }
+        message
+    });
+    let stdout = match child.stdout.take() {
+        Some(stdout) => stdout,
+        None => {
+            let _ = child.kill();
+            return Err("无法读取mysqlbinlog输出".to_string());
+        }
+    };
+    Ok((child, stdout, stderr_reader))
+}
+
+// 等待mysqlbinlog结束，失败时返回错误输出
+fn wait_mysqlbinlog(mut child: Child, stderr_reader: JoinHandle<String>) -> Result<(), String> {
+    let status = child
+        .wait()
+        .map_err(|e| format!("等待mysqlbinlog结束失败: {}", e))?;
+    let stderr_message = stderr_reader.join().unwrap_or_default();
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("读取binlog失败: {}", stderr_message.trim()))
+    }
+}
+
+// mysqlbinlog是否支持某个参数，不同版本和MariaDB的参数不同
+fn mysqlbinlog_supports(option: &str) -> bool {
+    let mut cmd = Command::new("mysqlbinlog");
+    cmd.arg("--help");
+
+    // 在Windows平台上添加无窗口标志
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(0x08000000);
+
+    cmd.output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains(option))
+        .unwrap_or(false)
+}
+
+// 重放的范围：扫描binlog找到终点，并收集终点之后被跳过的事务
+struct ReplayPlan {
+    start: BinlogPosition,
+    // 从备份位置所在的文件开始的binlog文件
+    files: Vec<String>,
+    stop_file: String,
+    stop_position: u64,
+    stop_found: bool,
+    applied: usize,
+    skipped: Vec<Transaction>,
+    more_skipped: bool,
+}
+
+impl ReplayPlan {
+    fn new(start: BinlogPosition, files: Vec<String>) -> Self {
+        ReplayPlan {
+            stop_file: start.file.clone(),
+            stop_position: start.position,
+            start,
+            files,
+            stop_found: false,
+            applied: 0,
+            skipped: Vec::new(),
+            more_skipped: false,
+        }
+    }
+
+    // 按顺序处理事务，预览已满不需要继续读取时返回false
+    fn accept(&mut self, transaction: Transaction, condition: &StopCondition) -> bool {
+        if !self.stop_found {
+            if !transaction.stops_at(condition) {
+                self.applied += 1;
+                self.stop_file = transaction.file;
+                self.stop_position = transaction.end;
+                return true;
+            }
+            self.stop_found = true;
+            self.stop_file = transaction.file.clone();
+            self.stop_position = transaction.start;
+        }
+        if self.skipped.len() < PREVIEW_TRANSACTIONS {
+            self.skipped.push(transaction);
+            return true;
+        }
+        self.more_skipped = true;
+        false
+    }
+
+    // 是否到达了终点：找到了终点处的事务，或者位置终点恰好是最后一个重放的事务的结束位置
+    fn reached(&self, condition: &StopCondition) -> bool {
+        self.stop_found
+            || matches!(condition, StopCondition::Position { file, position }
+                if *file == self.stop_file && *position == self.stop_position)
+    }
+
+    // 重放需要读取的文件，到终点所在的文件为止
+    fn replay_files(&self) -> &[String] {
+        match self.files.iter().position(|file| *file == self.stop_file) {
+            Some(index) => &self.files[..=index],
+            None => &self.files,
+        }
+    }
+
+    fn into_preview(self, condition: &StopCondition) -> PitrPreview {
+        PitrPreview {
+            stop_found: self.reached(condition),
+            start: self.start,
+            stop_file: self.stop_file,
+            stop_position: self.stop_position,
+            applied_transactions: self.applied,
+            skipped: self
+                .skipped
+                .into_iter()
+                .map(Transaction::into_preview)
+                .collect(),
+            more_skipped: self.more_skipped,
+        }
+    }
+}
+
+// 从备份位置开始逐个文件读取binlog，确定重放的终点
+fn scan_binlog(
+    connection: &ConnectionInfo,
+    source: &BinlogSource,
+    start: BinlogPosition,
+    condition: &StopCondition,
+    cancel_requested: &AtomicBool,
+) -> Result<ReplayPlan, String> {
+    let files = list_binlog_files(connection, source, &start)?;
+    let mut plan = ReplayPlan::new(start, files.clone());
+
+    for (index, file) in files.iter().enumerate() {
+        let mut cmd = mysqlbinlog_command(connection, source);
+        if index == 0 {
+            cmd.arg(format!("--start-position={}", plan.start.position));
+        }
+        // 行事件解码为注释形式的伪SQL，用于预览
+        cmd.arg("--base64-output=DECODE-ROWS")
+            .arg("--verbose")
+            .arg(binlog_file_arg(source, file));
+        let (mut child, stdout, stderr_reader) = spawn_mysqlbinlog(cmd)?;
+
+        let mut parser = EventParser::new(file);
+        let mut reader = BufReader::new(stdout);
+        let mut line = Vec::new();
+        let mut preview_full = false;
+        loop {
+            if cancel_requested.load(Ordering::SeqCst) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(RESTORE_CANCELLED.to_string());
+            }
+
+            line.clear();
+            let read = match reader.read_until(b'\n', &mut line) {
+                Ok(read) => read,
+                Err(e) => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(format!("读取mysqlbinlog输出失败: {}", e));
+                }
+            };
+            let transactions = if read == 0 {
+                parser.finish()
+            } else {
+                let text = String::from_utf8_lossy(&line);
+                parser.feed(text.trim_end_matches(['\r', '\n']))
+            };
+            for transaction in transactions {
+                if !plan.accept(transaction, condition) {
+                    preview_full = true;
+                    break;
+                }
+            }
+            if read == 0 || preview_full {
+                break;
+            }
+        }
+
+        // 预览已满时不再需要剩余的输出
+        if preview_full {
+            let _ = child.kill();
+            let _ = child.wait();
+            break;
+        }
+        wait_mysqlbinlog(child, stderr_reader)
+            .map_err(|e| format!("读取binlog文件 {} 失败: {}", file, e))?;
+    }
+
+    Ok(plan)
+}
+
+// 用mysqlbinlog读取重放范围内的事件并交给执行器，返回执行的语句数
+fn replay_binlog(
+    window: &Window,
+    connection: &ConnectionInfo,
+    source: &BinlogSource,
+    plan: &ReplayPlan,
+    manifest: &BackupManifest,
+    restore_options: &RestoreOptions,
+    executor: &mut RestoreExecutor,
+    cancel_requested: &AtomicBool,
+) -> Result<u64, String> {
+    let mut cmd = mysqlbinlog_command(connection, source);
+    cmd.arg(format!("--start-position={}", plan.start.position))
+        .arg(format!("--stop-position={}", plan.stop_position));
+
+    // 恢复到同一台服务器时这些GTID已经执行过，需要作为新事务执行，否则会被服务器跳过
+    if mysqlbinlog_supports("--skip-gtids") {
+        cmd.arg("--skip-gtids");
+    }
+
+    // 只重放备份的数据库，恢复到其他数据库时改写数据库名
+    if !manifest.database.is_empty() {
+        match restore_options.target_database.as_deref() {
+            Some(target) if target != manifest.database => {
+                cmd.arg(format!("--rewrite-db={}->{}", manifest.database, target))
+                    .arg(format!("--database={}", target));
+            }
+            _ => {
+                cmd.arg(format!("--database={}", manifest.database));
+            }
+        }
+    }
+    for file in plan.replay_files() {
+        cmd.arg(binlog_file_arg(source, file));
+    }
+
+    let (mut child, stdout, stderr_reader) = spawn_mysqlbinlog(cmd)?;
+    let mut statements = SqlStatementReader::new(BufReader::new(stdout));
+    let mut executed = 0;
+
+    let result = (|| {
+        while let Some(statement) = statements
+            .next_statement()
+            .map_err(|e| format!("读取mysqlbinlog输出失败: {}", e))?
+        {
+            if cancel_requested.load(Ordering::SeqCst) {
+                return Err(RESTORE_CANCELLED.to_string());
+            }
+            // /*!\C utf8mb4 */是mysql客户端切换字符集的命令，随后的SET语句会设置相同的字符集
+            if statement.starts_with(b"/*!\\C") {
+                continue;
+            }
+            executor.execute(&statement)?;
+
+            executed += 1;
+            if executed % 1000 == 0 {
+                send_restore_progress(
+                    window,
+                    99,
+                    &format!("正在重放binlog（已执行{}条语句）...", executed),
+                    None,
+                );
+            }
+        }
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(e);
+    }
+    wait_mysqlbinlog(child, stderr_reader)?;
+    Ok(executed)
+}
+
+// 读取清单并扫描binlog，得到重放的范围
+fn plan_recovery(
+    connection: &ConnectionInfo,
+    archive_path: &str,
+    options: &PitrOptions,
+    key: Option<&DecryptionKey>,
+    cancel_requested: &AtomicBool,
+) -> Result<(BackupManifest, BinlogSource, StopCondition, ReplayPlan), String> {
+    if !options.restore.tables.is_empty() {
+        return Err("按时间点恢复需要恢复整个数据库，不能只恢复部分表".to_string());
+    }
+    if !is_command_available("mysqlbinlog") {
+        return Err("系统中没有可用的mysqlbinlog命令，无法按时间点恢复".to_string());
+    }
+
+    let manifest = archive::read_manifest(Path::new(archive_path), key)?
+        .ok_or_else(|| "备份中没有清单，无法确定binlog位置".to_string())?;
+    let start = replay_start(&manifest)?;
+    let condition = options.target.condition(&start)?;
+
+    let source = match options.binlog_dir.as_deref().map(str::trim) {
+        Some(dir) if !dir.is_empty() => BinlogSource::Local(PathBuf::from(dir)),
+        _ => BinlogSource::Remote,
+    };
+    let plan = scan_binlog(connection, &source, start, &condition, cancel_requested)?;
+    Ok((manifest, source, condition, plan))
+}
+
+// 按时间点恢复：确定重放范围，恢复备份，再重放binlog到终点之前
+fn recover_to_point(
+    window: &Window,
+    connection: &ConnectionInfo,
+    archive_path: &str,
+    engine: Option<&str>,
+    options: &PitrOptions,
+    key: Option<&DecryptionKey>,
+    cancel_requested: &AtomicBool,
+) -> Result<String, String> {
+    send_restore_progress(window, 1, "正在读取binlog...", None);
+    let (manifest, source, condition, plan) =
+        plan_recovery(connection, archive_path, options, key, cancel_requested)?;
+
+    // GTID或位置写错时重放到末尾会得到错误的结果，在修改数据库之前报错；
+    // 时间可以晚于最后一个事务，此时重放到现有binlog的末尾
+    let reached = plan.reached(&condition);
+    if !reached && !matches!(condition, StopCondition::Datetime(_)) {
+        return Err(format!(
+            "在备份位置之后的binlog中没有找到重放的终点 {}，数据库没有被修改",
+            condition.describe()
+        ));
+    }
+
+    restore_archive(
+        window,
+        connection,
+        archive_path,
+        engine,
+        &options.restore,
+        key,
+        cancel_requested,
+    )?;
+
+    if plan.applied > 0 {
+        send_restore_progress(window, 99, "正在重放binlog...", None);
+        let mut executor = RestoreExecutor::for_engine(connection, engine)?;
+        let result = replay_binlog(
+            window,
+            connection,
+            &source,
+            &plan,
+            &manifest,
+            &options.restore,
+            &mut executor,
+            cancel_requested,
+        );
+        match result {
+            Ok(_) => executor.finish()?,
+            Err(e) => {
+                let detail = executor.abort();
+                if e == RESTORE_CANCELLED {
+                    send_restore_progress(window, 0, RESTORE_CANCELLED, None);
+                    return Err(e);
+                }
+                return match detail {
+                    Some(detail) => Err(format!("{}: {}", e, detail)),
+                    None => Err(e),
+                };
+            }
+        }
+    }
+
+    let mut status = format!(
+        "按时间点恢复完成，重放了{}个事务，停在 {}:{}",
+        plan.applied, plan.stop_file, plan.stop_position
+    );
+    if !reached {
+        status.push_str(&format!(
+            "。binlog中没有 {} 及之后的事务，已重放到现有binlog的末尾，没有到达指定的终点",
+            condition.describe()
+        ));
+    }
+    send_restore_progress(window, 100, &status, None);
+    Ok(archive_path.to_string())
+}
+
+// 预览按时间点恢复：重放的范围以及终点之后被跳过的语句，不修改数据库
+#[command]
+pub async fn preview_point_in_time_recovery(
+    host: &str,
+    port: u16,
+    username: &str,
+    password: &str,
+    archive_path: &str,
+    options: PitrOptions,
+    key: Option<DecryptionKey>,
+) -> Result<PitrPreview, String> {
+    let connection = ConnectionInfo {
+        host: host.to_string(),
+        port,
+        username: username.to_string(),
+        password: password.to_string(),
+    };
+    let archive_path = archive_path.to_string();
+
+    // 创建通道用于接收结果，读取binlog可能需要较长时间，放到单独的线程中执行
+    let (tx, mut rx) = mpsc::channel::<Result<PitrPreview, String>>(1);
+
+    let _ = thread::spawn(move || {
+        let cancel_requested = AtomicBool::new(false);
+        let preview_result = plan_recovery(
+            &connection,
+            &archive_path,
+            &options,
+            key.as_ref(),
+            &cancel_requested,
+        )
+        .map(|(_, _, condition, plan)| plan.into_preview(&condition));
+
+        if let Err(e) = tx.blocking_send(preview_result) {
+            eprintln!("无法发送预览结果: {}", e);
+        }
+    });
+
+    match rx.recv().await {
+        Some(r) => r,
+        None => Err("预览过程意外终止".to_string()),
+    }
+}
+
+// 按时间点恢复数据库
+#[command]
+pub async fn point_in_time_recovery(
+    window: Window,
+    host: &str,
+    port: u16,
+    username: &str,
+    password: &str,
+    archive_path: &str,
+    engine: Option<&str>,
+    options: PitrOptions,
+    key: Option<DecryptionKey>,
+    restore_state: State<'_, RestoreState>,
+) -> Result<String, String> {
+    // 与普通恢复共用运行标记，可以通过cancel_restore取消
+    let cancel_requested = restore_state.begin()?;
+
+    send_restore_progress(&window, 0, "正在准备按时间点恢复...", None);
+
+    // 克隆需要的数据以便在线程中使用
+    let window_clone = window.clone();
+    let connection = ConnectionInfo {
+        host: host.to_string(),
+        port,
+        username: username.to_string(),
+        password: password.to_string(),
+    };
+    let archive_path = archive_path.to_string();
+    let engine = engine.map(|s| s.to_string());
+
+    // 创建通道用于接收结果
+    let (tx, mut rx) = mpsc::channel::<Result<String, String>>(1);
+
+    let _ = thread::spawn(move || {
+        let recovery_result = recover_to_point(
+            &window_clone,
+            &connection,
+            &archive_path,
+            engine.as_deref(),
+            &options,
+            key.as_ref(),
+            &cancel_requested,
+        );
+
+        if let Err(e) = tx.blocking_send(recovery_result) {
+            eprintln!("无法发送恢复结果: {}", e);
+        }
+    });
+
+    // 等待结果
+    let result = match rx.recv().await {
+        Some(r) => r,
+        None => Err("恢复过程意外终止".to_string()),
+    };
+
+    // 释放恢复中标记
+    restore_state.end()?;
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // MySQL 8.0 mysqlbinlog --base64-output=DECODE-ROWS --verbose的输出，gtid_mode=ON：
+    // 一个行格式的事务，一条DDL，最后切换到下一个文件
+    const MYSQL_GTID_OUTPUT: &str = r#"# The proper term is pseudo_replica_mode, but we use this compatibility alias
+# to make the statement usable on server versions 8.0.24 and older.
+/*!50530 SET @@SESSION.PSEUDO_SLAVE_MODE=1*/;
+/*!50003 SET @OLD_COMPLETION_TYPE=@@COMPLETION_TYPE,COMPLETION_TYPE=0*/;
+DELIMITER /*!*/;
+# at 4
+#240115 10:00:00 server id 1  end_log_pos 126 CRC32 0x5f3c1e2a 	Start: binlog v 4, server v 8.0.36 created 240115 10:00:00
+# at 126
+#240115 10:00:00 server id 1  end_log_pos 197 CRC32 0x1b2c3d4e 	Previous-GTIDs
+# 3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5
+# at 197
+#240115 10:05:00 server id 1  end_log_pos 276 CRC32 0x6a7b8c9d 	GTID	last_committed=0	sequence_number=1	rbr_only=yes	original_committed_timestamp=1705313100000000	immediate_commit_timestamp=1705313100000000	transaction_length=300
+/*!50718 SET TRANSACTION ISOLATION LEVEL READ COMMITTED*//*!*/;
+# original_commit_timestamp=1705313100000000 (2024-01-15 10:05:00.000000 UTC)
+# immediate_commit_timestamp=1705313100000000 (2024-01-15 10:05:00.000000 UTC)
+/*!80001 SET @@session.original_commit_timestamp=1705313100000000*//*!*/;
+/*!80014 SET @@session.original_server_version=80036*//*!*/;
+/*!80014 SET @@session.immediate_server_version=80036*//*!*/;
+SET @@SESSION.GTID_NEXT= '3E11FA47-71CA-11E1-9E33-C80AA9429562:6'/*!*/;
+# at 276
+#240115 10:05:00 server id 1  end_log_pos 351 CRC32 0x11223344 	Query	thread_id=8	exec_time=0	error_code=0
+SET TIMESTAMP=1705313100/*!*/;
+SET @@session.pseudo_thread_id=8/*!*/;
+SET @@session.foreign_key_checks=1, @@session.sql_auto_is_null=0, @@session.unique_checks=1, @@session.autocommit=1/*!*/;
+SET @@session.sql_mode=1168113696/*!*/;
+/*!\C utf8mb4 *//*!*/;
+SET @@session.character_set_client=255,@@session.collation_connection=255,@@session.collation_server=255/*!*/;
+BEGIN
+/*!*/;
+# at 351
+#240115 10:05:00 server id 1  end_log_pos 410 CRC32 0x55667788 	Table_map: `shop`.`orders` mapped to number 90
+# has_generated_invisible_primary_key=0
+# at 410
+#240115 10:05:00 server id 1  end_log_pos 466 CRC32 0x99aabbcc 	Write_rows: table id 90 flags: STMT_END_F
+### INSERT INTO `shop`.`orders`
+### SET
+###   @1=1
+###   @2='apple'
+# at 466
+#240115 10:05:00 server id 1  end_log_pos 497 CRC32 0xddeeff00 	Xid = 42
+COMMIT/*!*/;
+# at 497
+#240115  9:59:59 server id 1  end_log_pos 574 CRC32 0x0a0b0c0d 	GTID	last_committed=1	sequence_number=2	rbr_only=no	original_committed_timestamp=1705313400000000	immediate_commit_timestamp=1705313400000000	transaction_length=203
+# original_commit_timestamp=1705313400000000 (2024-01-15 10:10:00.000000 UTC)
+SET @@SESSION.GTID_NEXT= '3e11fa47-71ca-11e1-9e33-c80aa9429562:7'/*!*/;
+# at 574
+#240115 10:10:00 server id 1  end_log_pos 700 CRC32 0x1a1b1c1d 	Query	thread_id=8	exec_time=0	error_code=0	Xid = 50
+use `shop`/*!*/;
+SET TIMESTAMP=1705313400/*!*/;
+/*!80013 SET @@session.sql_require_primary_key=0*//*!*/;
+ALTER TABLE orders ADD COLUMN note varchar(20)
+/*!*/;
+# at 700
+#240115 10:15:00 server id 1  end_log_pos 747 CRC32 0x2a2b2c2d 	Rotate to binlog.000043  pos: 4
+SET @@SESSION.GTID_NEXT= 'AUTOMATIC' /* added by mysqlbinlog */ /*!*/;
+DELIMITER ;
+# End of log file
+/*!50003 SET COMPLETION_TYPE=@OLD_COMPLETION_TYPE*/;
+/*!50530 SET @@SESSION.PSEUDO_SLAVE_MODE=0*/;
+"#;
+
+    // gtid_mode=OFF时每个事务以Anonymous_GTID开始，文件没有切换就结束
+    const MYSQL_ANONYMOUS_OUTPUT: &str = r#"DELIMITER /*!*/;
+# at 157
+#240115 11:00:00 server id 1  end_log_pos 236 CRC32 0x01020304 	Anonymous_GTID	last_committed=0	sequence_number=1	rbr_only=yes	original_committed_timestamp=1705316400000000	immediate_commit_timestamp=1705316400000000	transaction_length=290
+/*!50718 SET TRANSACTION ISOLATION LEVEL READ COMMITTED*//*!*/;
+SET @@SESSION.GTID_NEXT= 'ANONYMOUS'/*!*/;
+# at 236
+#240115 11:00:00 server id 1  end_log_pos 311 CRC32 0x05060708 	Query	thread_id=9	exec_time=0	error_code=0
+SET TIMESTAMP=1705316400/*!*/;
+BEGIN
+/*!*/;
+# at 311
+#240115 11:00:00 server id 1  end_log_pos 370 CRC32 0x090a0b0c 	Table_map: `shop`.`orders` mapped to number 90
+# at 370
+#240115 11:00:00 server id 1  end_log_pos 440 CRC32 0x0d0e0f10 	Update_rows: table id 90 flags: STMT_END_F
+### UPDATE `shop`.`orders`
+### WHERE
+###   @1=1
+### SET
+###   @1=1
+###   @2='pear'
+### UPDATE `shop`.`orders`
+### WHERE
+###   @1=2
+### SET
+###   @1=2
+###   @2='plum'
+# at 440
+#240115 11:00:00 server id 1  end_log_pos 471 CRC32 0x11121314 	Xid = 60
+COMMIT/*!*/;
+# at 471
+#240115 11:02:00 server id 1  end_log_pos 550 CRC32 0x15161718 	Anonymous_GTID	last_committed=1	sequence_number=2	rbr_only=no	original_committed_timestamp=1705316520000000	immediate_commit_timestamp=1705316520000000	transaction_length=180
+SET @@SESSION.GTID_NEXT= 'ANONYMOUS'/*!*/;
+# at 550
+#240115 11:02:00 server id 1  end_log_pos 651 CRC32 0x191a1b1c 	Query	thread_id=9	exec_time=0	error_code=0
+SET TIMESTAMP=1705316520/*!*/;
+DROP TABLE `tmp_orders` /* generated by server */
+/*!*/;
+SET @@SESSION.GTID_NEXT= 'AUTOMATIC' /* added by mysqlbinlog */ /*!*/;
+DELIMITER ;
+# End of log file
+/*!50003 SET COMPLETION_TYPE=@OLD_COMPLETION_TYPE*/;
+"#;
+
+    // MariaDB 10.11的mysqlbinlog输出：GTID写在事件头中，事务以START TRANSACTION开始
+    const MARIADB_OUTPUT: &str = r#"/*!50530 SET @@SESSION.PSEUDO_SLAVE_MODE=1*/;
+/*!40019 SET @@session.max_delayed_threads=0*/;
+/*!50003 SET @OLD_COMPLETION_TYPE=@@COMPLETION_TYPE,COMPLETION_TYPE=0*/;
+DELIMITER /*!*/;
+# at 4
+#240115 12:00:00 server id 1  end_log_pos 256 CRC32 0x21222324 	Start: binlog v 4, server v 10.11.6-MariaDB-log created 240115 12:00:00 at startup
+ROLLBACK/*!*/;
+# at 256
+#240115 12:00:00 server id 1  end_log_pos 299 CRC32 0x25262728 	Gtid list [0-1-99]
+# at 299
+#240115 12:00:00 server id 1  end_log_pos 342 CRC32 0x292a2b2c 	Binlog checkpoint mariadb-bin.000007
+# at 342
+#240115 12:05:00 server id 1  end_log_pos 384 CRC32 0x2d2e2f30 	GTID 0-1-100 trans
+/*!100101 SET @@session.skip_parallel_replication=0*//*!*/;
+/*!100001 SET @@session.gtid_domain_id=0*//*!*/;
+/*!100001 SET @@session.server_id=1*//*!*/;
+/*!100001 SET @@session.gtid_seq_no=100*//*!*/;
+START TRANSACTION
+/*!*/;
+# at 384
+#240115 12:05:00 server id 1  end_log_pos 440 CRC32 0x31323334 	Annotate_rows:
+#Q> INSERT INTO orders VALUES (3, 'fig')
+# at 440
+#240115 12:05:00 server id 1  end_log_pos 495 CRC32 0x35363738 	Table_map: `shop`.`orders` mapped to number 18
+# at 495
+#240115 12:05:00 server id 1  end_log_pos 545 CRC32 0x393a3b3c 	Write_rows_v1: table id 18 flags: STMT_END_F
+### INSERT INTO `shop`.`orders`
+### SET
+###   @1=3
+###   @2='fig'
+# at 545
+#240115 12:05:00 server id 1  end_log_pos 576 CRC32 0x3d3e3f40 	Xid = 12
+COMMIT/*!*/;
+# at 576
+#240115 12:06:00 server id 1  end_log_pos 618 CRC32 0x41424344 	GTID 0-1-101 ddl
+/*!100001 SET @@session.gtid_seq_no=101*//*!*/;
+# at 618
+#240115 12:06:00 server id 1  end_log_pos 731 CRC32 0x45464748 	Query	thread_id=5	exec_time=0	error_code=0	xid=0
+use `shop`/*!*/;
+SET TIMESTAMP=1705320360/*!*/;
+CREATE INDEX idx_name ON orders (name)
+/*!*/;
+DELIMITER ;
+# End of log file
+ROLLBACK /* added by mysqlbinlog */;
+/*!50003 SET COMPLETION_TYPE=@OLD_COMPLETION_TYPE*/;
+"#;
+
+    fn parse(file: &str, output: &str) -> Vec<Transaction> {
+        let mut parser = EventParser::new(file);
+        let mut transactions = Vec::new();
+        for line in output.lines() {
+            transactions.extend(parser.feed(line));
+        }
+        transactions.extend(parser.finish());
+        transactions
+    }
+
+    fn datetime(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn transaction(file: &str, start: u64, end: u64, time: &str) -> Transaction {
+        Transaction {
+            file: file.to_string(),
+            start,
+            end,
+            timestamp: Some(datetime(time)),
+            gtid: None,
+            statements: Vec::new(),
+            omitted_statements: 0,
+            explicit: false,
+        }
+    }
+
+    fn start(file: &str, position: u64) -> BinlogPosition {
+        BinlogPosition {
+            file: file.to_string(),
+            position,
+            gtid_executed: String::new(),
+        }
+    }
+
+    fn plan(
+        files: &[&str],
+        transactions: Vec<Transaction>,
+        condition: &StopCondition,
+    ) -> ReplayPlan {
+        let mut plan = ReplayPlan::new(
+            start(files[0], 4),
+            files.iter().map(|file| file.to_string()).collect(),
+        );
+        for transaction in transactions {
+            assert!(plan.accept(transaction, condition));
+        }
+        plan
+    }
+
+    #[test]
+    fn parse_event_header_reads_position_time_and_kind() {
+        let event = parse_event_header(
+            "#240115 10:05:00 server id 1  end_log_pos 276 CRC32 0x6a7b8c9d \tGTID\tlast_committed=0\tsequence_number=1",
+            197,
+        )
+        .unwrap();
+        assert_eq!(event.position, 197);
+        assert_eq!(event.end, 276);
+        assert_eq!(event.timestamp, Some(datetime("2024-01-15 10:05:00")));
+        assert_eq!(event.kind, "GTID");
+        assert_eq!(event.gtid, None);
+
+        // 小时不足两位时用空格补齐
+        let event = parse_event_header(
+            "#240115  9:59:59 server id 1  end_log_pos 466 CRC32 0x99aabbcc \tWrite_rows: table id 90 flags: STMT_END_F",
+            410,
+        )
+        .unwrap();
+        assert_eq!(event.timestamp, Some(datetime("2024-01-15 09:59:59")));
+        assert_eq!(event.kind, "Write_rows");
+
+        let event = parse_event_header(
+            "#240115 10:05:00 server id 1  end_log_pos 497 CRC32 0xddeeff00 \tXid = 42",
+            466,
+        )
+        .unwrap();
+        assert_eq!(event.kind, "Xid");
+    }
+
+    #[test]
+    fn parse_event_header_reads_mariadb_gtid() {
+        let event = parse_event_header(
+            "#240115 12:05:00 server id 1  end_log_pos 384 CRC32 0x2d2e2f30 \tGTID 0-1-100 trans",
+            342,
+        )
+        .unwrap();
+        assert_eq!(event.kind, "GTID");
+        assert_eq!(event.gtid.as_deref(), Some("0-1-100"));
+
+        let event = parse_event_header(
+            "#240115 12:00:00 server id 1  end_log_pos 299 CRC32 0x25262728 \tGtid list [0-1-99]",
+            256,
+        )
+        .unwrap();
+        assert_eq!(event.kind, "Gtid");
+        assert_eq!(event.gtid, None);
+    }
+
+    #[test]
+    fn parse_event_header_rejects_other_comment_lines() {
+        assert!(parse_event_header("# 3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5", 126).is_none());
+        assert!(parse_event_header("SET TIMESTAMP=1705313100/*!*/;", 276).is_none());
+    }
+
+    #[test]
+    fn event_statements_joins_lines_and_decoded_rows() {
+        let lines: Vec<String> = [
+            "SET TIMESTAMP=1705313100/*!*/;",
+            "# original_commit_timestamp=1705313100000000",
+            "ALTER TABLE orders",
+            "  ADD COLUMN note varchar(20)",
+            "/*!*/;",
+            "### UPDATE `shop`.`orders`",
+            "### WHERE",
+            "###   @1=1",
+            "### SET",
+            "###   @2='pear'",
+            "### DELETE FROM `shop`.`orders`",
+            "### WHERE",
+            "###   @1=2",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+
+        assert_eq!(
+            event_statements(&lines),
+            vec![
+                "SET TIMESTAMP=1705313100".to_string(),
+                "ALTER TABLE orders\n  ADD COLUMN note varchar(20)".to_string(),
+                "UPDATE `shop`.`orders`\nWHERE\n@1=1\nSET\n@2='pear'".to_string(),
+                "DELETE FROM `shop`.`orders`\nWHERE\n@1=2".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn gtid_next_ignores_anonymous_and_automatic() {
+        assert_eq!(
+            gtid_next("SET @@SESSION.GTID_NEXT= '3E11FA47-71CA-11E1-9E33-C80AA9429562:6'"),
+            Some("3e11fa47-71ca-11e1-9e33-c80aa9429562:6".to_string())
+        );
+        assert_eq!(gtid_next("SET @@SESSION.GTID_NEXT= 'ANONYMOUS'"), None);
+        assert_eq!(
+            gtid_next("SET @@SESSION.GTID_NEXT= 'AUTOMATIC' /* added by mysqlbinlog */ "),
+            None
+        );
+        assert_eq!(gtid_next("SET TIMESTAMP=1705313100"), None);
+    }
+
+    #[test]
+    fn parser_groups_mysql_gtid_transactions() {
+        let transactions = parse("binlog.000042", MYSQL_GTID_OUTPUT);
+        assert_eq!(transactions.len(), 2);
+
+        // GTID、BEGIN、行事件和Xid属于同一个事务
+        let first = &transactions[0];
+        assert_eq!((first.start, first.end), (197, 497));
+        assert_eq!(
+            first.gtid.as_deref(),
+            Some("3e11fa47-71ca-11e1-9e33-c80aa9429562:6")
+        );
+        assert_eq!(first.timestamp, Some(datetime("2024-01-15 10:05:00")));
+        assert!(first.explicit);
+        assert_eq!(
+            first.statements,
+            vec!["INSERT INTO `shop`.`orders`\nSET\n@1=1\n@2='apple'".to_string()]
+        );
+
+        // 不在事务中的DDL自身就是一个事务，Rotate和文件末尾的语句不属于任何事务
+        let second = &transactions[1];
+        assert_eq!((second.start, second.end), (497, 700));
+        assert_eq!(
+            second.gtid.as_deref(),
+            Some("3e11fa47-71ca-11e1-9e33-c80aa9429562:7")
+        );
+        assert!(!second.explicit);
+        assert_eq!(
+            second.statements,
+            vec!["ALTER TABLE orders ADD COLUMN note varchar(20)".to_string()]
+        );
+        assert!(second.file == "binlog.000042");
+    }
+
+    #[test]
+    fn parser_groups_anonymous_transactions() {
+        let transactions = parse("binlog.000007", MYSQL_ANONYMOUS_OUTPUT);
+        assert_eq!(transactions.len(), 2);
+
+        let first = &transactions[0];
+        assert_eq!((first.start, first.end), (157, 471));
+        assert_eq!(first.gtid, None);
+        assert_eq!(first.statements.len(), 2);
+        assert!(first.statements[1].starts_with("UPDATE `shop`.`orders`\nWHERE\n@1=2"));
+
+        // 最后一个事务在输出结束时由finish返回
+        let second = &transactions[1];
+        assert_eq!((second.start, second.end), (471, 651));
+        assert_eq!(second.timestamp, Some(datetime("2024-01-15 11:02:00")));
+        assert_eq!(
+            second.statements,
+            vec!["DROP TABLE `tmp_orders` /* generated by server */".to_string()]
+        );
+    }
+
+    #[test]
+    fn parser_groups_mariadb_transactions() {
+        let transactions = parse("mariadb-bin.000007", MARIADB_OUTPUT);
+        assert_eq!(transactions.len(), 2);
+
+        let first = &transactions[0];
+        assert_eq!((first.start, first.end), (342, 576));
+        assert_eq!(first.gtid.as_deref(), Some("0-1-100"));
+        assert!(first.explicit);
+        assert_eq!(
+            first.statements,
+            vec!["INSERT INTO `shop`.`orders`\nSET\n@1=3\n@2='fig'".to_string()]
+        );
+
+        let second = &transactions[1];
+        assert_eq!((second.start, second.end), (576, 731));
+        assert_eq!(second.gtid.as_deref(), Some("0-1-101"));
+        assert_eq!(
+            second.statements,
+            vec!["CREATE INDEX idx_name ON orders (name)".to_string()]
+        );
+    }
+
+    #[test]
+    fn explicit_transaction_waits_for_commit_across_query_events() {
+        let output = "# at 4
+#240115 10:00:00 server id 1  end_log_pos 80 CRC32 0x01 \tQuery\tthread_id=1
+BEGIN
+/*!*/;
+# at 80
+#240115 10:00:00 server id 1  end_log_pos 160 CRC32 0x02 \tQuery\tthread_id=1
+INSERT INTO t VALUES (1)
+/*!*/;
+# at 160
+#240115 10:00:01 server id 1  end_log_pos 240 CRC32 0x03 \tQuery\tthread_id=1
+UPDATE t SET a = 2
+/*!*/;
+# at 240
+#240115 10:00:01 server id 1  end_log_pos 300 CRC32 0x04 \tQuery\tthread_id=1
+COMMIT
+/*!*/;
+# at 300
+#240115 10:00:02 server id 1  end_log_pos 380 CRC32 0x05 \tQuery\tthread_id=1
+CREATE TABLE u (a int)
+/*!*/;
+";
+        let transactions = parse("mysql-bin.000001", output);
+        assert_eq!(transactions.len(), 2);
+        assert_eq!((transactions[0].start, transactions[0].end), (4, 300));
+        assert_eq!(
+            transactions[0].statements,
+            vec![
+                "INSERT INTO t VALUES (1)".to_string(),
+                "UPDATE t SET a = 2".to_string()
+            ]
+        );
+        assert_eq!((transactions[1].start, transactions[1].end), (300, 380));
+    }
+
+    #[test]
+    fn datetime_stop_is_first_transaction_at_or_after_target() {
+        let condition = StopCondition::Datetime(datetime("2024-01-15 10:05:00"));
+        let transactions = vec![
+            transaction("binlog.000042", 197, 497, "2024-01-15 10:04:59"),
+            // 同一秒内的多个事务全部跳过
+            transaction("binlog.000042", 497, 700, "2024-01-15 10:05:00"),
+            transaction("binlog.000042", 700, 900, "2024-01-15 10:05:00"),
+        ];
+        let plan = plan(&["binlog.000042"], transactions, &condition);
+        assert!(plan.reached(&condition));
+        assert_eq!(plan.applied, 1);
+        assert_eq!(
+            (plan.stop_file.as_str(), plan.stop_position),
+            ("binlog.000042", 497)
+        );
+        assert_eq!(plan.skipped.len(), 2);
+    }
+
+    #[test]
+    fn ambiguous_datetime_stops_at_first_occurrence() {
+        // 夏令时结束时01:00到02:00出现两次，事件头中的本地时间会倒退；
+        // 停在第一次到达01:30的事务之前，倒退后的事务即使时间更早也不再重放
+        let condition = StopCondition::Datetime(datetime("2024-11-03 01:30:00"));
+        let transactions = vec![
+            transaction("binlog.000042", 197, 300, "2024-11-03 01:20:00"),
+            transaction("binlog.000042", 300, 400, "2024-11-03 01:40:00"),
+            transaction("binlog.000042", 400, 500, "2024-11-03 01:10:00"),
+        ];
+        let plan = plan(&["binlog.000042"], transactions, &condition);
+        assert_eq!(plan.applied, 1);
+        assert_eq!(plan.stop_position, 300);
+        assert_eq!(plan.skipped.len(), 2);
+        assert_eq!(plan.skipped[1].start, 400);
+    }
+
+    #[test]
+    fn datetime_after_last_transaction_runs_to_end() {
+        let condition = StopCondition::Datetime(datetime("2024-01-16 00:00:00"));
+        let transactions = parse("binlog.000042", MYSQL_GTID_OUTPUT);
+        let plan = plan(&["binlog.000042"], transactions, &condition);
+        assert!(!plan.reached(&condition));
+        assert_eq!(plan.applied, 2);
+        assert_eq!(plan.stop_position, 700);
+    }
+
+    #[test]
+    fn gtid_stop_skips_matching_transaction() {
+        let condition = StopCondition::Gtid("3e11fa47-71ca-11e1-9e33-c80aa9429562:7".to_string());
+        let plan = plan(
+            &["binlog.000042"],
+            parse("binlog.000042", MYSQL_GTID_OUTPUT),
+            &condition,
+        );
+        assert!(plan.reached(&condition));
+        assert_eq!(plan.applied, 1);
+        assert_eq!(plan.stop_position, 497);
+    }
+
+    #[test]
+    fn gtid_never_found_is_not_reached() {
+        let condition = StopCondition::Gtid("3e11fa47-71ca-11e1-9e33-c80aa9429562:99".to_string());
+        let mut transactions = parse("binlog.000042", MYSQL_GTID_OUTPUT);
+        transactions.extend(parse("binlog.000043", MYSQL_ANONYMOUS_OUTPUT));
+        let plan = plan(
+            &["binlog.000042", "binlog.000043"],
+            transactions,
+            &condition,
+        );
+        assert!(!plan.stop_found);
+        assert!(!plan.reached(&condition));
+        assert_eq!(plan.applied, 4);
+        assert!(plan.skipped.is_empty());
+    }
+
+    #[test]
+    fn position_stop_across_files() {
+        let condition = StopCondition::Position {
+            file: "binlog.000043".to_string(),
+            position: 300,
+        };
+        let mut transactions = parse("binlog.000042", MYSQL_GTID_OUTPUT);
+        transactions.extend(parse("binlog.000043", MYSQL_ANONYMOUS_OUTPUT));
+        let plan = plan(
+            &["binlog.000042", "binlog.000043"],
+            transactions,
+            &condition,
+        );
+
+        // binlog.000042中的事务全部重放，binlog.000043中包含位置300的事务被跳过
+        assert!(plan.reached(&condition));
+        assert_eq!(plan.applied, 2);
+        assert_eq!(
+            (plan.stop_file.as_str(), plan.stop_position),
+            ("binlog.000043", 157)
+        );
+        assert_eq!(plan.replay_files(), ["binlog.000042", "binlog.000043"]);
+    }
+
+    #[test]
+    fn position_at_end_of_last_transaction_is_reached() {
+        let condition = StopCondition::Position {
+            file: "binlog.000043".to_string(),
+            position: 651,
+        };
+        let mut transactions = parse("binlog.000042", MYSQL_GTID_OUTPUT);
+        transactions.extend(parse("binlog.000043", MYSQL_ANONYMOUS_OUTPUT));
+        let plan = plan(
+            &["binlog.000042", "binlog.000043"],
+            transactions,
+            &condition,
+        );
+        assert!(!plan.stop_found);
+        assert!(plan.reached(&condition));
+        assert_eq!(plan.applied, 4);
+    }
+
+    #[test]
+    fn position_in_missing_file_is_not_reached() {
+        let condition = StopCondition::Position {
+            file: "binlog.000099".to_string(),
+            position: 4,
+        };
+        let plan = plan(
+            &["binlog.000042"],
+            parse("binlog.000042", MYSQL_GTID_OUTPUT),
+            &condition,
+        );
+        assert!(!plan.reached(&condition));
+    }
+
+    #[test]
+    fn position_compares_file_numbers_not_names() {
+        let condition = StopCondition::Position {
+            file: "binlog.1000000".to_string(),
+            position: 300,
+        };
+        assert!(!transaction("binlog.999999", 4, 500, "2024-01-15 10:00:00").stops_at(&condition));
+        assert!(!transaction("binlog.1000000", 4, 200, "2024-01-15 10:00:00").stops_at(&condition));
+        assert!(
+            transaction("binlog.1000000", 200, 400, "2024-01-15 10:00:00").stops_at(&condition)
+        );
+        assert!(transaction("binlog.1000001", 4, 100, "2024-01-15 10:00:00").stops_at(&condition));
+
+        let mut files = vec!["binlog.1000000", "binlog.999998", "binlog.999999"];
+        files.sort_by(|a, b| compare_binlog_files(a, b));
+        assert_eq!(files, ["binlog.999998", "binlog.999999", "binlog.1000000"]);
+    }
+}
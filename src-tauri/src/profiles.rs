@@ -0,0 +1,371 @@
+// 连接配置和备份作业：保存多个服务器的连接信息，每个备份作业绑定一个连接配置、
+// 要备份的数据库、备份引擎、保存目录、定时设置和保留策略，可以手动运行或按计划运行
+use crate::scheduler::{self, BackupTarget, ScheduleTiming};
+use crate::secrets::{self, SecretFile};
+use crate::BackupOptions;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+// 连接配置和备份作业在应用数据目录中的文件名
+const PROFILES_FILE: &str = "profiles.json";
+
+// 数据库服务器的连接配置
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ConnectionProfile {
+    // 为0时表示新建
+    pub id: u64,
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    // 密码在密钥文件中的引用，只出现在保存的文件中
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password_ref: Option<String>,
+}
+
+// 备份作业
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SavedJob {
+    // 为0时表示新建
+    pub id: u64,
+    pub name: String,
+    pub profile_id: u64,
+    pub databases: Vec<String>,
+    pub engine: Option<String>,
+    // 备份保存目录，每个数据库的备份放在以数据库名命名的子目录中
+    pub destination: String,
+    pub options: BackupOptions,
+    // 所有数据库作为一次多数据库备份保存在备份目录中，多数据库选项见options.server
+    pub server_backup: bool,
+    pub schedule: ScheduleTiming,
+    // 备份成功后清理超过保留天数的备份，0或负数表示不清理
+    pub keep_days: i32,
+    // 加密口令在密钥文件中的引用，只出现在保存的文件中
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub passphrase_ref: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+struct ProfileFile {
+    // 连接配置和备份作业共用的编号计数
+    next_id: u64,
+    profiles: Vec<ConnectionProfile>,
+    jobs: Vec<SavedJob>,
+}
+
+#[derive(Default)]
+struct ProfileState {
+    file: ProfileFile,
+    // 保存文件路径，应用启动后确定
+    path: Option<PathBuf>,
+}
+
+impl ProfileState {
+    fn next_id(&mut self) -> u64 {
+        self.file.next_id += 1;
+        self.file.next_id
+    }
+
+    fn profile(&self, id: u64) -> Result<&ConnectionProfile, String> {
+        self.file
+            .profiles
+            .iter()
+            .find(|profile| profile.id == id)
+            .ok_or_else(|| format!("连接配置 {} 不存在", id))
+    }
+
+    fn job(&self, id: u64) -> Result<&SavedJob, String> {
+        self.file
+            .jobs
+            .iter()
+            .find(|job| job.id == id)
+            .ok_or_else(|| format!("备份作业 {} 不存在", id))
+    }
+
+    // 备份作业中每个数据库的备份参数，多数据库备份只有一个
+    fn targets(&self, job: &SavedJob) -> Result<Vec<BackupTarget>, String> {
+        let profile = self.profile(job.profile_id)?;
+        let mut options = job.options.clone();
+        // 按作业运行的备份遇到未完成的可续传备份时直接继续
+        options.resume = true;
+
+        if job.server_backup {
+            options.server.databases = job.databases.clone();
+            return Ok(vec![BackupTarget {
+                host: profile.host.clone(),
+                port: profile.port,
+                username: profile.username.clone(),
+                password: profile.password.clone(),
+                database: options.server.label(),
+                backup_dir: job.destination.clone(),
+                engine: job.engine.clone(),
+                options,
+                keep_days: job.keep_days,
+            }]);
+        }
+        options.server = Default::default();
+
+        Ok(job
+            .databases
+            .iter()
+            .filter(|database| !database.trim().is_empty())
+            .map(|database| BackupTarget {
+                host: profile.host.clone(),
+                port: profile.port,
+                username: profile.username.clone(),
+                password: profile.password.clone(),
+                database: database.clone(),
+                backup_dir: Path::new(&job.destination)
+                    .join(database)
+                    .to_string_lossy()
+                    .into_owned(),
+                engine: job.engine.clone(),
+                options: options.clone(),
+                keep_days: job.keep_days,
+            })
+            .collect())
+    }
+
+    fn validate_job(&self, job: &SavedJob) -> Result<(), String> {
+        if job.name.trim().is_empty() {
+            return Err("请输入备份作业名称".to_string());
+        }
+        self.profile(job.profile_id)?;
+        let all_databases = job.server_backup && job.options.server.all_databases;
+        if !all_databases
+            && job
+                .databases
+                .iter()
+                .all(|database| database.trim().is_empty())
+        {
+            return Err("请选择要备份的数据库".to_string());
+        }
+        if job.destination.is_empty() {
+            return Err("未设置备份路径".to_string());
+        }
+        scheduler::validate_timing(&job.schedule)
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("创建应用数据目录失败: {}", e))?;
+        }
+        // 密码和加密口令写入密钥文件，配置文件中只保留引用
+        let mut file = self.file.clone();
+        let mut secrets = SecretFile::new(secrets::secrets_path(path));
+        for profile in &mut file.profiles {
+            profile.password_ref = secrets.stash(
+                format!("profile/{}/password", profile.id),
+                std::mem::take(&mut profile.password),
+            );
+        }
+        for job in &mut file.jobs {
+            job.passphrase_ref = secrets.stash(
+                format!("job/{}/passphrase", job.id),
+                job.options.encryption.passphrase.take().unwrap_or_default(),
+            );
+        }
+        secrets.save()?;
+
+        let json =
+            serde_json::to_vec_pretty(&file).map_err(|e| format!("保存备份作业失败: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("保存备份作业失败: {}", e))
+    }
+}
+
+// 连接配置和备份作业，作为Tauri状态管理
+#[derive(Default)]
+pub struct ProfileStore {
+    state: Mutex<ProfileState>,
+}
+
+impl ProfileStore {
+    fn lock(&self) -> Result<MutexGuard<'_, ProfileState>, String> {
+        self.state.lock().map_err(|e| e.to_string())
+    }
+
+    // 读取应用数据目录中保存的连接配置和备份作业
+    pub fn load(&self, app_data_dir: &Path) -> Result<(), String> {
+        let path = app_data_dir.join(PROFILES_FILE);
+        let mut state = self.lock()?;
+        let mut plaintext_secrets = false;
+        if path.exists() {
+            let content = fs::read(&path).map_err(|e| format!("读取备份作业失败: {}", e))?;
+            state.file =
+                serde_json::from_slice(&content).map_err(|e| format!("解析备份作业失败: {}", e))?;
+
+            // 从密钥文件中取回密码和加密口令，内存中不保留引用
+            let secrets = SecretFile::load(secrets::secrets_path(&path))?;
+            for profile in &mut state.file.profiles {
+                match profile.password_ref.take() {
+                    Some(reference) => {
+                        profile.password = secrets.get(&reference).unwrap_or_default()
+                    }
+                    None => plaintext_secrets |= !profile.password.is_empty(),
+                }
+            }
+            for job in &mut state.file.jobs {
+                let encryption = &mut job.options.encryption;
+                match job.passphrase_ref.take() {
+                    Some(reference) => encryption.passphrase = secrets.get(&reference),
+                    None => {
+                        plaintext_secrets |= encryption
+                            .passphrase
+                            .as_deref()
+                            .is_some_and(|passphrase| !passphrase.is_empty())
+                    }
+                }
+            }
+        }
+        state.path = Some(path);
+
+        // 旧版本以明文保存的密码和口令移到密钥文件中
+        if plaintext_secrets {
+            state.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn profiles(&self) -> Result<Vec<ConnectionProfile>, String> {
+        Ok(self.lock()?.file.profiles.clone())
+    }
+
+    // 新建或修改连接配置
+    pub fn save_profile(
+        &self,
+        mut profile: ConnectionProfile,
+    ) -> Result<ConnectionProfile, String> {
+        if profile.name.trim().is_empty() {
+            return Err("请输入连接配置名称".to_string());
+        }
+        if profile.host.trim().is_empty() {
+            return Err("请输入数据库主机地址".to_string());
+        }
+
+        let mut state = self.lock()?;
+        if profile.id == 0 {
+            profile.id = state.next_id();
+            state.file.profiles.push(profile.clone());
+        } else {
+            let existing = state
+                .file
+                .profiles
+                .iter_mut()
+                .find(|existing| existing.id == profile.id)
+                .ok_or_else(|| format!("连接配置 {} 不存在", profile.id))?;
+            *existing = profile.clone();
+        }
+        state.save()?;
+        Ok(profile)
+    }
+
+    // 删除连接配置，仍被备份作业使用时不能删除
+    pub fn delete_profile(&self, id: u64) -> Result<(), String> {
+        let mut state = self.lock()?;
+        if let Some(job) = state.file.jobs.iter().find(|job| job.profile_id == id) {
+            return Err(format!("连接配置正在被备份作业 {} 使用", job.name));
+        }
+        state.file.profiles.retain(|profile| profile.id != id);
+        state.save()
+    }
+
+    pub fn jobs(&self) -> Result<Vec<SavedJob>, String> {
+        Ok(self.lock()?.file.jobs.clone())
+    }
+
+    pub fn create_job(&self, mut job: SavedJob) -> Result<SavedJob, String> {
+        let mut state = self.lock()?;
+        state.validate_job(&job)?;
+        job.id = state.next_id();
+        state.file.jobs.push(job.clone());
+        state.save()?;
+        Ok(job)
+    }
+
+    pub fn update_job(&self, job: SavedJob) -> Result<SavedJob, String> {
+        let mut state = self.lock()?;
+        state.validate_job(&job)?;
+        let existing = state
+            .file
+            .jobs
+            .iter_mut()
+            .find(|existing| existing.id == job.id)
+            .ok_or_else(|| format!("备份作业 {} 不存在", job.id))?;
+        *existing = job.clone();
+        state.save()?;
+        Ok(job)
+    }
+
+    pub fn delete_job(&self, id: u64) -> Result<(), String> {
+        let mut state = self.lock()?;
+        state.file.jobs.retain(|job| job.id != id);
+        state.save()
+    }
+
+    // 备份作业中每个数据库的备份参数
+    pub fn job_targets(&self, id: u64) -> Result<Vec<BackupTarget>, String> {
+        let state = self.lock()?;
+        state.targets(state.job(id)?)
+    }
+
+    // 所有备份作业的定时设置和备份参数，供调度器使用
+    pub fn schedules(&self) -> Result<Vec<(u64, ScheduleTiming, Vec<BackupTarget>)>, String> {
+        let state = self.lock()?;
+        state
+            .file
+            .jobs
+            .iter()
+            .map(|job| Ok((job.id, job.schedule.clone(), state.targets(job)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_moves_plaintext_secrets_out_of_profiles_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(PROFILES_FILE);
+        let legacy = r#"{
+            "next_id": 2,
+            "profiles": [{"id": 1, "name": "prod", "host": "db", "port": 3306,
+                          "username": "root", "password": "p@ss"}],
+            "jobs": [{"id": 2, "name": "nightly", "profile_id": 1,
+                      "options": {"encryption": {"passphrase": "hunter2"}}}]
+        }"#;
+        fs::write(&path, legacy).unwrap();
+
+        let store = ProfileStore::default();
+        store.load(dir.path()).unwrap();
+
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(!saved.contains("p@ss"));
+        assert!(!saved.contains("hunter2"));
+        assert!(saved.contains("\"password_ref\": \"profile/1/password\""));
+        assert!(saved.contains("\"passphrase_ref\": \"job/2/passphrase\""));
+
+        // 重新读取时从密钥文件取回，返回给前端的配置中没有引用
+        let store = ProfileStore::default();
+        store.load(dir.path()).unwrap();
+        let profiles = store.profiles().unwrap();
+        assert_eq!(profiles[0].password, "p@ss");
+        assert_eq!(profiles[0].password_ref, None);
+        let state = store.lock().unwrap();
+        assert_eq!(
+            state.file.jobs[0].options.encryption.passphrase.as_deref(),
+            Some("hunter2")
+        );
+    }
+}
@@ -0,0 +1,1911 @@
+// 从备份文件恢复数据库，支持mysqldump和内置引擎生成的归档以及各种压缩格式
+use crate::archive;
+use crate::crypto::DecryptionKey;
+use crate::manifest::{self, BackupManifest, BackupMode, MANIFEST_ENTRY};
+use crate::{is_command_available, quote_identifier, BackupProgress};
+use mysql::{prelude::*, Conn, OptsBuilder};
+use serde::Deserialize;
+use std::collections::{HashSet, VecDeque};
+use std::io::{self, BufRead, BufReader, Read, Write};
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use tauri::{command, Emitter, State, Window};
+use tokio::sync::mpsc;
+
+// 用户取消恢复时返回的错误信息
+pub(crate) const RESTORE_CANCELLED: &str = "恢复已取消";
+
+// 写入mysql客户端时使用的语句分隔符，避免与存储过程体中的分号冲突
+const CLI_DELIMITER: &[u8] = b";;";
+
+// 定义恢复状态结构
+#[derive(Default)]
+pub struct RestoreState {
+    is_running: Mutex<bool>,
+    cancel_requested: Arc<AtomicBool>,
+}
+
+impl RestoreState {
+    // 标记恢复已开始并重置取消标记，已有恢复在运行时返回错误
+    pub(crate) fn begin(&self) -> Result<Arc<AtomicBool>, String> {
+        let mut is_running = self.is_running.lock().map_err(|e| e.to_string())?;
+        if *is_running {
+            return Err("已有恢复任务正在运行".to_string());
+        }
+        *is_running = true;
+        self.cancel_requested.store(false, Ordering::SeqCst);
+        Ok(Arc::clone(&self.cancel_requested))
+    }
+
+    // 释放恢复中标记
+    pub(crate) fn end(&self) -> Result<(), String> {
+        let mut is_running = self.is_running.lock().map_err(|e| e.to_string())?;
+        *is_running = false;
+        Ok(())
+    }
+}
+
+// 表已存在时的处理方式
+#[derive(Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum ConflictStrategy {
+    // 删除后重新创建，与备份文件中的语句一致
+    #[default]
+    DropRecreate,
+    // 保留表结构，清空数据后导入
+    TruncateLoad,
+    // 跳过目标数据库中已经存在的表
+    SkipExisting,
+}
+
+// 定义恢复选项，前端未传入的字段使用默认值
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RestoreOptions {
+    // 恢复到的数据库名，为空时使用备份中的数据库名
+    pub(crate) target_database: Option<String>,
+    // 只恢复这些表，为空时恢复全部表以及存储过程、视图、触发器和事件
+    pub(crate) tables: Vec<String>,
+    // 表已存在时的处理方式
+    conflict: ConflictStrategy,
+}
+
+impl RestoreOptions {
+    // 把备份中的全部对象恢复到指定的数据库
+    pub(crate) fn into_database(target_database: &str) -> Self {
+        RestoreOptions {
+            target_database: Some(target_database.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+// 恢复目标服务器的连接信息
+pub(crate) struct ConnectionInfo {
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    pub(crate) username: String,
+    pub(crate) password: String,
+}
+
+impl ConnectionInfo {
+    // 连接服务器，database为None时不选择默认数据库
+    pub(crate) fn connect(&self, database: Option<&str>) -> Result<Conn, String> {
+        let opts = OptsBuilder::new()
+            .ip_or_hostname(Some(self.host.as_str()))
+            .tcp_port(self.port)
+            .user(Some(self.username.as_str()))
+            .pass(Some(self.password.as_str()))
+            .db_name(database);
+
+        Conn::new(opts).map_err(|e| format!("连接数据库失败: {}", e))
+    }
+}
+
+// 发送恢复进度事件，数据结构与备份进度事件相同
+pub(crate) fn send_restore_progress(
+    window: &Window,
+    percent: u8,
+    status: &str,
+    current_table: Option<&str>,
+) {
+    let progress = BackupProgress {
+        job_id: None,
+        percent,
+        status: status.to_string(),
+        current_table: current_table.map(|s| s.to_string()),
+        worker: None,
+    };
+
+    window
+        .emit("restore-progress", progress)
+        .unwrap_or_else(|e| {
+            eprintln!("发送恢复进度事件失败: {}", e);
+        });
+}
+
+// 从备份文件恢复数据库
+#[command]
+pub async fn restore_mysql(
+    window: Window,
+    host: &str,
+    port: u16,
+    username: &str,
+    password: &str,
+    archive_path: &str,
+    engine: Option<&str>,
+    options: Option<RestoreOptions>,
+    key: Option<DecryptionKey>,
+    restore_state: State<'_, RestoreState>,
+) -> Result<String, String> {
+    // 检查是否已经有恢复在运行，并标记恢复已开始
+    let cancel_requested = restore_state.begin()?;
+
+    send_restore_progress(&window, 0, "正在准备恢复...", None);
+
+    // 克隆需要的数据以便在线程中使用
+    let window_clone = window.clone();
+    let connection = ConnectionInfo {
+        host: host.to_string(),
+        port,
+        username: username.to_string(),
+        password: password.to_string(),
+    };
+    let archive_path = archive_path.to_string();
+    let engine = engine.map(|s| s.to_string());
+    let options = options.unwrap_or_default();
+
+    // 创建通道用于接收结果
+    let (tx, mut rx) = mpsc::channel::<Result<String, String>>(1);
+
+    let _ = thread::spawn(move || {
+        let restore_result = restore_archive(
+            &window_clone,
+            &connection,
+            &archive_path,
+            engine.as_deref(),
+            &options,
+            key.as_ref(),
+            &cancel_requested,
+        );
+
+        if let Err(e) = tx.blocking_send(restore_result) {
+            eprintln!("无法发送恢复结果: {}", e);
+        }
+    });
+
+    // 等待结果
+    let result = match rx.recv().await {
+        Some(r) => r,
+        None => Err("恢复过程意外终止".to_string()),
+    };
+
+    // 释放恢复中标记
+    restore_state.end()?;
+
+    result
+}
+
+// 请求取消正在运行的恢复，返回是否有恢复在运行
+#[command]
+pub fn cancel_restore(restore_state: State<'_, RestoreState>) -> bool {
+    let is_running = restore_state
+        .is_running
+        .lock()
+        .map(|is_running| *is_running)
+        .unwrap_or(false);
+    if is_running {
+        restore_state.cancel_requested.store(true, Ordering::SeqCst);
+    }
+    is_running
+}
+
+// 检查系统中是否有mysql命令行客户端可用
+fn is_mysql_client_available() -> bool {
+    is_command_available("mysql")
+}
+
+// 恢复流程：读取归档、选择执行器、按顺序执行每个SQL文件中的语句
+pub(crate) fn restore_archive(
+    window: &Window,
+    connection: &ConnectionInfo,
+    archive_path: &str,
+    engine: Option<&str>,
+    options: &RestoreOptions,
+    key: Option<&DecryptionKey>,
+    cancel_requested: &AtomicBool,
+) -> Result<String, String> {
+    send_restore_progress(window, 2, "正在读取备份文件...", None);
+
+    if let Some(target) = &options.target_database {
+        if target.trim().is_empty() {
+            return Err("目标数据库名不能为空".to_string());
+        }
+    }
+
+    // ZIP可以预先列出条目并检查选择的表，其他格式只能顺序读取，在恢复后检查
+    if let Some(names) = archive::zip_entry_names(Path::new(archive_path), key)? {
+        check_restore_entries(&names, options)?;
+    }
+
+    send_restore_progress(window, 5, "连接数据库...", None);
+
+    let mut executor = RestoreExecutor::for_engine(connection, engine)?;
+
+    // 先读取清单以了解备份包含的内容，tar.zst的清单在恢复过程中读取
+    let mut backup_manifest = archive::read_manifest_upfront(Path::new(archive_path), key)?;
+    let mode = backup_manifest
+        .as_ref()
+        .map(|manifest| manifest.mode)
+        .unwrap_or_default();
+
+    let mut rewriter = StatementRewriter::new(options, connection, mode);
+    let result = restore_entries(
+        window,
+        Path::new(archive_path),
+        key,
+        options,
+        &mut executor,
+        &mut rewriter,
+        &mut backup_manifest,
+        cancel_requested,
+    );
+
+    match result {
+        Ok(_) => {
+            send_restore_progress(window, 95, "正在等待数据库完成写入...", None);
+            executor.finish()?;
+        }
+        Err(e) => {
+            let detail = executor.abort();
+            if e == RESTORE_CANCELLED {
+                send_restore_progress(window, 0, RESTORE_CANCELLED, None);
+                return Err(e);
+            }
+            return match detail {
+                Some(detail) => Err(format!("{}: {}", e, detail)),
+                None => Err(e),
+            };
+        }
+    }
+
+    let missing_tables = rewriter.missing_tables();
+    if !missing_tables.is_empty() {
+        return Err(format!(
+            "恢复已完成，但备份中不存在以下表: {}",
+            missing_tables.join(", ")
+        ));
+    }
+
+    // 备份只有结构或数据，或者使用了表过滤时，提示恢复的内容不完整
+    let notes = backup_manifest
+        .map(|manifest| manifest.partial_notes())
+        .unwrap_or_default();
+    if notes.is_empty() {
+        send_restore_progress(window, 100, "恢复完成", None);
+    } else {
+        send_restore_progress(
+            window,
+            100,
+            &format!("恢复完成（{}）", notes.join("；")),
+            None,
+        );
+    }
+    Ok(archive_path.to_string())
+}
+
+// 检查ZIP归档中是否有可恢复的SQL文件，以及选择的表是否都在备份中
+fn check_restore_entries(names: &[String], options: &RestoreOptions) -> Result<(), String> {
+    let names: Vec<&String> = names.iter().filter(|name| name.ends_with(".sql")).collect();
+    if names.is_empty() {
+        return Err("备份文件中没有可恢复的SQL文件".to_string());
+    }
+    for name in &names {
+        check_server_entry(name, options)?;
+    }
+
+    // mysqldump归档中所有表都在一个文件里，只能在恢复时检查
+    if options.tables.is_empty() || names.iter().any(|name| *name == "mysqldump_backup.sql") {
+        return Ok(());
+    }
+
+    let missing_tables: Vec<&str> = options
+        .tables
+        .iter()
+        .filter(|table| {
+            !names
+                .iter()
+                .any(|name| table_name_from_entry(name).as_deref() == Some(table.as_str()))
+        })
+        .map(|table| table.as_str())
+        .collect();
+    if !missing_tables.is_empty() {
+        return Err(format!("备份中不存在以下表: {}", missing_tables.join(", ")));
+    }
+
+    Ok(())
+}
+
+// 多数据库备份的条目在以数据库名命名的目录中，只能整体恢复到备份中的各个数据库
+fn check_server_entry(entry_name: &str, options: &RestoreOptions) -> Result<(), String> {
+    if entry_name.contains('/') && (options.target_database.is_some() || !options.tables.is_empty())
+    {
+        return Err("多数据库备份只能恢复到原数据库，不能指定目标数据库或表".to_string());
+    }
+    Ok(())
+}
+
+// 是否需要恢复该条目：归档按恢复顺序存储（数据库信息、表、存储过程、视图、触发器、事件），
+// 只恢复部分表时跳过其他表的文件以及存储过程、视图和事件
+fn should_restore_entry(entry_name: &str, options: &RestoreOptions) -> bool {
+    if !entry_name.ends_with(".sql") {
+        return false;
+    }
+    if options.tables.is_empty() {
+        return true;
+    }
+
+    match table_name_from_entry(entry_name) {
+        Some(table) => options.tables.contains(&table),
+        None => matches!(
+            entry_name,
+            "00_database_info.sql"
+                | crate::DUMP_FOOTER_ENTRY
+                | "triggers.sql"
+                | "mysqldump_backup.sql"
+                | archive::SQL_STREAM_ENTRY
+        ),
+    }
+}
+
+// 按存储顺序执行每个SQL文件中的语句，按已读取的备份文件字节数报告进度
+fn restore_entries(
+    window: &Window,
+    archive_path: &Path,
+    key: Option<&DecryptionKey>,
+    options: &RestoreOptions,
+    executor: &mut RestoreExecutor,
+    rewriter: &mut StatementRewriter,
+    backup_manifest: &mut Option<BackupManifest>,
+    cancel_requested: &AtomicBool,
+) -> Result<(), String> {
+    let mut restored_entries = 0;
+    // 内置引擎的表条目没有文件头，沿用清单或前面条目中文件头设置的sql_mode
+    let mut no_backslash_escapes = backup_manifest
+        .as_ref()
+        .is_some_and(|manifest| manifest.no_backslash_escapes());
+
+    archive::read_entries(archive_path, key, |entry_name, entry, progress| {
+        if entry_name == MANIFEST_ENTRY {
+            let mut content = Vec::new();
+            entry
+                .read_to_end(&mut content)
+                .map_err(|e| format!("读取备份清单失败: {}", e))?;
+            *backup_manifest = Some(manifest::parse_manifest(&content)?);
+            return Ok(());
+        }
+        check_server_entry(entry_name, options)?;
+        if !should_restore_entry(entry_name, options) {
+            return Ok(());
+        }
+        restored_entries += 1;
+
+        let mut current_table = table_name_from_entry(entry_name);
+        let mut last_percent = None;
+        let mut statements = SqlStatementReader::new(BufReader::new(entry))
+            .with_no_backslash_escapes(no_backslash_escapes);
+
+        loop {
+            let statement = match statements.next_statement() {
+                Ok(Some(statement)) => statement,
+                Ok(None) => break,
+                Err(e) => return Err(format!("读取备份文件 {} 失败: {}", entry_name, e)),
+            };
+
+            if cancel_requested.load(Ordering::SeqCst) {
+                return Err(RESTORE_CANCELLED.to_string());
+            }
+
+            // mysqldump归档和单文件SQL中所有表都在一个文件里，根据语句识别当前表
+            let kind = classify_statement(&statement);
+            if let StatementKind::Table { name, .. } = &kind {
+                if current_table.as_deref() != Some(name.as_str()) {
+                    current_table = Some(name.clone());
+                    last_percent = None;
+                }
+            }
+
+            let percent = 5 + (progress.fraction() * 90.0) as u8;
+            if last_percent != Some(percent) {
+                send_restore_progress(window, percent, "正在恢复...", current_table.as_deref());
+                last_percent = Some(percent);
+            }
+
+            for statement in rewriter.rewrite(statement, kind)? {
+                executor.execute(&statement)?;
+            }
+        }
+        no_backslash_escapes = statements.no_backslash_escapes();
+
+        Ok(())
+    })?;
+
+    if restored_entries == 0 {
+        return Err("备份文件中没有可恢复的SQL文件".to_string());
+    }
+
+    Ok(())
+}
+
+// 从内置引擎的表文件名中提取表名，例如table_orders.sql和table_orders.part0001.sql
+fn table_name_from_entry(entry_name: &str) -> Option<String> {
+    let name = entry_name.strip_prefix("table_")?.strip_suffix(".sql")?;
+    match name.rsplit_once(".part") {
+        Some((table, part)) if part.len() == 4 && part.bytes().all(|b| b.is_ascii_digit()) => {
+            Some(table.to_string())
+        }
+        _ => Some(name.to_string()),
+    }
+}
+
+// 语句开头的词法单元
+#[derive(Debug, PartialEq)]
+enum Token {
+    // 未加引号的单词，保留原始大小写
+    Word(String),
+    // 反引号包裹的标识符，已去掉引号
+    Identifier(String),
+    Symbol(u8),
+}
+
+// 带有原语句中字节位置的词法单元，用于改写语句中的名称
+struct SpannedToken {
+    token: Token,
+    start: usize,
+    end: usize,
+}
+
+impl SpannedToken {
+    fn is_word(&self, keyword: &str) -> bool {
+        matches!(&self.token, Token::Word(word) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn is_symbol(&self, symbol: u8) -> bool {
+        self.token == Token::Symbol(symbol)
+    }
+
+    fn name(&self) -> Option<&str> {
+        match &self.token {
+            Token::Word(name) | Token::Identifier(name) => Some(name),
+            Token::Symbol(_) => None,
+        }
+    }
+}
+
+// 解析语句开头最多limit个词法单元，可执行注释的标记视为空白
+fn leading_tokens(statement: &[u8], limit: usize) -> Vec<SpannedToken> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < statement.len() && tokens.len() < limit {
+        let b = statement[i];
+        let start = i;
+
+        if b.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        if statement[i..].starts_with(b"/*!") || statement[i..].starts_with(b"/*+") {
+            i += 3;
+            while i < statement.len() && statement[i].is_ascii_digit() {
+                i += 1;
+            }
+            continue;
+        }
+        if statement[i..].starts_with(b"*/") {
+            i += 2;
+            continue;
+        }
+
+        if b == b'`' {
+            let mut name = Vec::new();
+            i += 1;
+            while i < statement.len() {
+                if statement[i] == b'`' {
+                    if statement.get(i + 1) == Some(&b'`') {
+                        name.push(b'`');
+                        i += 2;
+                        continue;
+                    }
+                    i += 1;
+                    break;
+                }
+                name.push(statement[i]);
+                i += 1;
+            }
+            tokens.push(SpannedToken {
+                token: Token::Identifier(String::from_utf8_lossy(&name).into_owned()),
+                start,
+                end: i,
+            });
+        } else if is_identifier_byte(b) {
+            while i < statement.len() {
+                let c = statement[i];
+                if !is_identifier_byte(c) {
+                    break;
+                }
+                i += 1;
+            }
+            tokens.push(SpannedToken {
+                token: Token::Word(String::from_utf8_lossy(&statement[start..i]).into_owned()),
+                start,
+                end: i,
+            });
+        } else {
+            i += 1;
+            tokens.push(SpannedToken {
+                token: Token::Symbol(b),
+                start,
+                end: i,
+            });
+        }
+    }
+
+    tokens
+}
+
+// 可以出现在未加引号的标识符中的字节
+fn is_identifier_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b >= 0x80
+}
+
+// 跳过IF EXISTS或IF NOT EXISTS，返回之后的位置
+fn skip_if_exists(tokens: &[SpannedToken], index: usize) -> usize {
+    let is_word = |i: usize, keyword: &str| tokens.get(i).map_or(false, |t| t.is_word(keyword));
+    if is_word(index, "IF") && is_word(index + 1, "EXISTS") {
+        index + 2
+    } else if is_word(index, "IF") && is_word(index + 1, "NOT") && is_word(index + 2, "EXISTS") {
+        index + 3
+    } else {
+        index
+    }
+}
+
+// 读取对象名，支持`db`.`name`形式并只返回对象本身的名称，同时返回之后的位置
+fn object_name(tokens: &[SpannedToken], index: usize) -> Option<(String, usize)> {
+    let name = tokens.get(index)?.name()?;
+    if tokens.get(index + 1).map_or(false, |t| t.is_symbol(b'.')) {
+        if let Some(qualified) = tokens.get(index + 2).and_then(|t| t.name()) {
+            return Some((qualified.to_string(), index + 3));
+        }
+    }
+    Some((name.to_string(), index + 1))
+}
+
+// 对表的操作类型
+#[derive(Debug, PartialEq)]
+pub(crate) enum TableAction {
+    Drop,
+    // 建表语句，记录TABLE关键字结束的位置以及是否已有IF NOT EXISTS
+    Create {
+        keyword_end: usize,
+        if_not_exists: bool,
+    },
+    // 锁表、插入、禁用索引等数据相关语句
+    Data,
+}
+
+// 恢复时需要区别处理的语句类型
+#[derive(Debug, PartialEq)]
+pub(crate) enum StatementKind {
+    // 删除、创建、修改或切换数据库，记录数据库名在语句中的位置
+    Database {
+        name: String,
+        drop: bool,
+        is_use: bool,
+        start: usize,
+        end: usize,
+    },
+    Table {
+        name: String,
+        action: TableAction,
+    },
+    CreateTrigger {
+        name: String,
+        table: String,
+    },
+    DropTrigger,
+    // 存储过程、函数、视图和事件
+    Object,
+    Other,
+}
+
+// 根据语句开头的关键字判断语句类型
+pub(crate) fn classify_statement(statement: &[u8]) -> StatementKind {
+    let tokens = leading_tokens(statement, 48);
+    let is_word = |i: usize, keyword: &str| tokens.get(i).map_or(false, |t| t.is_word(keyword));
+    let database = |index: usize, drop: bool, is_use: bool| match tokens.get(index) {
+        Some(token) => match token.name() {
+            Some(name) => StatementKind::Database {
+                name: name.to_string(),
+                drop,
+                is_use,
+                start: token.start,
+                end: token.end,
+            },
+            None => StatementKind::Other,
+        },
+        None => StatementKind::Other,
+    };
+    let table = |index: usize, action: TableAction| match object_name(&tokens, index) {
+        Some((name, _)) => StatementKind::Table { name, action },
+        None => StatementKind::Other,
+    };
+
+    if is_word(0, "USE") {
+        return database(1, false, true);
+    }
+
+    if is_word(0, "DROP") {
+        if is_word(1, "DATABASE") || is_word(1, "SCHEMA") {
+            return database(skip_if_exists(&tokens, 2), true, false);
+        }
+        let index = if is_word(1, "TEMPORARY") { 2 } else { 1 };
+        if is_word(index, "TABLE") {
+            return table(skip_if_exists(&tokens, index + 1), TableAction::Drop);
+        }
+        if is_word(1, "TRIGGER") {
+            return StatementKind::DropTrigger;
+        }
+        if ["VIEW", "PROCEDURE", "FUNCTION", "EVENT"]
+            .iter()
+            .any(|keyword| is_word(1, keyword))
+        {
+            return StatementKind::Object;
+        }
+        return StatementKind::Other;
+    }
+
+    if is_word(0, "CREATE") {
+        // CREATE和对象类型之间可能有DEFINER、ALGORITHM等子句
+        let keyword_index = tokens.iter().position(|t| {
+            [
+                "DATABASE",
+                "SCHEMA",
+                "TABLE",
+                "TRIGGER",
+                "VIEW",
+                "PROCEDURE",
+                "FUNCTION",
+                "EVENT",
+            ]
+            .iter()
+            .any(|keyword| t.is_word(keyword))
+        });
+        let keyword_index = match keyword_index {
+            Some(index) => index,
+            None => return StatementKind::Other,
+        };
+
+        if is_word(keyword_index, "DATABASE") || is_word(keyword_index, "SCHEMA") {
+            return database(skip_if_exists(&tokens, keyword_index + 1), false, false);
+        }
+        if is_word(keyword_index, "TABLE") {
+            let name_index = skip_if_exists(&tokens, keyword_index + 1);
+            return table(
+                name_index,
+                TableAction::Create {
+                    keyword_end: tokens[keyword_index].end,
+                    if_not_exists: name_index != keyword_index + 1,
+                },
+            );
+        }
+        if is_word(keyword_index, "TRIGGER") {
+            let (name, next) = match object_name(&tokens, keyword_index + 1) {
+                Some(name) => name,
+                None => return StatementKind::Other,
+            };
+            let on_index = match tokens[next..].iter().position(|t| t.is_word("ON")) {
+                Some(offset) => next + offset,
+                None => return StatementKind::Other,
+            };
+            return match object_name(&tokens, on_index + 1) {
+                Some((table, _)) => StatementKind::CreateTrigger { name, table },
+                None => StatementKind::Other,
+            };
+        }
+        return StatementKind::Object;
+    }
+
+    if is_word(0, "ALTER") {
+        if is_word(1, "DATABASE") || is_word(1, "SCHEMA") {
+            return database(2, false, false);
+        }
+        if is_word(1, "TABLE") {
+            return table(2, TableAction::Data);
+        }
+        return StatementKind::Other;
+    }
+
+    if is_word(0, "LOCK") && is_word(1, "TABLES") {
+        return table(2, TableAction::Data);
+    }
+
+    if is_word(0, "INSERT") || is_word(0, "REPLACE") {
+        return match tokens.iter().position(|t| t.is_word("INTO")) {
+            Some(index) => table(index + 1, TableAction::Data),
+            None => StatementKind::Other,
+        };
+    }
+
+    if is_word(0, "TRUNCATE") {
+        let index = if is_word(1, "TABLE") { 2 } else { 1 };
+        return table(index, TableAction::Data);
+    }
+
+    StatementKind::Other
+}
+
+// 统计INSERT语句VALUES之后的行数，只计算引号外最外层的括号。
+// no_backslash_escapes为true时反斜杠是普通字符
+pub(crate) fn count_insert_rows(statement: &[u8], no_backslash_escapes: bool) -> u64 {
+    let mut quote: Option<u8> = None;
+    let mut escaped = false;
+    let mut in_values = false;
+    let mut depth = 0usize;
+    let mut rows = 0;
+    let mut i = 0;
+
+    while i < statement.len() {
+        let b = statement[i];
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if b == b'\\' && q != b'`' && !no_backslash_escapes {
+                escaped = true;
+            } else if b == q {
+                quote = None;
+            }
+        } else if matches!(b, b'\'' | b'"' | b'`') {
+            quote = Some(b);
+        } else if !in_values {
+            if statement.len() - i >= 6
+                && statement[i..i + 6].eq_ignore_ascii_case(b"VALUES")
+                && (i == 0 || !is_identifier_byte(statement[i - 1]))
+                && statement
+                    .get(i + 6)
+                    .map_or(true, |c| !is_identifier_byte(*c))
+            {
+                in_values = true;
+                i += 6;
+                continue;
+            }
+        } else if b == b'(' {
+            if depth == 0 {
+                rows += 1;
+            }
+            depth += 1;
+        } else if b == b')' {
+            depth = depth.saturating_sub(1);
+        }
+        i += 1;
+    }
+
+    rows
+}
+
+// 按恢复选项改写语句：替换数据库名、过滤表、处理已存在的表
+struct StatementRewriter<'a> {
+    options: &'a RestoreOptions,
+    connection: &'a ConnectionInfo,
+    // 目标数据库中恢复开始前已经存在的表，仅在跳过已存在的表时使用
+    existing_tables: HashSet<String>,
+    // 备份中出现过的表
+    seen_tables: HashSet<String>,
+    // 备份包含的内容，只有数据的备份没有建表语句，清空表时在导入数据前执行
+    mode: BackupMode,
+}
+
+impl<'a> StatementRewriter<'a> {
+    fn new(options: &'a RestoreOptions, connection: &'a ConnectionInfo, mode: BackupMode) -> Self {
+        StatementRewriter {
+            options,
+            connection,
+            existing_tables: HashSet::new(),
+            seen_tables: HashSet::new(),
+            mode,
+        }
+    }
+
+    fn includes_table(&self, table: &str) -> bool {
+        self.options.tables.is_empty() || self.options.tables.iter().any(|t| t == table)
+    }
+
+    // 返回需要执行的语句，可能为空（跳过）或多条
+    fn rewrite(&mut self, statement: Vec<u8>, kind: StatementKind) -> Result<Vec<Vec<u8>>, String> {
+        match kind {
+            StatementKind::Database {
+                name,
+                drop,
+                is_use,
+                start,
+                end,
+            } => {
+                // 只恢复部分表或保留已有表时不能删除整个数据库
+                if drop
+                    && (!self.options.tables.is_empty()
+                        || self.options.conflict != ConflictStrategy::DropRecreate)
+                {
+                    return Ok(Vec::new());
+                }
+
+                let (database, statement) = match &self.options.target_database {
+                    Some(target) => {
+                        let mut rewritten = statement[..start].to_vec();
+                        rewritten.extend_from_slice(quote_identifier(target).as_bytes());
+                        rewritten.extend_from_slice(&statement[end..]);
+                        (target.clone(), rewritten)
+                    }
+                    None => (name, statement),
+                };
+
+                if is_use && self.options.conflict == ConflictStrategy::SkipExisting {
+                    self.existing_tables = query_existing_tables(self.connection, &database)?;
+                }
+
+                Ok(vec![statement])
+            }
+            StatementKind::Table { name, action } => {
+                if !self.includes_table(&name) {
+                    return Ok(Vec::new());
+                }
+                let first_statement = self.seen_tables.insert(name.clone());
+
+                match self.options.conflict {
+                    ConflictStrategy::DropRecreate => Ok(vec![statement]),
+                    ConflictStrategy::SkipExisting => {
+                        if self.existing_tables.contains(&name) {
+                            Ok(Vec::new())
+                        } else {
+                            Ok(vec![statement])
+                        }
+                    }
+                    ConflictStrategy::TruncateLoad => match action {
+                        TableAction::Drop => Ok(Vec::new()),
+                        TableAction::Create {
+                            keyword_end,
+                            if_not_exists,
+                        } => {
+                            // 表不存在时创建，存在时保留结构并清空数据
+                            let create = if if_not_exists {
+                                statement
+                            } else {
+                                let mut rewritten = statement[..keyword_end].to_vec();
+                                rewritten.extend_from_slice(b" IF NOT EXISTS");
+                                rewritten.extend_from_slice(&statement[keyword_end..]);
+                                rewritten
+                            };
+                            let truncate = format!("TRUNCATE TABLE {}", quote_identifier(&name));
+                            Ok(vec![create, truncate.into_bytes()])
+                        }
+                        TableAction::Data
+                            if first_statement && self.mode == BackupMode::DataOnly =>
+                        {
+                            let truncate = format!("TRUNCATE TABLE {}", quote_identifier(&name));
+                            Ok(vec![truncate.into_bytes(), statement])
+                        }
+                        TableAction::Data => Ok(vec![statement]),
+                    },
+                }
+            }
+            StatementKind::CreateTrigger { name, table } => {
+                if !self.includes_table(&table)
+                    || (self.options.conflict == ConflictStrategy::SkipExisting
+                        && self.existing_tables.contains(&table))
+                {
+                    return Ok(Vec::new());
+                }
+                // 备份中的DROP TRIGGER不带表名无法过滤，统一在创建前删除同名触发器
+                let drop = format!("DROP TRIGGER IF EXISTS {}", quote_identifier(&name));
+                Ok(vec![drop.into_bytes(), statement])
+            }
+            StatementKind::DropTrigger => Ok(Vec::new()),
+            StatementKind::Object => {
+                if self.options.tables.is_empty() {
+                    Ok(vec![statement])
+                } else {
+                    Ok(Vec::new())
+                }
+            }
+            StatementKind::Other => Ok(vec![statement]),
+        }
+    }
+
+    // 选择的表中备份里没有的表
+    fn missing_tables(&self) -> Vec<String> {
+        self.options
+            .tables
+            .iter()
+            .filter(|table| !self.seen_tables.contains(*table))
+            .cloned()
+            .collect()
+    }
+}
+
+// 查询目标数据库中已经存在的表
+fn query_existing_tables(
+    connection: &ConnectionInfo,
+    database: &str,
+) -> Result<HashSet<String>, String> {
+    let mut conn = connection.connect(None)?;
+    let tables: Vec<String> = conn
+        .exec(
+            "SELECT TABLE_NAME FROM information_schema.TABLES WHERE TABLE_SCHEMA = ?",
+            (database,),
+        )
+        .map_err(|e| format!("查询已存在的表失败: {}", e))?;
+
+    Ok(tables.into_iter().collect())
+}
+
+// 语句执行器
+pub(crate) enum RestoreExecutor {
+    // 内置执行器，通过mysql连接逐条执行语句
+    Builtin(Conn),
+    // mysql命令行客户端，语句写入其标准输入
+    Cli {
+        child: Child,
+        stdin: Option<ChildStdin>,
+        stderr_reader: Option<JoinHandle<String>>,
+    },
+}
+
+impl RestoreExecutor {
+    // 有mysql客户端时优先使用，否则使用内置执行器
+    pub(crate) fn for_engine(
+        connection: &ConnectionInfo,
+        engine: Option<&str>,
+    ) -> Result<Self, String> {
+        match engine {
+            Some("mysql") => {
+                if is_mysql_client_available() {
+                    RestoreExecutor::cli(connection)
+                } else {
+                    Err("指定使用mysql客户端但系统中没有可用的mysql命令".to_string())
+                }
+            }
+            Some("builtin") => RestoreExecutor::builtin(connection),
+            _ => {
+                if is_mysql_client_available() {
+                    RestoreExecutor::cli(connection)
+                } else {
+                    RestoreExecutor::builtin(connection)
+                }
+            }
+        }
+    }
+
+    fn builtin(connection: &ConnectionInfo) -> Result<Self, String> {
+        Ok(RestoreExecutor::Builtin(connection.connect(None)?))
+    }
+
+    fn cli(connection: &ConnectionInfo) -> Result<Self, String> {
+        let mut cmd = Command::new("mysql");
+
+        // 在Windows平台上添加无窗口标志
+        #[cfg(target_os = "windows")]
+        cmd.creation_flags(0x08000000);
+
+        cmd.arg(format!("--host={}", connection.host))
+            .arg(format!("--port={}", connection.port))
+            .arg(format!("--user={}", connection.username));
+
+        // 如果密码不为空，则添加密码参数
+        if !connection.password.is_empty() {
+            cmd.arg(format!("--password={}", connection.password));
+        }
+
+        cmd.arg("--default-character-set=utf8mb4")
+            .arg("--binary-mode")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| format!("执行mysql命令失败: {}", e))?;
+
+        // 在单独的线程中读取错误输出，避免管道写满导致mysql阻塞
+        let stderr = child.stderr.take();
+        let stderr_reader = thread::spawn(move || {
+            let mut message = String::new();
+            if let Some(mut stderr) = stderr {
+                let _ = stderr.read_to_string(&mut message);
+            }
+            message
+        });
+
+        let mut stdin = child.stdin.take();
+        if let Some(stdin) = stdin.as_mut() {
+            let header = [b"DELIMITER ".as_slice(), CLI_DELIMITER, b"\n"].concat();
+            if let Err(e) = stdin.write_all(&header) {
+                let _ = child.kill();
+                return Err(format!("向mysql客户端写入语句失败: {}", e));
+            }
+        }
+
+        Ok(RestoreExecutor::Cli {
+            child,
+            stdin,
+            stderr_reader: Some(stderr_reader),
+        })
+    }
+
+    pub(crate) fn execute(&mut self, statement: &[u8]) -> Result<(), String> {
+        match self {
+            RestoreExecutor::Builtin(conn) => {
+                let sql = std::str::from_utf8(statement).map_err(|_| {
+                    "语句中包含非UTF-8数据，内置执行器无法执行，请安装mysql客户端后重试".to_string()
+                })?;
+                conn.query_drop(sql)
+                    .map_err(|e| format!("执行语句失败: {}\n{}", e, statement_preview(sql)))
+            }
+            RestoreExecutor::Cli { stdin, .. } => {
+                let stdin = match stdin.as_mut() {
+                    Some(stdin) => stdin,
+                    None => return Err("mysql客户端的输入已关闭".to_string()),
+                };
+                stdin
+                    .write_all(statement)
+                    .and_then(|_| stdin.write_all(b"\n"))
+                    .and_then(|_| stdin.write_all(CLI_DELIMITER))
+                    .and_then(|_| stdin.write_all(b"\n"))
+                    .map_err(|e| format!("向mysql客户端写入语句失败: {}", e))
+            }
+        }
+    }
+
+    // 所有语句写入完成，等待执行器执行完毕
+    pub(crate) fn finish(self) -> Result<(), String> {
+        match self {
+            RestoreExecutor::Builtin(_) => Ok(()),
+            RestoreExecutor::Cli {
+                mut child,
+                stdin,
+                stderr_reader,
+            } => {
+                // 关闭标准输入，mysql执行完剩余语句后退出
+                drop(stdin);
+                let status = child
+                    .wait()
+                    .map_err(|e| format!("等待mysql客户端结束失败: {}", e))?;
+                let stderr_message = stderr_reader
+                    .and_then(|reader| reader.join().ok())
+                    .unwrap_or_default();
+
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(format!("恢复失败: {}", stderr_message.trim()))
+                }
+            }
+        }
+    }
+
+    // 中止执行器，返回mysql客户端已输出的错误信息
+    pub(crate) fn abort(self) -> Option<String> {
+        match self {
+            RestoreExecutor::Builtin(_) => None,
+            RestoreExecutor::Cli {
+                mut child,
+                stdin,
+                stderr_reader,
+            } => {
+                drop(stdin);
+                let _ = child.kill();
+                let _ = child.wait();
+                stderr_reader
+                    .and_then(|reader| reader.join().ok())
+                    .map(|message| message.trim().to_string())
+                    .filter(|message| !message.is_empty())
+            }
+        }
+    }
+}
+
+// 截取语句开头用于错误信息
+fn statement_preview(sql: &str) -> String {
+    let preview: String = sql.chars().take(200).collect();
+    if preview.len() < sql.len() {
+        format!("{}...", preview)
+    } else {
+        preview
+    }
+}
+
+// 词法状态
+#[derive(Clone, Copy, PartialEq)]
+enum LexState {
+    Normal,
+    // 在引号内，记录引号字符
+    Quoted(u8),
+    // 引号内反斜杠之后的字符，NO_BACKSLASH_ESCAPES模式下不使用
+    Escaped(u8),
+    // 普通块注释，内容会被丢弃
+    Comment,
+    // /*! */ 可执行注释或 /*+ */ 优化器提示，内容保留
+    ExecutableComment,
+}
+
+// 把SQL脚本拆分为单条语句：识别引号、注释和DELIMITER命令，
+// 去掉普通注释，保留可执行注释，返回的语句不包含分隔符。
+// 脚本中修改sql_mode的语句会改变引号内反斜杠的含义，之后的语句按新的模式拆分
+pub(crate) struct SqlStatementReader<R> {
+    reader: R,
+    delimiter: Vec<u8>,
+    state: LexState,
+    current: Vec<u8>,
+    // 待返回的语句及拆分时是否处于NO_BACKSLASH_ESCAPES模式
+    pending: VecDeque<(Vec<u8>, bool)>,
+    finished: bool,
+    // 脚本结束时还有未结束的引号、注释或缺少分隔符的语句
+    incomplete: bool,
+    // 脚本开始时的模式，sql_mode恢复为保存的变量时回到这个模式
+    initial_no_backslash_escapes: bool,
+    no_backslash_escapes: bool,
+    // 最近返回的语句拆分时的模式
+    statement_no_backslash_escapes: bool,
+}
+
+impl<R: BufRead> SqlStatementReader<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        SqlStatementReader {
+            reader,
+            delimiter: b";".to_vec(),
+            state: LexState::Normal,
+            current: Vec::new(),
+            pending: VecDeque::new(),
+            finished: false,
+            incomplete: false,
+            initial_no_backslash_escapes: false,
+            no_backslash_escapes: false,
+            statement_no_backslash_escapes: false,
+        }
+    }
+
+    // 脚本开始时是否处于NO_BACKSLASH_ESCAPES模式，用于没有设置sql_mode文件头的条目，
+    // 例如内置引擎备份中每张表单独的条目
+    pub(crate) fn with_no_backslash_escapes(mut self, enabled: bool) -> Self {
+        self.initial_no_backslash_escapes = enabled;
+        self.no_backslash_escapes = enabled;
+        self.statement_no_backslash_escapes = enabled;
+        self
+    }
+
+    // 最近返回的语句中反斜杠是否只是普通字符，供count_insert_rows使用
+    pub(crate) fn statement_no_backslash_escapes(&self) -> bool {
+        self.statement_no_backslash_escapes
+    }
+
+    // 已读取部分结束时的模式，同一备份中的下一个条目从这个模式开始
+    pub(crate) fn no_backslash_escapes(&self) -> bool {
+        self.no_backslash_escapes
+    }
+
+    // 脚本读取完毕后，最后一条语句是否完整
+    pub(crate) fn is_complete(&self) -> bool {
+        self.finished && !self.incomplete
+    }
+
+    // 读取下一条语句，到达末尾时返回None
+    pub(crate) fn next_statement(&mut self) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            if let Some((statement, no_backslash_escapes)) = self.pending.pop_front() {
+                self.statement_no_backslash_escapes = no_backslash_escapes;
+                return Ok(Some(statement));
+            }
+            if self.finished {
+                return Ok(None);
+            }
+
+            let mut line = Vec::new();
+            if self.reader.read_until(b'\n', &mut line)? == 0 {
+                // 最后一条语句可以没有分隔符，但记录下来供校验使用
+                self.finished = true;
+                self.incomplete =
+                    self.state != LexState::Normal || !trim_ascii(&self.current).is_empty();
+                self.push_current();
+                continue;
+            }
+            self.process_line(&line);
+        }
+    }
+
+    fn process_line(&mut self, line: &[u8]) {
+        // DELIMITER是客户端命令，只能出现在语句开头
+        if self.state == LexState::Normal && self.current.iter().all(|b| b.is_ascii_whitespace()) {
+            let trimmed = trim_ascii(line);
+            if trimmed.len() > 10 && trimmed[..10].eq_ignore_ascii_case(b"DELIMITER ") {
+                let delimiter = trim_ascii(&trimmed[10..]);
+                if !delimiter.is_empty() {
+                    self.delimiter = delimiter.to_vec();
+                    self.current.clear();
+                }
+                return;
+            }
+        }
+
+        let mut line_comment = false;
+        let mut i = 0;
+        while i < line.len() {
+            let b = line[i];
+            match self.state {
+                LexState::Normal => {
+                    if line[i..].starts_with(&self.delimiter) {
+                        self.push_current();
+                        i += self.delimiter.len();
+                        continue;
+                    }
+
+                    match b {
+                        b'\'' | b'"' | b'`' => {
+                            self.state = LexState::Quoted(b);
+                            self.current.push(b);
+                        }
+                        // 行注释，忽略本行剩余内容
+                        b'#' => {
+                            line_comment = true;
+                            break;
+                        }
+                        b'-' if line[i..].starts_with(b"--")
+                            && line.get(i + 2).map_or(true, |c| {
+                                c.is_ascii_whitespace() || c.is_ascii_control()
+                            }) =>
+                        {
+                            line_comment = true;
+                            break;
+                        }
+                        b'/' if line.get(i + 1) == Some(&b'*') => {
+                            if matches!(line.get(i + 2), Some(b'!') | Some(b'+')) {
+                                self.state = LexState::ExecutableComment;
+                                self.current.extend_from_slice(b"/*");
+                            } else {
+                                self.state = LexState::Comment;
+                            }
+                            i += 2;
+                            continue;
+                        }
+                        _ => self.current.push(b),
+                    }
+                }
+                LexState::Quoted(quote) => {
+                    self.current.push(b);
+                    if b == b'\\' && quote != b'`' && !self.no_backslash_escapes {
+                        self.state = LexState::Escaped(quote);
+                    } else if b == quote {
+                        self.state = LexState::Normal;
+                    }
+                }
+                LexState::Escaped(quote) => {
+                    self.current.push(b);
+                    self.state = LexState::Quoted(quote);
+                }
+                LexState::Comment => {
+                    if line[i..].starts_with(b"*/") {
+                        // 注释替换为空格，避免前后的词连在一起
+                        self.current.push(b' ');
+                        self.state = LexState::Normal;
+                        i += 2;
+                        continue;
+                    }
+                }
+                LexState::ExecutableComment => {
+                    if line[i..].starts_with(b"*/") {
+                        self.current.extend_from_slice(b"*/");
+                        self.state = LexState::Normal;
+                        i += 2;
+                        continue;
+                    }
+                    self.current.push(b);
+                }
+            }
+            i += 1;
+        }
+
+        if line_comment {
+            self.current.push(b'\n');
+        }
+    }
+
+    // 当前语句结束，非空时加入待返回队列
+    fn push_current(&mut self) {
+        let statement = trim_ascii(&self.current).to_vec();
+        if !statement.is_empty() {
+            let mode = self.no_backslash_escapes;
+            match sql_mode_assignment(&statement) {
+                Some(SqlModeAssignment::Value(enabled)) => self.no_backslash_escapes = enabled,
+                Some(SqlModeAssignment::Restore) => {
+                    self.no_backslash_escapes = self.initial_no_backslash_escapes
+                }
+                None => {}
+            }
+            self.pending.push_back((statement, mode));
+        }
+        self.current.clear();
+    }
+}
+
+// sql_mode中NO_BACKSLASH_ESCAPES对应的位，mysqlbinlog以数字输出sql_mode
+const NO_BACKSLASH_ESCAPES_BIT: u64 = 1 << 20;
+
+// 语句对会话sql_mode的赋值
+#[derive(Debug, PartialEq)]
+enum SqlModeAssignment {
+    // 新的sql_mode是否包含NO_BACKSLASH_ESCAPES
+    Value(bool),
+    // 赋值为变量，例如文件尾的SET SQL_MODE=@OLD_SQL_MODE
+    Restore,
+}
+
+// 识别SET语句中对sql_mode的最后一次赋值，@OLD_SQL_MODE=@@SQL_MODE这样的读取不算赋值
+fn sql_mode_assignment(statement: &[u8]) -> Option<SqlModeAssignment> {
+    let text = String::from_utf8_lossy(statement).to_ascii_uppercase();
+    // mysqldump的文件头写在/*!40101 ... */中
+    let body = match text.strip_prefix("/*!") {
+        Some(rest) => rest.trim_start_matches(|c: char| c.is_ascii_digit()),
+        None => &text,
+    }
+    .trim_start();
+    if !body.starts_with("SET") || !body[3..].starts_with(|c: char| c.is_ascii_whitespace()) {
+        return None;
+    }
+
+    let bytes = body.as_bytes();
+    let mut assignment = None;
+    let mut search_from = 0;
+    while let Some(offset) = body[search_from..].find("SQL_MODE") {
+        let start = search_from + offset;
+        let end = start + "SQL_MODE".len();
+        search_from = end;
+        if start > 0 && is_identifier_byte(bytes[start - 1]) {
+            continue;
+        }
+        let rest = body[end..].trim_start();
+        let value = match rest.strip_prefix('=') {
+            Some(value) => value.trim_start(),
+            None => continue,
+        };
+        assignment = Some(match value.chars().next() {
+            Some(quote @ ('\'' | '"')) => {
+                let literal = value[1..].split(quote).next().unwrap_or_default();
+                SqlModeAssignment::Value(literal.contains("NO_BACKSLASH_ESCAPES"))
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let digits: String = value.chars().take_while(|c| c.is_ascii_digit()).collect();
+                let mode = digits.parse::<u64>().unwrap_or(0);
+                SqlModeAssignment::Value(mode & NO_BACKSLASH_ESCAPES_BIT != 0)
+            }
+            _ => SqlModeAssignment::Restore,
+        });
+    }
+    assignment
+}
+
+// 去掉首尾的ASCII空白字符
+fn trim_ascii(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    let end = bytes
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .map_or(start, |end| end + 1);
+    &bytes[start..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(script: &str) -> (Vec<String>, bool) {
+        let mut reader = SqlStatementReader::new(script.as_bytes());
+        let mut statements = Vec::new();
+        while let Some(statement) = reader.next_statement().unwrap() {
+            statements.push(String::from_utf8(statement).unwrap());
+        }
+        (statements, reader.is_complete())
+    }
+
+    fn statements(script: &str) -> Vec<String> {
+        split(script).0
+    }
+
+    #[test]
+    fn reader_keeps_trailing_backslash_without_backslash_escapes() {
+        // NO_BACKSLASH_ESCAPES模式下'C:\'是完整的字符串
+        let script = "INSERT INTO t VALUES ('C:\\'),('x');\nINSERT INTO t VALUES ('y');\n";
+        let mut reader = SqlStatementReader::new(script.as_bytes()).with_no_backslash_escapes(true);
+        let mut statements = Vec::new();
+        let mut rows = Vec::new();
+        while let Some(statement) = reader.next_statement().unwrap() {
+            rows.push(count_insert_rows(
+                &statement,
+                reader.statement_no_backslash_escapes(),
+            ));
+            statements.push(String::from_utf8(statement).unwrap());
+        }
+        assert!(reader.is_complete());
+        assert_eq!(
+            statements,
+            [
+                "INSERT INTO t VALUES ('C:\\'),('x')",
+                "INSERT INTO t VALUES ('y')"
+            ]
+        );
+        assert_eq!(rows, [2, 1]);
+
+        // 默认按反斜杠转义，同一脚本的引号没有结束
+        let (_, complete) = split(script);
+        assert!(!complete);
+    }
+
+    #[test]
+    fn reader_follows_sql_mode_from_dump_header() {
+        let script = "/*!40101 SET @OLD_SQL_MODE=@@SQL_MODE, \
+                      SQL_MODE='NO_AUTO_VALUE_ON_ZERO,NO_BACKSLASH_ESCAPES' */;\n\
+                      INSERT INTO t VALUES ('a\\');\n\
+                      /*!40101 SET SQL_MODE=@OLD_SQL_MODE */;\n\
+                      INSERT INTO t VALUES ('b\\'');\n";
+        let mut reader = SqlStatementReader::new(script.as_bytes());
+        let mut modes = Vec::new();
+        while let Some(statement) = reader.next_statement().unwrap() {
+            modes.push((
+                String::from_utf8(statement).unwrap(),
+                reader.statement_no_backslash_escapes(),
+            ));
+        }
+        assert!(reader.is_complete());
+        assert_eq!(modes.len(), 4);
+        assert_eq!(modes[1], ("INSERT INTO t VALUES ('a\\')".to_string(), true));
+        // 恢复保存的sql_mode后回到脚本开始时的模式
+        assert_eq!(
+            modes[3],
+            ("INSERT INTO t VALUES ('b\\'')".to_string(), false)
+        );
+        assert!(!reader.no_backslash_escapes());
+    }
+
+    #[test]
+    fn sql_mode_assignment_reads_literals_numbers_and_variables() {
+        assert_eq!(
+            sql_mode_assignment(b"SET sql_mode = 'ANSI_QUOTES,NO_BACKSLASH_ESCAPES'"),
+            Some(SqlModeAssignment::Value(true))
+        );
+        assert_eq!(
+            sql_mode_assignment(b"SET @saved_sql_mode = @@sql_mode"),
+            None
+        );
+        assert_eq!(
+            sql_mode_assignment(b"SET sql_mode = @saved_sql_mode"),
+            Some(SqlModeAssignment::Restore)
+        );
+        // mysqlbinlog输出的sql_mode是数字，MySQL 8.0的默认值不包含NO_BACKSLASH_ESCAPES
+        assert_eq!(
+            sql_mode_assignment(b"SET @@session.sql_mode=1168113696/*!*/"),
+            Some(SqlModeAssignment::Value(false))
+        );
+        assert_eq!(
+            sql_mode_assignment(b"SET @@session.sql_mode=1169162272/*!*/"),
+            Some(SqlModeAssignment::Value(true))
+        );
+        assert_eq!(sql_mode_assignment(b"SELECT 'sql_mode=1'"), None);
+    }
+
+    #[test]
+    fn reader_splits_on_delimiter() {
+        assert_eq!(
+            statements("SELECT 1;\nSELECT 2; SELECT 3;\n"),
+            ["SELECT 1", "SELECT 2", "SELECT 3"]
+        );
+    }
+
+    #[test]
+    fn reader_ignores_delimiter_in_quotes() {
+        assert_eq!(
+            statements("INSERT INTO t VALUES ('a;b', \"c;d\", `e;f`);\nSELECT 1;"),
+            ["INSERT INTO t VALUES ('a;b', \"c;d\", `e;f`)", "SELECT 1"]
+        );
+    }
+
+    #[test]
+    fn reader_handles_escaped_quotes() {
+        assert_eq!(
+            statements(r"INSERT INTO t VALUES ('it\'s;', 'a''b;', 'c:\\');SELECT 2;"),
+            [
+                r"INSERT INTO t VALUES ('it\'s;', 'a''b;', 'c:\\')",
+                "SELECT 2"
+            ]
+        );
+        // 反引号内的反斜杠没有转义含义
+        assert_eq!(
+            statements("SELECT `a\\`;SELECT 2;"),
+            ["SELECT `a\\`", "SELECT 2"]
+        );
+    }
+
+    #[test]
+    fn reader_drops_line_comments() {
+        assert_eq!(
+            statements("SELECT 1; # tail;\n-- comment;\n--\nSELECT 2;"),
+            ["SELECT 1", "SELECT 2"]
+        );
+        // --后没有空白时不是注释
+        assert_eq!(statements("SELECT 1--1;"), ["SELECT 1--1"]);
+    }
+
+    #[test]
+    fn reader_drops_block_comments_and_keeps_executable_comments() {
+        assert_eq!(
+            statements("/* drop;\n still comment; */SELECT/**/1;\n/*!40101 SET NAMES utf8mb4; */;"),
+            ["SELECT 1", "/*!40101 SET NAMES utf8mb4; */"]
+        );
+        assert_eq!(
+            statements("SELECT /*+ MAX_EXECUTION_TIME(1) */ 1;"),
+            ["SELECT /*+ MAX_EXECUTION_TIME(1) */ 1"]
+        );
+    }
+
+    #[test]
+    fn reader_switches_delimiter() {
+        let script = "DELIMITER ;;\n\
+            CREATE TRIGGER t BEFORE INSERT ON x FOR EACH ROW BEGIN SET NEW.a = 1; END ;;\n\
+            DELIMITER ;\n\
+            SELECT 1;\n";
+        assert_eq!(
+            statements(script),
+            [
+                "CREATE TRIGGER t BEFORE INSERT ON x FOR EACH ROW BEGIN SET NEW.a = 1; END",
+                "SELECT 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn reader_reads_mysqlbinlog_delimiter() {
+        let script = "DELIMITER /*!*/;\n\
+            SET TIMESTAMP=1704074400/*!*/;\n\
+            BEGIN\n\
+            /*!*/;\n\
+            COMMIT/*!*/;\n\
+            DELIMITER ;\n";
+        assert_eq!(
+            statements(script),
+            ["SET TIMESTAMP=1704074400", "BEGIN", "COMMIT"]
+        );
+    }
+
+    #[test]
+    fn reader_reports_incomplete_script() {
+        assert_eq!(split("SELECT 1;"), (vec!["SELECT 1".to_string()], true));
+        assert_eq!(split("SELECT 1"), (vec!["SELECT 1".to_string()], false));
+        assert_eq!(
+            split("SELECT 'abc"),
+            (vec!["SELECT 'abc".to_string()], false)
+        );
+        assert_eq!(
+            split("SELECT 1; /* open"),
+            (vec!["SELECT 1".to_string()], false)
+        );
+    }
+
+    #[test]
+    fn classify_database_statements() {
+        let statement = b"USE `shop`";
+        match classify_statement(statement) {
+            StatementKind::Database {
+                name,
+                drop,
+                is_use,
+                start,
+                end,
+            } => {
+                assert_eq!(name, "shop");
+                assert!(!drop && is_use);
+                assert_eq!(&statement[start..end], b"`shop`");
+            }
+            kind => panic!("unexpected {:?}", kind),
+        }
+
+        assert!(matches!(
+            classify_statement(b"DROP DATABASE IF EXISTS `shop`"),
+            StatementKind::Database { name, drop: true, is_use: false, .. } if name == "shop"
+        ));
+        assert!(matches!(
+            classify_statement(b"/*!40000 CREATE DATABASE IF NOT EXISTS shop */"),
+            StatementKind::Database { name, drop: false, .. } if name == "shop"
+        ));
+    }
+
+    #[test]
+    fn classify_table_statements() {
+        assert_eq!(
+            classify_statement(b"DROP TABLE IF EXISTS `a``b`"),
+            StatementKind::Table {
+                name: "a`b".to_string(),
+                action: TableAction::Drop,
+            }
+        );
+        assert_eq!(
+            classify_statement(b"CREATE TABLE `orders` (id int)"),
+            StatementKind::Table {
+                name: "orders".to_string(),
+                action: TableAction::Create {
+                    keyword_end: 12,
+                    if_not_exists: false,
+                },
+            }
+        );
+        assert_eq!(
+            classify_statement(b"create table if not exists shop.orders (id int)"),
+            StatementKind::Table {
+                name: "orders".to_string(),
+                action: TableAction::Create {
+                    keyword_end: 12,
+                    if_not_exists: true,
+                },
+            }
+        );
+        for statement in [
+            &b"INSERT INTO `orders` VALUES (1)"[..],
+            b"LOCK TABLES `orders` WRITE",
+            b"/*!40000 ALTER TABLE `orders` DISABLE KEYS */",
+            b"TRUNCATE TABLE orders",
+        ] {
+            assert_eq!(
+                classify_statement(statement),
+                StatementKind::Table {
+                    name: "orders".to_string(),
+                    action: TableAction::Data,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn classify_objects() {
+        assert_eq!(
+            classify_statement(
+                b"CREATE DEFINER=`root`@`%` TRIGGER `trg` BEFORE INSERT ON `orders` \
+                  FOR EACH ROW SET NEW.total = 0"
+            ),
+            StatementKind::CreateTrigger {
+                name: "trg".to_string(),
+                table: "orders".to_string(),
+            }
+        );
+        assert_eq!(
+            classify_statement(b"DROP TRIGGER IF EXISTS `trg`"),
+            StatementKind::DropTrigger
+        );
+        assert_eq!(
+            classify_statement(
+                b"CREATE ALGORITHM=UNDEFINED DEFINER=`root`@`%` SQL SECURITY DEFINER \
+                  VIEW `v` AS select 1"
+            ),
+            StatementKind::Object
+        );
+        assert_eq!(
+            classify_statement(b"DROP PROCEDURE IF EXISTS `p`"),
+            StatementKind::Object
+        );
+        assert_eq!(
+            classify_statement(b"SET NAMES utf8mb4"),
+            StatementKind::Other
+        );
+    }
+
+    #[test]
+    fn count_insert_rows_counts_outer_parentheses() {
+        assert_eq!(
+            count_insert_rows(b"INSERT INTO t VALUES (1,'a'),(2,'b'),\n(3,'c')", false),
+            3
+        );
+        assert_eq!(
+            count_insert_rows(
+                b"INSERT INTO t VALUES (1,'(x),('),(2,CONCAT('a','b'))",
+                false
+            ),
+            2
+        );
+        assert_eq!(
+            count_insert_rows(br"INSERT INTO t VALUES ('it\'s (1)'),('x')", false),
+            2
+        );
+    }
+
+    #[test]
+    fn count_insert_rows_finds_values_keyword() {
+        assert_eq!(
+            count_insert_rows(b"INSERT INTO t (`values`, values_x) VALUES (1, 2)", false),
+            1
+        );
+        assert_eq!(
+            count_insert_rows(b"INSERT INTO t SELECT * FROM s", false),
+            0
+        );
+    }
+
+    #[test]
+    fn count_insert_rows_without_backslash_escapes() {
+        assert_eq!(
+            count_insert_rows(br"INSERT INTO t VALUES ('C:'),('it''s (1)'),('x')", true),
+            3
+        );
+    }
+
+    fn connection() -> ConnectionInfo {
+        ConnectionInfo {
+            host: "localhost".to_string(),
+            port: 3306,
+            username: "root".to_string(),
+            password: String::new(),
+        }
+    }
+
+    fn rewrite(rewriter: &mut StatementRewriter, statement: &str) -> Vec<String> {
+        let kind = classify_statement(statement.as_bytes());
+        rewriter
+            .rewrite(statement.as_bytes().to_vec(), kind)
+            .unwrap()
+            .into_iter()
+            .map(|statement| String::from_utf8(statement).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn rewriter_renames_database() {
+        let options = RestoreOptions::into_database("shop_copy");
+        let connection = connection();
+        let mut rewriter = StatementRewriter::new(&options, &connection, BackupMode::Full);
+
+        assert_eq!(
+            rewrite(
+                &mut rewriter,
+                "CREATE DATABASE /*!32312 IF NOT EXISTS*/ `shop` /*!40100 DEFAULT CHARACTER SET utf8mb4 */"
+            ),
+            ["CREATE DATABASE /*!32312 IF NOT EXISTS*/ `shop_copy` /*!40100 DEFAULT CHARACTER SET utf8mb4 */"]
+        );
+        assert_eq!(rewrite(&mut rewriter, "USE `shop`"), ["USE `shop_copy`"]);
+        assert_eq!(
+            rewrite(&mut rewriter, "DROP DATABASE IF EXISTS shop"),
+            ["DROP DATABASE IF EXISTS `shop_copy`"]
+        );
+    }
+
+    #[test]
+    fn rewriter_keeps_database_when_restoring_some_tables() {
+        let options = RestoreOptions {
+            tables: vec!["orders".to_string()],
+            ..Default::default()
+        };
+        let connection = connection();
+        let mut rewriter = StatementRewriter::new(&options, &connection, BackupMode::Full);
+
+        assert!(rewrite(&mut rewriter, "DROP DATABASE IF EXISTS `shop`").is_empty());
+        assert_eq!(rewrite(&mut rewriter, "USE `shop`"), ["USE `shop`"]);
+    }
+
+    #[test]
+    fn rewriter_filters_tables() {
+        let options = RestoreOptions {
+            tables: vec!["orders".to_string(), "ghost".to_string()],
+            ..Default::default()
+        };
+        let connection = connection();
+        let mut rewriter = StatementRewriter::new(&options, &connection, BackupMode::Full);
+
+        assert!(rewrite(&mut rewriter, "INSERT INTO `users` VALUES (1)").is_empty());
+        assert_eq!(
+            rewrite(&mut rewriter, "INSERT INTO `orders` VALUES (1)"),
+            ["INSERT INTO `orders` VALUES (1)"]
+        );
+        // 只恢复部分表时跳过视图、存储过程等对象
+        assert!(rewrite(&mut rewriter, "CREATE VIEW `v` AS SELECT 1").is_empty());
+        assert!(rewrite(
+            &mut rewriter,
+            "CREATE TRIGGER `t1` BEFORE INSERT ON `users` FOR EACH ROW SET NEW.a = 1"
+        )
+        .is_empty());
+        assert_eq!(
+            rewrite(
+                &mut rewriter,
+                "CREATE TRIGGER `t2` BEFORE INSERT ON `orders` FOR EACH ROW SET NEW.a = 1"
+            ),
+            [
+                "DROP TRIGGER IF EXISTS `t2`",
+                "CREATE TRIGGER `t2` BEFORE INSERT ON `orders` FOR EACH ROW SET NEW.a = 1"
+            ]
+        );
+        assert!(rewrite(&mut rewriter, "DROP TRIGGER IF EXISTS `t2`").is_empty());
+        assert_eq!(rewriter.missing_tables(), ["ghost"]);
+    }
+
+    #[test]
+    fn rewriter_truncates_existing_tables() {
+        let options = RestoreOptions {
+            conflict: ConflictStrategy::TruncateLoad,
+            ..Default::default()
+        };
+        let connection = connection();
+        let mut rewriter = StatementRewriter::new(&options, &connection, BackupMode::Full);
+
+        assert!(rewrite(&mut rewriter, "DROP TABLE IF EXISTS `orders`").is_empty());
+        assert_eq!(
+            rewrite(&mut rewriter, "CREATE TABLE `orders` (id int)"),
+            [
+                "CREATE TABLE IF NOT EXISTS `orders` (id int)",
+                "TRUNCATE TABLE `orders`"
+            ]
+        );
+        assert_eq!(
+            rewrite(&mut rewriter, "CREATE TABLE IF NOT EXISTS `users` (id int)"),
+            [
+                "CREATE TABLE IF NOT EXISTS `users` (id int)",
+                "TRUNCATE TABLE `users`"
+            ]
+        );
+        assert_eq!(
+            rewrite(&mut rewriter, "INSERT INTO `orders` VALUES (1)"),
+            ["INSERT INTO `orders` VALUES (1)"]
+        );
+    }
+
+    #[test]
+    fn rewriter_truncates_before_data_only_import() {
+        let options = RestoreOptions {
+            conflict: ConflictStrategy::TruncateLoad,
+            ..Default::default()
+        };
+        let connection = connection();
+        let mut rewriter = StatementRewriter::new(&options, &connection, BackupMode::DataOnly);
+
+        assert_eq!(
+            rewrite(&mut rewriter, "LOCK TABLES `orders` WRITE"),
+            ["TRUNCATE TABLE `orders`", "LOCK TABLES `orders` WRITE"]
+        );
+        assert_eq!(
+            rewrite(&mut rewriter, "INSERT INTO `orders` VALUES (1)"),
+            ["INSERT INTO `orders` VALUES (1)"]
+        );
+    }
+
+    #[test]
+    fn rewriter_skips_existing_tables() {
+        let options = RestoreOptions {
+            conflict: ConflictStrategy::SkipExisting,
+            ..Default::default()
+        };
+        let connection = connection();
+        let mut rewriter = StatementRewriter::new(&options, &connection, BackupMode::Full);
+        rewriter.existing_tables.insert("orders".to_string());
+
+        assert!(rewrite(&mut rewriter, "DROP DATABASE `shop`").is_empty());
+        assert!(rewrite(&mut rewriter, "DROP TABLE IF EXISTS `orders`").is_empty());
+        assert!(rewrite(&mut rewriter, "INSERT INTO `orders` VALUES (1)").is_empty());
+        assert!(rewrite(
+            &mut rewriter,
+            "CREATE TRIGGER `t` BEFORE INSERT ON `orders` FOR EACH ROW SET NEW.a = 1"
+        )
+        .is_empty());
+        assert_eq!(
+            rewrite(&mut rewriter, "CREATE TABLE `users` (id int)"),
+            ["CREATE TABLE `users` (id int)"]
+        );
+    }
+}
@@ -0,0 +1,975 @@
+// 定时备份：后端按cron表达式触发备份，不依赖前端页面是否打开。
+// 支持时区和夏令时，休眠或关机错过的备份可以补做一次，同一定时计划同时只运行一次备份。
+// 除了通过set_backup_schedule设置的定时备份，每个启用了定时的备份作业也是一个定时计划
+use crate::crypto::{DecryptionKey, ENCRYPTED_SUFFIX};
+use crate::jobs::JobRegistry;
+use crate::profiles::ProfileStore;
+use crate::secrets::{self, SecretFile};
+use crate::verify;
+use crate::BackupOptions;
+use chrono::{DateTime, LocalResult, TimeZone, Utc};
+use cron::Schedule;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+// 定时备份设置在应用数据目录中的文件名
+const SCHEDULE_FILE: &str = "schedule.json";
+
+// 调度线程最长的休眠时间，休眠唤醒或修改系统时间后最多这么久就能发现
+const MAX_SLEEP: Duration = Duration::from_secs(30);
+
+// 超过计划时间这么久仍未触发的备份视为错过，不补做时直接跳过
+const MISSED_GRACE_SECONDS: i64 = 300;
+
+// 定时备份的触发时间设置
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ScheduleTiming {
+    pub enabled: bool,
+    // cron表达式：5段为标准格式（分 时 日 月 周，周日为0或7），6或7段为带秒（和年）的格式
+    pub cron: String,
+    // IANA时区名（如Asia/Shanghai），为空时使用系统时区
+    pub timezone: String,
+    // 每次触发随机延后的最大秒数，0表示不延后
+    pub jitter_seconds: u64,
+    // 休眠或关机错过的备份在恢复后补做一次
+    pub catch_up: bool,
+}
+
+// 一个数据库的备份参数
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct BackupTarget {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    pub database: String,
+    pub backup_dir: String,
+    pub engine: Option<String>,
+    pub options: BackupOptions,
+    // 备份成功后清理超过保留天数的备份，0或负数表示不清理
+    pub keep_days: i32,
+}
+
+// 定时备份设置
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ScheduleConfig {
+    #[serde(flatten)]
+    pub timing: ScheduleTiming,
+    #[serde(flatten)]
+    pub target: BackupTarget,
+}
+
+// 保存到文件的设置和运行记录
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct ScheduleFile {
+    // 数据库密码和加密口令不保存在config中，见password_ref和passphrase_ref
+    config: ScheduleConfig,
+    // 数据库密码在密钥文件中的引用
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password_ref: Option<String>,
+    // 加密口令在密钥文件中的引用
+    #[serde(skip_serializing_if = "Option::is_none")]
+    passphrase_ref: Option<String>,
+    // 最近一次触发对应的计划时间，RFC 3339格式
+    last_run: Option<String>,
+    // 各备份作业最近一次触发对应的计划时间
+    saved_job_last_run: BTreeMap<u64, String>,
+}
+
+// 返回给前端的定时备份状态
+#[derive(Serialize, Clone)]
+pub struct ScheduleStatus {
+    pub config: ScheduleConfig,
+    // 含随机延后的下次备份时间
+    pub next_run: Option<String>,
+    pub last_run: Option<String>,
+    // 正在运行的定时备份任务
+    pub running_job: Option<u64>,
+    // 各备份作业的定时备份状态
+    pub saved_jobs: Vec<SavedJobSchedule>,
+}
+
+// 备份作业的定时备份状态
+#[derive(Serialize, Clone)]
+pub struct SavedJobSchedule {
+    pub saved_job: u64,
+    pub next_run: Option<String>,
+    pub last_run: Option<String>,
+    // 正在运行的备份任务，每个数据库一个
+    pub running_jobs: Vec<u64>,
+}
+
+// 定时备份事件的类型
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleEventKind {
+    // 已计算出下次备份时间
+    Scheduled,
+    Started,
+    // 上一次定时备份还没有结束，本次不运行
+    Skipped,
+    // 错过了计划时间且没有启用补做
+    Missed,
+    Succeeded,
+    Failed,
+}
+
+// 通过backup-schedule事件发送给前端
+#[derive(Serialize, Clone)]
+pub struct ScheduleEvent {
+    pub kind: ScheduleEventKind,
+    // 备份作业编号，为空表示通过set_backup_schedule设置的定时备份
+    pub saved_job: Option<u64>,
+    pub job_id: Option<u64>,
+    pub next_run: Option<String>,
+    pub message: String,
+}
+
+// 调度器中的一个定时计划
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum ScheduleKey {
+    // 通过set_backup_schedule设置的定时备份
+    Default,
+    SavedJob(u64),
+}
+
+impl ScheduleKey {
+    fn saved_job(self) -> Option<u64> {
+        match self {
+            ScheduleKey::Default => None,
+            ScheduleKey::SavedJob(id) => Some(id),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Slot {
+    timing: ScheduleTiming,
+    // 每次触发时备份的数据库
+    targets: Vec<BackupTarget>,
+    last_run: Option<DateTime<Utc>>,
+    // 下次计划时间和加上随机延后的触发时间
+    next_occurrence: Option<DateTime<Utc>>,
+    next_run: Option<DateTime<Utc>>,
+    running_jobs: Vec<u64>,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    config: ScheduleConfig,
+    // 设置文件路径，应用启动后确定
+    path: Option<PathBuf>,
+    slots: BTreeMap<ScheduleKey, Slot>,
+}
+
+fn format_time(time: Option<DateTime<Utc>>) -> Option<String> {
+    time.map(|time| time.to_rfc3339())
+}
+
+fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+impl SchedulerState {
+    fn status(&self) -> ScheduleStatus {
+        let default = self.slots.get(&ScheduleKey::Default);
+        ScheduleStatus {
+            config: self.config.clone(),
+            next_run: format_time(default.and_then(|slot| slot.next_run)),
+            last_run: format_time(default.and_then(|slot| slot.last_run)),
+            running_job: default.and_then(|slot| slot.running_jobs.first().copied()),
+            saved_jobs: self
+                .slots
+                .iter()
+                .filter_map(|(key, slot)| {
+                    key.saved_job().map(|saved_job| SavedJobSchedule {
+                        saved_job,
+                        next_run: format_time(slot.next_run),
+                        last_run: format_time(slot.last_run),
+                        running_jobs: slot.running_jobs.clone(),
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("创建应用数据目录失败: {}", e))?;
+        }
+        // 密码和口令写入密钥文件，设置文件中只保留引用
+        let mut config = self.config.clone();
+        let mut secrets = SecretFile::new(secrets::secrets_path(path));
+        let password_ref = secrets.stash(
+            "target/password".to_string(),
+            std::mem::take(&mut config.target.password),
+        );
+        let passphrase_ref = secrets.stash(
+            "target/passphrase".to_string(),
+            config
+                .target
+                .options
+                .encryption
+                .passphrase
+                .take()
+                .unwrap_or_default(),
+        );
+        secrets.save()?;
+
+        let file = ScheduleFile {
+            config,
+            password_ref,
+            passphrase_ref,
+            last_run: format_time(
+                self.slots
+                    .get(&ScheduleKey::Default)
+                    .and_then(|slot| slot.last_run),
+            ),
+            saved_job_last_run: self
+                .slots
+                .iter()
+                .filter_map(|(key, slot)| Some((key.saved_job()?, format_time(slot.last_run)?)))
+                .collect(),
+        };
+        let json =
+            serde_json::to_vec_pretty(&file).map_err(|e| format!("保存定时备份设置失败: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("保存定时备份设置失败: {}", e))
+    }
+
+    // 替换定时计划的时间和备份参数。时间设置变化时从现在开始重新计算，之前错过的备份不再补做
+    fn update_slot(
+        &mut self,
+        key: ScheduleKey,
+        timing: ScheduleTiming,
+        targets: Vec<BackupTarget>,
+    ) {
+        let slot = self.slots.entry(key).or_default();
+        if slot.last_run.is_none() || slot.timing != timing {
+            slot.last_run = Some(Utc::now());
+            slot.next_occurrence = None;
+            slot.next_run = None;
+        }
+        slot.timing = timing;
+        slot.targets = targets;
+    }
+}
+
+// 定时备份调度器，作为Tauri状态管理
+#[derive(Default)]
+pub struct Scheduler {
+    state: Mutex<SchedulerState>,
+    // 设置变化或定时备份结束时唤醒调度线程
+    wake: Condvar,
+}
+
+impl Scheduler {
+    fn lock(&self) -> Result<MutexGuard<'_, SchedulerState>, String> {
+        self.state.lock().map_err(|e| e.to_string())
+    }
+
+    pub fn status(&self) -> Result<ScheduleStatus, String> {
+        Ok(self.lock()?.status())
+    }
+
+    // 更新设置并重新计算下次备份时间，之前错过的备份不再补做
+    pub fn configure(&self, config: ScheduleConfig) -> Result<ScheduleStatus, String> {
+        validate_timing(&config.timing)?;
+        if config.timing.enabled && config.target.backup_dir.is_empty() {
+            return Err("未设置备份路径".to_string());
+        }
+
+        let mut state = self.lock()?;
+        state.config = config.clone();
+        // 每次保存都视为新的设置，和之前的运行记录无关
+        if let Some(slot) = state.slots.get_mut(&ScheduleKey::Default) {
+            slot.last_run = None;
+        }
+        state.update_slot(ScheduleKey::Default, config.timing, vec![config.target]);
+        state.save()?;
+        let status = state.status();
+        drop(state);
+
+        self.wake.notify_all();
+        Ok(status)
+    }
+
+    // 用备份作业的定时设置替换调度器中所有备份作业的定时计划
+    pub fn sync_saved_jobs(
+        &self,
+        saved_jobs: Vec<(u64, ScheduleTiming, Vec<BackupTarget>)>,
+    ) -> Result<(), String> {
+        let mut state = self.lock()?;
+        state.slots.retain(|key, _| match key {
+            ScheduleKey::Default => true,
+            ScheduleKey::SavedJob(id) => saved_jobs.iter().any(|(saved_job, _, _)| saved_job == id),
+        });
+        for (id, timing, targets) in saved_jobs {
+            state.update_slot(ScheduleKey::SavedJob(id), timing, targets);
+        }
+        state.save()?;
+        drop(state);
+
+        self.wake.notify_all();
+        Ok(())
+    }
+}
+
+// 检查启用的定时设置中cron表达式和时区是否有效
+pub fn validate_timing(timing: &ScheduleTiming) -> Result<(), String> {
+    if timing.enabled {
+        CronSchedule::parse(&timing.cron, &timing.timezone)?;
+    }
+    Ok(())
+}
+
+// 解析后的cron表达式和时区
+struct CronSchedule {
+    schedule: Schedule,
+    timezone: ScheduleTimeZone,
+}
+
+enum ScheduleTimeZone {
+    Local,
+    Named(chrono_tz::Tz),
+}
+
+impl CronSchedule {
+    fn parse(expression: &str, timezone: &str) -> Result<Self, String> {
+        let schedule = Schedule::from_str(&normalize_cron(expression))
+            .map_err(|e| format!("无效的cron表达式 {}: {}", expression, e))?;
+        let timezone = match timezone.trim() {
+            "" | "local" => ScheduleTimeZone::Local,
+            name => ScheduleTimeZone::Named(
+                name.parse::<chrono_tz::Tz>()
+                    .map_err(|_| format!("无效的时区: {}", name))?,
+            ),
+        };
+        Ok(CronSchedule { schedule, timezone })
+    }
+
+    // 严格晚于after的下一次计划时间
+    fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match &self.timezone {
+            ScheduleTimeZone::Local => next_in_timezone(&self.schedule, &chrono::Local, after),
+            ScheduleTimeZone::Named(tz) => next_in_timezone(&self.schedule, tz, after),
+        }
+    }
+
+    // 不晚于now的最后一次计划时间，用于把休眠期间错过的多次备份合并为一次
+    fn latest_until(&self, from: DateTime<Utc>, now: DateTime<Utc>) -> DateTime<Utc> {
+        let mut latest = from;
+        while let Some(next) = self.next_after(latest) {
+            if next > now {
+                break;
+            }
+            latest = next;
+        }
+        latest
+    }
+}
+
+// 按当地时间的钟面匹配cron表达式，再换算成实际时间。
+// 夏令时结束时重复的时段只运行一次，夏令时开始时跳过的时间顺延到之后第一个存在的时间
+fn next_in_timezone<Tz: TimeZone>(
+    schedule: &Schedule,
+    timezone: &Tz,
+    after: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let wall_clock = Utc.from_utc_datetime(&after.with_timezone(timezone).naive_local());
+    for candidate in schedule.after(&wall_clock).take(1000) {
+        let local = candidate.naive_utc();
+        let resolved = match timezone.from_local_datetime(&local) {
+            LocalResult::Single(time) => time,
+            LocalResult::Ambiguous(earliest, _) => earliest,
+            LocalResult::None => {
+                match (1..=180)
+                    .map(|minutes| local + chrono::Duration::minutes(minutes))
+                    .find_map(|shifted| timezone.from_local_datetime(&shifted).earliest())
+                {
+                    Some(time) => time,
+                    None => continue,
+                }
+            }
+        };
+
+        let resolved = resolved.with_timezone(&Utc);
+        if resolved > after {
+            return Some(resolved);
+        }
+    }
+    None
+}
+
+// 把标准的5段cron表达式转换为cron库使用的格式：补上秒，星期从周日=0改为周日=1
+fn normalize_cron(expression: &str) -> String {
+    let fields: Vec<&str> = expression.split_whitespace().collect();
+    if fields.len() != 5 {
+        return fields.join(" ");
+    }
+
+    let day_of_week = fields[4]
+        .split(',')
+        .map(|part| {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, Some(step)),
+                None => (part, None),
+            };
+            let suffix = step.map(|step| format!("/{}", step)).unwrap_or_default();
+            match range.split_once('-') {
+                Some((start, end)) => match (start.parse::<u32>(), end.parse::<u32>()) {
+                    // 0-7包含整周
+                    (Ok(0), Ok(7)) => format!("1-7{}", suffix),
+                    // 以7（周日）结尾的范围改为到周六的范围，步长落在周日时再加上周日
+                    (Ok(start), Ok(7)) if (1..7).contains(&start) => {
+                        let step = step.and_then(|step| step.parse::<u32>().ok()).unwrap_or(1);
+                        if step > 0 && (7 - start) % step == 0 {
+                            format!("{}-7{},1", start + 1, suffix)
+                        } else {
+                            format!("{}-7{}", start + 1, suffix)
+                        }
+                    }
+                    (Ok(start), Ok(end)) => {
+                        format!("{}-{}{}", start % 7 + 1, end % 7 + 1, suffix)
+                    }
+                    _ => part.to_string(),
+                },
+                None => match range.parse::<u32>() {
+                    Ok(day) => format!("{}{}", day % 7 + 1, suffix),
+                    Err(_) => part.to_string(),
+                },
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+
+    format!(
+        "0 {} {} {} {} {}",
+        fields[0], fields[1], fields[2], fields[3], day_of_week
+    )
+}
+
+// 读取保存的设置和备份作业的定时设置，启动调度线程
+pub fn start(app: AppHandle) {
+    let path = app
+        .path()
+        .app_data_dir()
+        .map(|dir| dir.join(SCHEDULE_FILE))
+        .ok();
+    let saved_jobs = match app.state::<ProfileStore>().schedules() {
+        Ok(saved_jobs) => saved_jobs,
+        Err(e) => {
+            eprintln!("读取备份作业失败: {}", e);
+            Vec::new()
+        }
+    };
+
+    {
+        let scheduler = app.state::<Scheduler>();
+        let mut state = match scheduler.lock() {
+            Ok(state) => state,
+            Err(e) => {
+                eprintln!("启动定时备份失败: {}", e);
+                return;
+            }
+        };
+        let file = path
+            .as_deref()
+            .and_then(load_schedule_file)
+            .unwrap_or_default();
+        let migrate_secrets = file.has_plaintext_secrets();
+
+        state.config = file.config.clone();
+        state.slots.insert(
+            ScheduleKey::Default,
+            Slot {
+                timing: file.config.timing,
+                targets: vec![file.config.target],
+                last_run: file.last_run.as_deref().and_then(parse_time),
+                ..Slot::default()
+            },
+        );
+        for (id, timing, targets) in saved_jobs {
+            state.slots.insert(
+                ScheduleKey::SavedJob(id),
+                Slot {
+                    timing,
+                    targets,
+                    // 没有运行记录的备份作业从现在开始计算
+                    last_run: file
+                        .saved_job_last_run
+                        .get(&id)
+                        .and_then(|time| parse_time(time))
+                        .or_else(|| Some(Utc::now())),
+                    ..Slot::default()
+                },
+            );
+        }
+        state.path = path;
+
+        // 旧版本以明文保存的密码和口令移到密钥文件中
+        if migrate_secrets {
+            if let Err(e) = state.save() {
+                eprintln!("{}", e);
+            }
+        }
+    }
+
+    thread::spawn(move || run_scheduler(app));
+}
+
+// 读取设置文件，并从密钥文件中取回密码和口令
+fn load_schedule_file(path: &Path) -> Option<ScheduleFile> {
+    let content = fs::read(path).ok()?;
+    let mut file: ScheduleFile = match serde_json::from_slice(&content) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("读取定时备份设置失败: {}", e);
+            return None;
+        }
+    };
+
+    if file.password_ref.is_some() || file.passphrase_ref.is_some() {
+        let secrets = match SecretFile::load(secrets::secrets_path(path)) {
+            Ok(secrets) => secrets,
+            Err(e) => {
+                eprintln!("{}", e);
+                SecretFile::new(secrets::secrets_path(path))
+            }
+        };
+        let target = &mut file.config.target;
+        if let Some(reference) = &file.password_ref {
+            target.password = secrets.get(reference).unwrap_or_default();
+        }
+        if let Some(reference) = &file.passphrase_ref {
+            target.options.encryption.passphrase = secrets.get(reference);
+        }
+    }
+    Some(file)
+}
+
+impl ScheduleFile {
+    // 设置文件中有旧版本保存的明文密码或口令
+    fn has_plaintext_secrets(&self) -> bool {
+        let target = &self.config.target;
+        (self.password_ref.is_none() && !target.password.is_empty())
+            || (self.passphrase_ref.is_none()
+                && target
+                    .options
+                    .encryption
+                    .passphrase
+                    .as_deref()
+                    .is_some_and(|passphrase| !passphrase.is_empty()))
+    }
+}
+
+fn emit_event(
+    app: &AppHandle,
+    kind: ScheduleEventKind,
+    key: ScheduleKey,
+    job_id: Option<u64>,
+    next_run: Option<DateTime<Utc>>,
+    message: String,
+) {
+    let event = ScheduleEvent {
+        kind,
+        saved_job: key.saved_job(),
+        job_id,
+        next_run: format_time(next_run),
+        message,
+    };
+    if let Err(e) = app.emit("backup-schedule", event) {
+        eprintln!("发送定时备份事件失败: {}", e);
+    }
+}
+
+// 调度线程：计算各定时计划的下次备份时间，到点后提交备份任务
+fn run_scheduler(app: AppHandle) {
+    let scheduler = app.state::<Scheduler>();
+    let mut state = match scheduler.lock() {
+        Ok(state) => state,
+        Err(_) => return,
+    };
+
+    loop {
+        let now = Utc::now();
+        let mut sleep = MAX_SLEEP;
+        let mut changed = false;
+        for (key, slot) in state.slots.iter_mut() {
+            if let Some(until) = tick(&app, *key, slot, now, &mut changed) {
+                sleep = sleep.min(until);
+            }
+        }
+
+        if changed {
+            if let Err(e) = state.save() {
+                eprintln!("{}", e);
+            }
+            // 立即为触发过的计划计算下次备份时间
+            continue;
+        }
+
+        state = match scheduler.wake.wait_timeout(state, sleep) {
+            Ok((state, _)) => state,
+            Err(_) => return,
+        };
+    }
+}
+
+// 检查一个定时计划，到点时提交备份任务。返回距离下次触发的时间
+fn tick(
+    app: &AppHandle,
+    key: ScheduleKey,
+    slot: &mut Slot,
+    now: DateTime<Utc>,
+    changed: &mut bool,
+) -> Option<Duration> {
+    if !slot.timing.enabled {
+        return None;
+    }
+    let schedule = CronSchedule::parse(&slot.timing.cron, &slot.timing.timezone).ok()?;
+
+    // 上一次定时备份结束后才允许开始下一次
+    let registry = app.state::<JobRegistry>();
+    slot.running_jobs.retain(|job_id| {
+        registry
+            .get(*job_id)
+            .map(|job| !job.status.is_finished())
+            .unwrap_or(false)
+    });
+
+    if slot.next_run.is_none() {
+        let base = slot.last_run.unwrap_or(now);
+        slot.next_occurrence = schedule.next_after(base);
+        slot.next_run = slot.next_occurrence.map(|occurrence| {
+            let jitter = slot.timing.jitter_seconds;
+            let delay = if jitter > 0 {
+                rand::thread_rng().gen_range(0..=jitter)
+            } else {
+                0
+            };
+            occurrence + chrono::Duration::seconds(delay as i64)
+        });
+        if let Some(next_run) = slot.next_run {
+            emit_event(
+                app,
+                ScheduleEventKind::Scheduled,
+                key,
+                None,
+                Some(next_run),
+                format!(
+                    "下次自动备份时间: {}",
+                    next_run.with_timezone(&chrono::Local)
+                ),
+            );
+        }
+    }
+
+    let (occurrence, next_run) = (slot.next_occurrence?, slot.next_run?);
+    if now < next_run {
+        return (next_run - now).to_std().ok();
+    }
+
+    // 休眠期间错过的多次备份合并为一次
+    slot.last_run = Some(schedule.latest_until(occurrence, now));
+    slot.next_occurrence = None;
+    slot.next_run = None;
+    *changed = true;
+
+    let late = (now - next_run).num_seconds() > MISSED_GRACE_SECONDS;
+    if late && !slot.timing.catch_up {
+        emit_event(
+            app,
+            ScheduleEventKind::Missed,
+            key,
+            None,
+            None,
+            format!(
+                "错过了 {} 的自动备份",
+                occurrence.with_timezone(&chrono::Local)
+            ),
+        );
+    } else if let Some(job_id) = slot.running_jobs.first() {
+        emit_event(
+            app,
+            ScheduleEventKind::Skipped,
+            key,
+            Some(*job_id),
+            None,
+            "上一次自动备份还没有结束，跳过本次备份".to_string(),
+        );
+    } else {
+        for target in &slot.targets {
+            match start_scheduled_backup(app, key, target) {
+                Ok(job_id) => slot.running_jobs.push(job_id),
+                Err(e) => emit_event(
+                    app,
+                    ScheduleEventKind::Failed,
+                    key,
+                    None,
+                    None,
+                    format!("自动备份 {} 失败: {}", target.database, e),
+                ),
+            }
+        }
+    }
+    Some(Duration::ZERO)
+}
+
+// 立即运行备份作业，返回各数据库的备份任务编号。运行期间到点的定时备份会被跳过
+pub fn run_saved_job(
+    app: &AppHandle,
+    saved_job: u64,
+    targets: Vec<BackupTarget>,
+) -> Result<Vec<u64>, String> {
+    let key = ScheduleKey::SavedJob(saved_job);
+    let mut job_ids = Vec::new();
+    let mut errors = Vec::new();
+    for target in &targets {
+        match start_scheduled_backup(app, key, target) {
+            Ok(job_id) => job_ids.push(job_id),
+            Err(e) => errors.push(format!("{}: {}", target.database, e)),
+        }
+    }
+    if job_ids.is_empty() {
+        return Err(format!("运行备份作业失败: {}", errors.join("; ")));
+    }
+
+    let scheduler = app.state::<Scheduler>();
+    if let Some(slot) = scheduler.lock()?.slots.get_mut(&key) {
+        slot.running_jobs.extend(&job_ids);
+    }
+    Ok(job_ids)
+}
+
+// 提交一个数据库的备份任务，并在后台等待任务结束后校验备份、清理过期备份
+fn start_scheduled_backup(
+    app: &AppHandle,
+    key: ScheduleKey,
+    target: &BackupTarget,
+) -> Result<u64, String> {
+    let window = app
+        .get_webview_window("main")
+        .map(|window| window.as_ref().window())
+        .ok_or_else(|| "找不到主窗口".to_string())?;
+
+    let timestamp = chrono::Local::now().format("%Y%m%d%H%M");
+    let suffix = if target.options.encryption.is_enabled() {
+        ENCRYPTED_SUFFIX
+    } else {
+        ""
+    };
+    let output_path = Path::new(&target.backup_dir).join(format!(
+        "BACKUP_{}{}{}",
+        timestamp,
+        target.options.format.extension(),
+        suffix
+    ));
+    let output_path = output_path.to_string_lossy().into_owned();
+
+    let job_id = crate::spawn_backup_job(
+        window,
+        &target.host,
+        target.port,
+        &target.username,
+        &target.password,
+        &target.database,
+        &output_path,
+        target.engine.as_deref(),
+        Some(target.options.clone()),
+    )?;
+    emit_event(
+        app,
+        ScheduleEventKind::Started,
+        key,
+        Some(job_id),
+        None,
+        format!("自动备份已开始: {}", target.database),
+    );
+
+    let app = app.clone();
+    let target = target.clone();
+    thread::spawn(move || {
+        let job = tauri::async_runtime::block_on(app.state::<JobRegistry>().wait(job_id));
+        let result = job
+            .and_then(|job| job.into_result())
+            .and_then(|path| finish_scheduled_backup(&target, &path).map(|_| path));
+
+        match result {
+            Ok(path) => emit_event(
+                &app,
+                ScheduleEventKind::Succeeded,
+                key,
+                Some(job_id),
+                None,
+                path,
+            ),
+            Err(e) => emit_event(&app, ScheduleEventKind::Failed, key, Some(job_id), None, e),
+        }
+
+        // 唤醒调度线程，使其尽快发现定时备份已结束
+        app.state::<Scheduler>().wake.notify_all();
+    });
+
+    Ok(job_id)
+}
+
+// 校验定时备份，校验失败时不清理旧备份。使用公钥加密的备份没有私钥无法校验
+fn finish_scheduled_backup(target: &BackupTarget, path: &str) -> Result<(), String> {
+    let encryption = &target.options.encryption;
+    if encryption.recipients.is_empty() {
+        let key = encryption
+            .passphrase
+            .clone()
+            .map(|passphrase| DecryptionKey {
+                passphrase: Some(passphrase),
+                identity: None,
+            });
+        let report = verify::verify_archive(Path::new(path), key.as_ref())?;
+        if !report.ok {
+            return Err(format!("备份校验失败: {}", report.errors.join("; ")));
+        }
+    }
+
+    if target.keep_days > 0 {
+        match crate::cleanup_old_backups_impl(&target.backup_dir, target.keep_days) {
+            Ok(count) if count > 0 => println!("已清理 {} 个过期备份文件", count),
+            Ok(_) => {}
+            Err(e) => eprintln!("清理旧备份文件失败: {}", e),
+        }
+    }
+    Ok(())
+}
+
+// 计算接下来几次的备份时间（不含随机延后），用于在设置界面预览cron表达式
+pub fn preview(expression: &str, timezone: &str, count: usize) -> Result<Vec<String>, String> {
+    let schedule = CronSchedule::parse(expression, timezone)?;
+    let mut runs = Vec::with_capacity(count);
+    let mut after = Utc::now();
+    while runs.len() < count {
+        match schedule.next_after(after) {
+            Some(next) => {
+                runs.push(next.with_timezone(&chrono::Local).to_rfc3339());
+                after = next;
+            }
+            None => break,
+        }
+    }
+    Ok(runs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, Weekday};
+    use chrono_tz::America::New_York;
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    fn schedule(expression: &str) -> Schedule {
+        Schedule::from_str(&normalize_cron(expression)).unwrap()
+    }
+
+    #[test]
+    fn normalize_cron_adds_seconds_and_maps_sunday_zero() {
+        assert_eq!(normalize_cron("30 2 * * 0"), "0 30 2 * * 1");
+        assert_eq!(normalize_cron("30 2 * * 7"), "0 30 2 * * 1");
+        assert_eq!(normalize_cron("0 9 * * 1-5"), "0 0 9 * * 2-6");
+        assert_eq!(normalize_cron("0 9 * * 0,6"), "0 0 9 * * 1,7");
+        assert_eq!(normalize_cron("0 9 * * *"), "0 0 9 * * *");
+        assert_eq!(normalize_cron("0 9 * * MON-FRI"), "0 0 9 * * MON-FRI");
+    }
+
+    #[test]
+    fn normalize_cron_splits_ranges_ending_on_sunday() {
+        assert_eq!(normalize_cron("0 9 * * 5-7"), "0 0 9 * * 6-7,1");
+        assert_eq!(normalize_cron("0 9 * * 0-7"), "0 0 9 * * 1-7");
+        // 周一起每隔一天：周一、三、五、日
+        assert_eq!(normalize_cron("0 9 * * 1-7/2"), "0 0 9 * * 2-7/2,1");
+        // 周二起每隔一天：周二、四、六，步长落不到周日
+        assert_eq!(normalize_cron("0 9 * * 2-7/2"), "0 0 9 * * 3-7/2");
+    }
+
+    #[test]
+    fn normalize_cron_keeps_six_and_seven_field_expressions() {
+        assert_eq!(normalize_cron("0  30 2 * * 1"), "0 30 2 * * 1");
+        assert_eq!(normalize_cron("0 30 2 * * 1 2030"), "0 30 2 * * 1 2030");
+    }
+
+    #[test]
+    fn sunday_zero_runs_on_sunday() {
+        // 2024-06-01是周六
+        let next =
+            next_in_timezone(&schedule("0 9 * * 0"), &Utc, utc("2024-06-01T12:00:00Z")).unwrap();
+        assert_eq!(next, utc("2024-06-02T09:00:00Z"));
+        assert_eq!(next.weekday(), Weekday::Sun);
+
+        let next = next_in_timezone(
+            &schedule("0 9 * * 1-7/2"),
+            &Utc,
+            utc("2024-06-01T12:00:00Z"),
+        )
+        .unwrap();
+        assert_eq!(next.weekday(), Weekday::Sun);
+    }
+
+    #[test]
+    fn next_in_timezone_uses_wall_clock_time() {
+        // 2024-06-01 02:30 EDT = 06:30 UTC
+        let next = next_in_timezone(
+            &schedule("30 2 * * *"),
+            &New_York,
+            utc("2024-06-01T00:00:00Z"),
+        );
+        assert_eq!(next, Some(utc("2024-06-01T06:30:00Z")));
+    }
+
+    #[test]
+    fn next_in_timezone_moves_skipped_time_to_end_of_dst_gap() {
+        // 2024-03-10 02:00 EST直接跳到03:00 EDT，02:30不存在，顺延到03:00 EDT = 07:00 UTC
+        let next = next_in_timezone(
+            &schedule("30 2 * * *"),
+            &New_York,
+            utc("2024-03-10T05:00:00Z"),
+        );
+        assert_eq!(next, Some(utc("2024-03-10T07:00:00Z")));
+
+        // 第二天恢复正常
+        let next = next_in_timezone(&schedule("30 2 * * *"), &New_York, next.unwrap());
+        assert_eq!(next, Some(utc("2024-03-11T06:30:00Z")));
+    }
+
+    #[test]
+    fn next_in_timezone_runs_repeated_time_once_in_dst_overlap() {
+        // 2024-11-03 01:30出现两次：05:30 UTC（EDT）和06:30 UTC（EST），只在第一次运行
+        let schedule = schedule("30 1 * * *");
+        let first = next_in_timezone(&schedule, &New_York, utc("2024-11-03T04:00:00Z"));
+        assert_eq!(first, Some(utc("2024-11-03T05:30:00Z")));
+
+        let second = next_in_timezone(&schedule, &New_York, first.unwrap());
+        assert_eq!(second, Some(utc("2024-11-04T06:30:00Z")));
+
+        // 在重复时段的第二次01:00之后计算，也不会再运行一次
+        let after_fall_back = next_in_timezone(&schedule, &New_York, utc("2024-11-03T06:00:00Z"));
+        assert_eq!(after_fall_back, Some(utc("2024-11-04T06:30:00Z")));
+    }
+}
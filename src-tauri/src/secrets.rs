@@ -0,0 +1,128 @@
+// 密码和加密口令不写入设置文件：集中保存在设置文件旁边只有当前用户可读写的密钥文件中，
+// 设置文件中只记录引用
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 密钥文件，键为设置文件中记录的引用
+pub struct SecretFile {
+    path: PathBuf,
+    secrets: BTreeMap<String, String>,
+}
+
+// 设置文件对应的密钥文件路径，如profiles.json对应profiles.secrets.json
+pub fn secrets_path(settings_path: &Path) -> PathBuf {
+    let stem = settings_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    settings_path.with_file_name(format!("{}.secrets.json", stem))
+}
+
+impl SecretFile {
+    // 空的密钥文件，保存时覆盖原有内容
+    pub fn new(path: PathBuf) -> Self {
+        SecretFile {
+            path,
+            secrets: BTreeMap::new(),
+        }
+    }
+
+    // 读取密钥文件，不存在时为空
+    pub fn load(path: PathBuf) -> Result<Self, String> {
+        let mut file = SecretFile::new(path);
+        if file.path.exists() {
+            let content = fs::read(&file.path).map_err(|e| format!("读取密钥文件失败: {}", e))?;
+            file.secrets =
+                serde_json::from_slice(&content).map_err(|e| format!("解析密钥文件失败: {}", e))?;
+        }
+        Ok(file)
+    }
+
+    // 按引用取出保存的内容
+    pub fn get(&self, reference: &str) -> Option<String> {
+        self.secrets.get(reference).cloned()
+    }
+
+    // 保存内容并返回引用，内容为空时不保存
+    pub fn stash(&mut self, reference: String, secret: String) -> Option<String> {
+        if secret.is_empty() {
+            return None;
+        }
+        self.secrets.insert(reference.clone(), secret);
+        Some(reference)
+    }
+
+    // 先写临时文件再重命名，Unix上权限为0600；Windows上应用数据目录本身只有当前用户可访问
+    pub fn save(&self) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(&self.secrets)
+            .map_err(|e| format!("生成密钥文件失败: {}", e))?;
+        let mut temp_path = self.path.as_os_str().to_os_string();
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options
+            .open(&temp_path)
+            .map_err(|e| format!("写入密钥文件失败: {}", e))?;
+        // 临时文件已存在时open不会修改权限
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(fs::Permissions::from_mode(0o600))
+                .map_err(|e| format!("设置密钥文件权限失败: {}", e))?;
+        }
+        file.write_all(&json)
+            .map_err(|e| format!("写入密钥文件失败: {}", e))?;
+        drop(file);
+
+        fs::rename(&temp_path, &self.path).map_err(|e| format!("写入密钥文件失败: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_path_sits_next_to_settings_file() {
+        assert_eq!(
+            secrets_path(Path::new("/data/schedule.json")),
+            PathBuf::from("/data/schedule.secrets.json")
+        );
+    }
+
+    #[test]
+    fn stash_skips_empty_secrets_and_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schedule.secrets.json");
+
+        let mut secrets = SecretFile::new(path.clone());
+        assert_eq!(
+            secrets.stash("target/password".to_string(), String::new()),
+            None
+        );
+        let reference = secrets
+            .stash("target/password".to_string(), "s3cret".to_string())
+            .unwrap();
+        secrets.save().unwrap();
+
+        let loaded = SecretFile::load(path.clone()).unwrap();
+        assert_eq!(loaded.get(&reference).as_deref(), Some("s3cret"));
+        assert_eq!(loaded.get("missing"), None);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
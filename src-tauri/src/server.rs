@@ -0,0 +1,550 @@
+// 多数据库和整个服务器的备份：每个数据库写入同一归档中以数据库名命名的目录，或各自写入
+// 单独的归档；可选备份用户、角色和授权，使一次备份就能重建整个服务器
+use crate::archive::ArchiveFormat;
+use crate::crypto::{self, ENCRYPTED_SUFFIX};
+use crate::jobs::JobWindow;
+use crate::manifest::{self, HashingWriter, ServerInfo, MANIFEST_ENTRY};
+use crate::{check_backup_cancelled, send_progress_update, BackupOptions};
+use mysql::prelude::*;
+use mysql::OptsBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use zip::write::ZipWriter;
+use zip::ZipArchive;
+
+// 备份所有数据库时跳过的系统数据库
+const SYSTEM_DATABASES: &[&str] = &["information_schema", "mysql", "performance_schema", "sys"];
+
+// MySQL内部使用的账户，目标服务器上一定存在，不需要备份
+const RESERVED_ACCOUNTS: &[&str] = &["mysql.sys", "mysql.session", "mysql.infoschema"];
+
+// 用户、角色和授权在归档中的条目名，写在所有数据库之后
+pub const USERS_ENTRY: &str = "users.sql";
+
+// 多数据库备份选项，没有选择数据库且不备份所有数据库时按单个数据库备份
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ServerBackupOptions {
+    // 要备份的数据库
+    pub databases: Vec<String>,
+    // 备份除系统数据库外的所有数据库，忽略databases
+    pub all_databases: bool,
+    // 每个数据库写入单独的归档，保存在以备份文件名命名的目录中
+    pub separate_archives: bool,
+    // 备份用户、角色和授权
+    pub include_users: bool,
+}
+
+impl ServerBackupOptions {
+    pub fn is_enabled(&self) -> bool {
+        self.all_databases || self.databases.iter().any(|db| !db.trim().is_empty())
+    }
+
+    // 在任务列表和定时备份通知中代替数据库名显示
+    pub fn label(&self) -> String {
+        if self.all_databases {
+            "所有数据库".to_string()
+        } else {
+            self.databases.join(", ")
+        }
+    }
+}
+
+// 备份多个数据库，返回备份文件路径，分别归档时返回保存归档的目录
+pub fn backup_server(
+    window: &JobWindow,
+    host: &str,
+    port: u16,
+    username: &str,
+    password: &str,
+    output_path: &str,
+    engine: &'static str,
+    options: &BackupOptions,
+    cancel_requested: &Arc<AtomicBool>,
+) -> Result<String, String> {
+    send_progress_update(window, 2, "连接数据库...", None);
+    let opts = OptsBuilder::new()
+        .ip_or_hostname(Some(host))
+        .tcp_port(port)
+        .user(Some(username))
+        .pass(Some(password));
+    let mut conn = mysql::Conn::new(opts).map_err(|e| format!("连接数据库失败: {}", e))?;
+    let databases = resolve_databases(&mut conn, &options.server)?;
+    drop(conn);
+
+    // 每个数据库按单数据库备份，多数据库备份不支持续传
+    let mut database_options = options.clone();
+    database_options.server = ServerBackupOptions::default();
+    database_options.resumable = false;
+
+    let backup = ServerBackup {
+        window,
+        host,
+        port,
+        username,
+        password,
+        engine,
+        databases: &databases,
+        options,
+        database_options,
+        cancel_requested,
+    };
+    let output_path = Path::new(output_path);
+    if options.server.separate_archives {
+        backup.write_separate(output_path)
+    } else {
+        backup.write_combined(output_path)
+    }
+}
+
+// 列出要备份的数据库，检查选择的数据库是否存在
+fn resolve_databases(
+    conn: &mut mysql::Conn,
+    server: &ServerBackupOptions,
+) -> Result<Vec<String>, String> {
+    let existing: Vec<String> = conn
+        .query("SHOW DATABASES")
+        .map_err(|e| format!("获取数据库列表失败: {}", e))?;
+
+    if server.all_databases {
+        let databases: Vec<String> = existing
+            .into_iter()
+            .filter(|db| !SYSTEM_DATABASES.contains(&db.as_str()))
+            .collect();
+        if databases.is_empty() {
+            return Err("服务器上没有可备份的数据库".to_string());
+        }
+        return Ok(databases);
+    }
+
+    let mut databases = Vec::new();
+    for database in server.databases.iter().map(|db| db.trim()) {
+        if database.is_empty() || databases.iter().any(|db| db == database) {
+            continue;
+        }
+        if !existing.iter().any(|db| db == database) {
+            return Err(format!("数据库 {} 不存在", database));
+        }
+        databases.push(database.to_string());
+    }
+    Ok(databases)
+}
+
+struct ServerBackup<'a> {
+    window: &'a JobWindow,
+    host: &'a str,
+    port: u16,
+    username: &'a str,
+    password: &'a str,
+    engine: &'static str,
+    databases: &'a [String],
+    options: &'a BackupOptions,
+    database_options: BackupOptions,
+    cancel_requested: &'a Arc<AtomicBool>,
+}
+
+impl ServerBackup<'_> {
+    // 第index个数据库的进度区间，所有数据库占5%到90%
+    fn scoped_window(&self, index: usize, database: &str) -> JobWindow {
+        let count = self.databases.len();
+        let start = 5 + 85 * index / count;
+        let end = 5 + 85 * (index + 1) / count;
+        self.window.scoped(start as u8, end as u8, database)
+    }
+
+    fn backup_database(
+        &self,
+        index: usize,
+        database: &str,
+        output_path: &Path,
+        options: &BackupOptions,
+    ) -> Result<String, String> {
+        crate::run_backup(
+            &self.scoped_window(index, database),
+            self.host,
+            self.port,
+            self.username,
+            self.password,
+            database,
+            &output_path.to_string_lossy(),
+            Some(self.engine),
+            options,
+            self.cancel_requested,
+        )
+    }
+
+    // 所有数据库写入同一归档：每个数据库先备份到临时ZIP，再把条目复制到以数据库名命名的目录
+    fn write_combined(&self, output_path: &Path) -> Result<String, String> {
+        if self.options.format.is_sql_stream() {
+            return Err(format!(
+                "{}格式只能保存一个数据库，请使用ZIP或tar.zst格式，或选择分别归档",
+                self.options.format.extension()
+            ));
+        }
+
+        let parent = output_path.parent().unwrap_or_else(|| Path::new("."));
+        fs::create_dir_all(parent).map_err(|e| format!("创建输出目录失败: {}", e))?;
+        let started_at = chrono::Local::now().to_rfc3339();
+
+        let (zip_path, _staging_dir) = crate::staging_zip_path(output_path, self.options)?;
+        let zip_file = File::create(&zip_path).map_err(|e| format!("创建ZIP文件失败: {}", e))?;
+        let mut zip = ZipWriter::new(zip_file);
+
+        let result = self
+            .write_combined_entries(&mut zip, parent)
+            .and_then(|server_info| {
+                zip.finish()
+                    .map_err(|e| format!("完成ZIP文件失败: {}", e))?;
+
+                check_backup_cancelled(self.cancel_requested)?;
+                send_progress_update(self.window, 95, "正在生成备份清单...", None);
+                manifest::append_manifest(
+                    &zip_path,
+                    self.engine,
+                    "",
+                    server_info,
+                    started_at,
+                    self.options.encryption.scheme(),
+                )?;
+
+                check_backup_cancelled(self.cancel_requested)?;
+                crate::convert_staging_zip(self.window, &zip_path, output_path, self.options)
+            });
+
+        if let Err(e) = result {
+            drop(zip);
+            crate::remove_incomplete_backup(&zip_path, output_path);
+            return Err(e);
+        }
+
+        send_progress_update(self.window, 100, "备份完成", None);
+        Ok(output_path.to_string_lossy().into_owned())
+    }
+
+    fn write_combined_entries(
+        &self,
+        zip: &mut ZipWriter<File>,
+        parent: &Path,
+    ) -> Result<ServerInfo, String> {
+        // 临时ZIP放在输出目录中，备份结束后整个目录一起删除；需要加密时临时ZIP是明文，放在私有临时目录中
+        let temp_dir = if self.options.encryption.is_enabled() {
+            crypto::private_staging_dir(".mysql_backup_server_")?
+        } else {
+            tempfile::Builder::new()
+                .prefix(".mysql_backup_server_")
+                .tempdir_in(parent)
+                .map_err(|e| format!("创建临时目录失败: {}", e))?
+        };
+
+        // 每个数据库备份成不加密、不转换格式的ZIP，条目按原有的压缩方式复制
+        let mut part_options = self.database_options.clone();
+        part_options.format = ArchiveFormat::Zip;
+        part_options.encryption = crypto::EncryptionOptions::default();
+
+        let mut server_info: Option<ServerInfo> = None;
+        for (index, database) in self.databases.iter().enumerate() {
+            check_backup_cancelled(self.cancel_requested)?;
+            let part_path = temp_dir.path().join(format!("{}.zip", index));
+            self.backup_database(index, database, &part_path, &part_options)?;
+
+            let info = copy_part_into_folder(zip, &part_path, database)?;
+            fs::remove_file(&part_path).map_err(|e| format!("删除临时文件失败: {}", e))?;
+            match &mut server_info {
+                Some(server_info) => {
+                    server_info.tables.extend(info.tables);
+                    server_info.views.extend(info.views);
+                    server_info.checksums.extend(info.checksums);
+                    server_info.databases.extend(info.databases);
+                    server_info.entries.extend(info.entries);
+                    server_info.row_counts.extend(info.row_counts);
+                }
+                None => server_info = Some(info),
+            }
+        }
+
+        let mut server_info = server_info.ok_or_else(|| "没有选择要备份的数据库".to_string())?;
+        // 各数据库的字符集不同，整个服务器的备份不记录数据库字符集
+        server_info.character_set = String::new();
+        server_info.collation = String::new();
+
+        if self.options.server.include_users {
+            check_backup_cancelled(self.cancel_requested)?;
+            send_progress_update(self.window, 91, "正在备份用户和授权...", None);
+            zip.start_file(USERS_ENTRY, self.options.zip_file_options())
+                .map_err(|e| format!("添加备份文件到ZIP失败: {}", e))?;
+            let mut output = HashingWriter::new(&mut *zip);
+            self.write_users(&mut output)?;
+            server_info.entries.push(output.into_entry(USERS_ENTRY));
+        }
+
+        Ok(server_info)
+    }
+
+    // 每个数据库写入单独的归档，保存在以备份文件名（去掉扩展名）命名的目录中
+    fn write_separate(&self, output_path: &Path) -> Result<String, String> {
+        let dir = separate_archive_dir(output_path, self.options.format);
+        if dir.exists() {
+            return Err(format!("备份目录 {} 已存在", dir.display()));
+        }
+        fs::create_dir_all(&dir).map_err(|e| format!("创建输出目录失败: {}", e))?;
+
+        let result = self.write_separate_archives(&dir);
+        if let Err(e) = result {
+            let _ = fs::remove_dir_all(&dir);
+            return Err(e);
+        }
+
+        send_progress_update(self.window, 100, "备份完成", None);
+        Ok(dir.to_string_lossy().into_owned())
+    }
+
+    fn write_separate_archives(&self, dir: &Path) -> Result<(), String> {
+        let suffix = if self.options.encryption.is_enabled() {
+            ENCRYPTED_SUFFIX
+        } else {
+            ""
+        };
+
+        for (index, database) in self.databases.iter().enumerate() {
+            check_backup_cancelled(self.cancel_requested)?;
+            let path = dir.join(format!(
+                "{}{}{}",
+                database,
+                self.options.format.extension(),
+                suffix
+            ));
+            self.backup_database(index, database, &path, &self.database_options)?;
+        }
+
+        if self.options.server.include_users {
+            check_backup_cancelled(self.cancel_requested)?;
+            send_progress_update(self.window, 91, "正在备份用户和授权...", None);
+            let path = dir.join(format!(
+                "users{}{}",
+                self.options.format.extension(),
+                suffix
+            ));
+            self.write_users_archive(&path)?;
+        }
+        Ok(())
+    }
+
+    // 用户和授权单独保存为一个归档，格式和加密方式与数据库的归档相同
+    fn write_users_archive(&self, output_path: &Path) -> Result<(), String> {
+        let started_at = chrono::Local::now().to_rfc3339();
+        let (zip_path, _staging_dir) = crate::staging_zip_path(output_path, self.options)?;
+        let zip_file = File::create(&zip_path).map_err(|e| format!("创建ZIP文件失败: {}", e))?;
+        let mut zip = ZipWriter::new(zip_file);
+
+        let result = zip
+            .start_file(USERS_ENTRY, self.options.zip_file_options())
+            .map_err(|e| format!("添加备份文件到ZIP失败: {}", e))
+            .and_then(|_| {
+                let mut output = HashingWriter::new(&mut zip);
+                let mut server_info = self.write_users(&mut output)?;
+                server_info.entries.push(output.into_entry(USERS_ENTRY));
+                Ok(server_info)
+            })
+            .and_then(|server_info| {
+                zip.finish()
+                    .map_err(|e| format!("完成ZIP文件失败: {}", e))?;
+                manifest::append_manifest(
+                    &zip_path,
+                    self.engine,
+                    "",
+                    server_info,
+                    started_at,
+                    self.options.encryption.scheme(),
+                )?;
+                crate::convert_staging_zip(self.window, &zip_path, output_path, self.options)
+            });
+
+        if let Err(e) = result {
+            drop(zip);
+            crate::remove_incomplete_backup(&zip_path, output_path);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    // 写入重建用户、角色和授权的SQL，返回写入清单所需的服务器信息
+    fn write_users<W: Write>(&self, out: &mut W) -> Result<ServerInfo, String> {
+        let opts = OptsBuilder::new()
+            .ip_or_hostname(Some(self.host))
+            .tcp_port(self.port)
+            .user(Some(self.username))
+            .pass(Some(self.password));
+        let mut conn = mysql::Conn::new(opts).map_err(|e| format!("连接数据库失败: {}", e))?;
+        let server_info = manifest::read_server_info(&mut conn, "mysql")?;
+
+        let (sql, warnings) = users_sql(&mut conn)?;
+        for warning in &warnings {
+            eprintln!("{}", warning);
+            send_progress_update(self.window, 92, warning, None);
+        }
+        out.write_all(sql.as_bytes())
+            .map_err(|e| format!("写入用户和授权失败: {}", e))?;
+
+        Ok(ServerInfo {
+            character_set: String::new(),
+            collation: String::new(),
+            tables: Vec::new(),
+            views: Vec::new(),
+            checksums: BTreeMap::new(),
+            ..server_info
+        })
+    }
+}
+
+// 分别归档时保存归档的目录：备份文件名去掉格式扩展名和加密后缀
+fn separate_archive_dir(output_path: &Path, format: ArchiveFormat) -> PathBuf {
+    let file_name = output_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = crypto::strip_encrypted_suffix(&file_name);
+    let file_name = file_name
+        .strip_suffix(format.extension())
+        .unwrap_or(file_name);
+    output_path.with_file_name(file_name)
+}
+
+// 把单个数据库的ZIP条目复制到归档中以数据库名命名的目录，不重新压缩。
+// 返回该数据库的清单信息，表名和条目名加上数据库名前缀，条目的校验值和行数沿用该数据库的清单
+fn copy_part_into_folder(
+    zip: &mut ZipWriter<File>,
+    part_path: &Path,
+    database: &str,
+) -> Result<ServerInfo, String> {
+    let file = File::open(part_path).map_err(|e| format!("打开临时文件失败: {}", e))?;
+    let mut part = ZipArchive::new(file).map_err(|e| format!("读取临时文件失败: {}", e))?;
+    let part_manifest = manifest::read_manifest(&mut part)?
+        .ok_or_else(|| format!("数据库 {} 的备份中没有清单", database))?;
+
+    for index in 0..part.len() {
+        let entry = part
+            .by_index_raw(index)
+            .map_err(|e| format!("读取临时文件失败: {}", e))?;
+        if entry.name() == MANIFEST_ENTRY {
+            continue;
+        }
+        let name = format!("{}/{}", database, entry.name());
+        zip.raw_copy_file_rename(entry, name)
+            .map_err(|e| format!("合并数据库 {} 的备份失败: {}", database, e))?;
+    }
+
+    let mut info = ServerInfo {
+        version: part_manifest.server_version,
+        sql_mode: part_manifest.sql_mode,
+        character_set: part_manifest.character_set,
+        collation: part_manifest.collation,
+        tables: Vec::new(),
+        views: Vec::new(),
+        checksums: BTreeMap::new(),
+        resumed_at: Vec::new(),
+        databases: vec![database.to_string()],
+        table_filter: part_manifest.table_filter,
+        mode: part_manifest.mode,
+        // 各数据库在不同的快照中备份，没有共同的binlog位置
+        binlog: None,
+        entries: part_manifest
+            .entries
+            .into_iter()
+            .map(|entry| manifest::ManifestEntry {
+                name: format!("{}/{}", database, entry.name),
+                ..entry
+            })
+            .collect(),
+        row_counts: BTreeMap::new(),
+    };
+    for table in part_manifest.tables {
+        let name = manifest::qualified_table_name(database, &table.name);
+        if table.kind == "view" {
+            info.views.push(name);
+        } else {
+            if let Some(checksum) = table.checksum {
+                info.checksums.insert(name.clone(), checksum);
+            }
+            if let Some(rows) = table.rows {
+                info.row_counts.insert(name.clone(), rows);
+            }
+            info.tables.push(name);
+        }
+    }
+    Ok(info)
+}
+
+// 生成重建用户和角色的SQL：先创建所有账户（角色在前），再执行授权，返回SQL和警告
+fn users_sql(conn: &mut mysql::Conn) -> Result<(String, Vec<String>), String> {
+    // MySQL 8.0.17起可以让认证字符串以十六进制输出，避免二进制内容破坏SQL文件；旧版本忽略
+    let _ = conn.query_drop("SET SESSION print_identified_with_as_hex = ON");
+
+    let accounts: Vec<(String, String)> = conn
+        .query("SELECT User, Host FROM mysql.user ORDER BY User, Host")
+        .map_err(|e| format!("获取用户列表失败: {}", e))?;
+    let mut accounts: Vec<(String, String)> = accounts
+        .into_iter()
+        .filter(|(user, _)| !RESERVED_ACCOUNTS.contains(&user.as_str()))
+        .collect();
+
+    // 角色要在引用它的账户（DEFAULT ROLE）之前创建；MariaDB没有role_edges表，忽略错误
+    let roles: Vec<(String, String)> = conn
+        .query("SELECT DISTINCT FROM_USER, FROM_HOST FROM mysql.role_edges")
+        .unwrap_or_default();
+    accounts.sort_by_key(|account| !roles.contains(account));
+
+    let mut warnings = Vec::new();
+    let mut creates = String::new();
+    let mut grants = String::new();
+    for (user, host) in &accounts {
+        let account = format!(
+            "'{}'@'{}'",
+            crate::escape_string_literal(user, false),
+            crate::escape_string_literal(host, false)
+        );
+
+        match conn.query_first::<String, _>(format!("SHOW CREATE USER {}", account)) {
+            Ok(Some(statement)) => {
+                creates.push_str(&create_if_not_exists(&statement));
+                creates.push_str(";\n");
+            }
+            Ok(None) => {}
+            Err(e) => {
+                creates.push_str(&format!("-- 无法读取账户 {}: {}\n", account, e));
+                warnings.push(format!("无法备份账户 {}: {}", account, e));
+                continue;
+            }
+        }
+
+        match conn.query::<String, _>(format!("SHOW GRANTS FOR {}", account)) {
+            Ok(rows) => {
+                for grant in rows {
+                    grants.push_str(&grant);
+                    grants.push_str(";\n");
+                }
+            }
+            Err(e) => warnings.push(format!("无法备份账户 {} 的授权: {}", account, e)),
+        }
+    }
+
+    let sql = format!(
+        "-- 用户和角色\n{}\n-- 授权\n{}FLUSH PRIVILEGES;\n",
+        creates, grants
+    );
+    Ok((sql, warnings))
+}
+
+// 账户已存在时跳过创建，保留目标服务器上的密码
+fn create_if_not_exists(statement: &str) -> String {
+    match statement.strip_prefix("CREATE USER ") {
+        Some(rest) if !rest.starts_with("IF NOT EXISTS") => {
+            format!("CREATE USER IF NOT EXISTS {}", rest)
+        }
+        _ => statement.to_string(),
+    }
+}
@@ -0,0 +1,964 @@
+// 校验备份归档：检查每个条目的CRC、清单中的SHA-256以及SQL语句的完整性，不需要恢复
+use crate::archive;
+use crate::crypto::DecryptionKey;
+use crate::manifest::{self, BackupManifest, BackupMode, HashingReader, MANIFEST_ENTRY};
+use crate::quote_identifier;
+use crate::restore::{
+    classify_statement, count_insert_rows, restore_archive, send_restore_progress, ConnectionInfo,
+    RestoreOptions, RestoreState, SqlStatementReader, StatementKind, TableAction,
+    RESTORE_CANCELLED,
+};
+use mysql::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use tauri::{command, State, Window};
+use tokio::sync::mpsc;
+
+// mysqldump正常结束时在输出末尾写入的注释
+const MYSQLDUMP_COMPLETED_MARKER: &[u8] = b"-- Dump completed";
+
+// 保留条目末尾的字节数，用于检查mysqldump的结束标记
+const TAIL_SIZE: usize = 256;
+
+// 校验报告
+#[derive(Serialize, Clone, Default)]
+pub struct VerifyReport {
+    pub archive_path: String,
+    // 没有任何错误时为true
+    pub ok: bool,
+    pub has_manifest: bool,
+    pub entries: Vec<EntryCheck>,
+    pub tables: Vec<TableCheck>,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+// 单个归档条目的校验结果
+#[derive(Serialize, Clone)]
+pub struct EntryCheck {
+    pub name: String,
+    pub size: u64,
+    // 条目可以完整读取且CRC正确
+    pub crc_ok: bool,
+    pub sha256: String,
+    // 与清单中的SHA-256是否一致，没有清单时为null
+    pub sha256_ok: Option<bool>,
+    // SQL条目中的语句数
+    pub statements: u64,
+    // SQL条目的最后一条语句是否完整
+    pub complete: bool,
+}
+
+// 单张表的校验结果
+#[derive(Serialize, Clone, Default)]
+pub struct TableCheck {
+    pub name: String,
+    pub has_structure: bool,
+    // 备份中INSERT语句包含的行数
+    pub rows: u64,
+    // 清单中记录的行数，没有清单时为null
+    pub expected_rows: Option<u64>,
+}
+
+// 深度校验中单张表的比较结果
+#[derive(Serialize, Clone)]
+pub struct TableComparison {
+    pub name: String,
+    // 清单中记录的行数
+    pub expected_rows: Option<u64>,
+    pub restored_rows: Option<u64>,
+    // 备份时记录的CHECKSUM TABLE结果，未记录时为null
+    pub expected_checksum: Option<u64>,
+    pub restored_checksum: Option<u64>,
+    pub ok: bool,
+}
+
+// 深度校验报告
+#[derive(Serialize, Clone, Default)]
+pub struct DeepVerifyReport {
+    pub archive_path: String,
+    // 用于试恢复的临时数据库，校验结束后会被删除
+    pub scratch_database: String,
+    // 没有任何错误时为true
+    pub ok: bool,
+    pub tables: Vec<TableComparison>,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+// 校验备份文件
+#[command]
+pub async fn verify_backup(
+    archive_path: &str,
+    key: Option<DecryptionKey>,
+) -> Result<VerifyReport, String> {
+    let archive_path = archive_path.to_string();
+
+    // 创建通道用于接收结果，校验需要读取整个归档，放到单独的线程中执行
+    let (tx, mut rx) = mpsc::channel::<Result<VerifyReport, String>>(1);
+
+    let _ = thread::spawn(move || {
+        let verify_result = verify_archive(Path::new(&archive_path), key.as_ref());
+
+        if let Err(e) = tx.blocking_send(verify_result) {
+            eprintln!("无法发送校验结果: {}", e);
+        }
+    });
+
+    match rx.recv().await {
+        Some(r) => r,
+        None => Err("校验过程意外终止".to_string()),
+    }
+}
+
+// 深度校验：把备份恢复到临时数据库，比较每张表的行数和校验和后删除临时数据库
+#[command]
+pub async fn deep_verify_backup(
+    window: Window,
+    host: &str,
+    port: u16,
+    username: &str,
+    password: &str,
+    archive_path: &str,
+    engine: Option<&str>,
+    key: Option<DecryptionKey>,
+    restore_state: State<'_, RestoreState>,
+) -> Result<DeepVerifyReport, String> {
+    // 试恢复与普通恢复共用运行标记，可以通过cancel_restore取消
+    let cancel_requested = restore_state.begin()?;
+
+    // 克隆需要的数据以便在线程中使用
+    let window_clone = window.clone();
+    let connection = ConnectionInfo {
+        host: host.to_string(),
+        port,
+        username: username.to_string(),
+        password: password.to_string(),
+    };
+    let archive_path = archive_path.to_string();
+    let engine = engine.map(|s| s.to_string());
+
+    // 创建通道用于接收结果
+    let (tx, mut rx) = mpsc::channel::<Result<DeepVerifyReport, String>>(1);
+
+    let _ = thread::spawn(move || {
+        let verify_result = deep_verify_archive(
+            &window_clone,
+            &connection,
+            &archive_path,
+            engine.as_deref(),
+            key.as_ref(),
+            &cancel_requested,
+        );
+
+        if let Err(e) = tx.blocking_send(verify_result) {
+            eprintln!("无法发送校验结果: {}", e);
+        }
+    });
+
+    // 等待结果
+    let result = match rx.recv().await {
+        Some(r) => r,
+        None => Err("校验过程意外终止".to_string()),
+    };
+
+    // 释放恢复中标记
+    restore_state.end()?;
+
+    result
+}
+
+// 校验归档并生成报告，归档本身损坏时也返回报告，只有无法读取文件时返回错误。
+// 多数据库分别归档时传入保存归档的目录，校验其中的每个归档
+pub fn verify_archive(
+    archive_path: &Path,
+    key: Option<&DecryptionKey>,
+) -> Result<VerifyReport, String> {
+    if archive_path.is_dir() {
+        return verify_archive_dir(archive_path, key);
+    }
+
+    let mut report = VerifyReport {
+        archive_path: archive_path.to_string_lossy().into_owned(),
+        ..Default::default()
+    };
+
+    // 单文件SQL格式的清单在备份文件旁边，tar.zst的清单在读取条目时取出。
+    // ZIP可以先读取清单，出错时在读取条目时报告
+    let format = archive::detect_format(archive_path, key)?;
+    let mut manifest = None;
+    if format.is_sql_stream() {
+        match archive::read_manifest(archive_path, key) {
+            Ok(sidecar) => manifest = sidecar,
+            Err(e) => report.errors.push(e),
+        }
+    } else if format == archive::ArchiveFormat::Zip {
+        manifest = archive::read_manifest_upfront(archive_path, key).unwrap_or(None);
+    }
+
+    let mut tables: BTreeMap<String, TableCheck> = BTreeMap::new();
+    let mut entry_errors = Vec::new();
+    // 内置引擎的表条目没有文件头，沿用清单或前面条目中文件头设置的sql_mode
+    let mut no_backslash_escapes = manifest
+        .as_ref()
+        .is_some_and(|manifest| manifest.no_backslash_escapes());
+
+    let read_result = archive::read_entries(archive_path, key, |name, entry, _| {
+        if name == MANIFEST_ENTRY {
+            let mut content = Vec::new();
+            match entry.read_to_end(&mut content) {
+                Ok(_) => match manifest::parse_manifest(&content) {
+                    Ok(parsed) => manifest = Some(parsed),
+                    Err(e) => entry_errors.push(e),
+                },
+                Err(e) => entry_errors.push(format!("读取备份清单失败: {}", e)),
+            }
+            return Ok(());
+        }
+
+        let mut reader = TailReader::new(HashingReader::new(entry));
+        let mut check = EntryCheck {
+            name: name.to_string(),
+            size: 0,
+            crc_ok: true,
+            sha256: String::new(),
+            sha256_ok: None,
+            statements: 0,
+            complete: true,
+        };
+
+        let read_result = if name.ends_with(".sql") {
+            check_sql_entry(
+                &mut reader,
+                name,
+                &mut no_backslash_escapes,
+                &mut check,
+                &mut tables,
+                &mut entry_errors,
+            )
+        } else {
+            io::copy(&mut reader, &mut io::sink()).map(|_| ())
+        };
+
+        // 单文件SQL只有通过清单才能知道是否为mysqldump的输出
+        let is_mysqldump_output = name.ends_with("mysqldump_backup.sql")
+            || (name == archive::SQL_STREAM_ENTRY
+                && manifest.as_ref().map(|m| m.engine.as_str()) == Some("mysqldump"));
+
+        // ZIP和gzip读取器在条目结束时校验CRC，zstd校验帧校验和，读取失败说明数据损坏或被截断
+        if let Err(e) = read_result {
+            check.crc_ok = false;
+            check.complete = false;
+            entry_errors.push(format!("条目 {} 读取失败: {}", name, e));
+        } else if is_mysqldump_output && !reader.ends_with_marker() {
+            check.complete = false;
+            entry_errors.push("mysqldump输出缺少结束标记，备份可能不完整".to_string());
+        }
+
+        let hashing_reader = reader.into_inner();
+        check.size = hashing_reader.size();
+        check.sha256 = hashing_reader.hex_digest();
+        report.entries.push(check);
+        Ok(())
+    });
+
+    report.errors.append(&mut entry_errors);
+    if let Err(e) = read_result {
+        report.errors.push(format!("{}，文件可能已损坏或被截断", e));
+    }
+
+    report.has_manifest = manifest.is_some();
+    if manifest.is_none() && report.errors.is_empty() {
+        report
+            .warnings
+            .push("备份中没有清单，无法校验SHA-256和行数".to_string());
+    }
+
+    if let Some(manifest) = &manifest {
+        compare_with_manifest(manifest, &mut report, &mut tables);
+        if !manifest.resumed_at.is_empty() {
+            report.warnings.push(format!(
+                "备份中断后继续完成（{}），各表数据不属于同一个快照",
+                manifest.resumed_at.join(", ")
+            ));
+        }
+        report.warnings.extend(manifest.partial_notes());
+    }
+
+    // 有数据没有结构的表无法恢复，只备份数据的备份恢复到已有的表结构中
+    let data_only = manifest.as_ref().map(|m| m.mode) == Some(BackupMode::DataOnly);
+    for table in tables.values() {
+        if !table.has_structure && table.rows > 0 && !data_only {
+            report
+                .errors
+                .push(format!("表 {} 有数据但备份中没有表结构", table.name));
+        }
+    }
+
+    report.tables = tables.into_values().collect();
+    report.ok = report.errors.is_empty();
+    Ok(report)
+}
+
+// 校验目录中的每个归档，合并为一份报告，条目名和表名加上归档文件名前缀
+fn verify_archive_dir(dir: &Path, key: Option<&DecryptionKey>) -> Result<VerifyReport, String> {
+    let mut report = VerifyReport {
+        archive_path: dir.to_string_lossy().into_owned(),
+        has_manifest: true,
+        ..Default::default()
+    };
+
+    let mut archives: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| format!("读取目录失败: {}", e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .file_name()
+                    .is_some_and(|name| archive::is_archive_file_name(&name.to_string_lossy()))
+        })
+        .collect();
+    if archives.is_empty() {
+        return Err(format!("目录 {} 中没有备份文件", dir.display()));
+    }
+    archives.sort();
+
+    for path in archives {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let archive_report = verify_archive(&path, key)?;
+
+        report.has_manifest &= archive_report.has_manifest;
+        report
+            .entries
+            .extend(archive_report.entries.into_iter().map(|mut entry| {
+                entry.name = format!("{}/{}", file_name, entry.name);
+                entry
+            }));
+        report
+            .tables
+            .extend(archive_report.tables.into_iter().map(|mut table| {
+                table.name = format!("{}/{}", file_name, table.name);
+                table
+            }));
+        report.errors.extend(
+            archive_report
+                .errors
+                .into_iter()
+                .map(|e| format!("{}: {}", file_name, e)),
+        );
+        report.warnings.extend(
+            archive_report
+                .warnings
+                .into_iter()
+                .map(|w| format!("{}: {}", file_name, w)),
+        );
+    }
+
+    report.ok = report.errors.is_empty();
+    Ok(report)
+}
+
+// 逐条解析SQL条目，检查数据段是否成对出现、语句是否完整，并统计每张表的行数。
+// no_backslash_escapes为条目开始时的模式，读取后更新为条目结束时的模式
+fn check_sql_entry<R: Read>(
+    reader: &mut R,
+    entry_name: &str,
+    no_backslash_escapes: &mut bool,
+    check: &mut EntryCheck,
+    tables: &mut BTreeMap<String, TableCheck>,
+    errors: &mut Vec<String>,
+) -> io::Result<()> {
+    let mut statements = SqlStatementReader::new(BufReader::new(reader))
+        .with_no_backslash_escapes(*no_backslash_escapes);
+    // 当前处于LOCK TABLES和UNLOCK TABLES之间的表
+    let mut open_section: Option<String> = None;
+
+    while let Some(statement) = statements.next_statement()? {
+        check.statements += 1;
+
+        match classify_statement(&statement) {
+            StatementKind::Table {
+                name,
+                action: TableAction::Create { .. },
+            } => {
+                let key = manifest::table_key(entry_name, &name);
+                table_check(tables, &key).has_structure = true;
+            }
+            StatementKind::Table {
+                name,
+                action: TableAction::Data,
+            } => {
+                if starts_with_keywords(&statement, b"LOCK TABLES") {
+                    if let Some(previous) = open_section.replace(name.clone()) {
+                        errors.push(format!(
+                            "{} 中表 {} 的数据段没有结束就开始了表 {} 的数据段",
+                            entry_name, previous, name
+                        ));
+                    }
+                }
+                let rows =
+                    count_insert_rows(&statement, statements.statement_no_backslash_escapes());
+                if rows > 0 {
+                    let key = manifest::table_key(entry_name, &name);
+                    table_check(tables, &key).rows += rows;
+                }
+            }
+            StatementKind::Other if starts_with_keywords(&statement, b"UNLOCK TABLES") => {
+                open_section = None;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(table) = open_section {
+        errors.push(format!("{} 中表 {} 的数据段没有结束", entry_name, table));
+    }
+    if !statements.is_complete() {
+        check.complete = false;
+        errors.push(format!("{} 的最后一条语句不完整", entry_name));
+    }
+    *no_backslash_escapes = statements.no_backslash_escapes();
+
+    Ok(())
+}
+
+// 对照清单检查条目的SHA-256和每张表的行数
+fn compare_with_manifest(
+    manifest: &manifest::BackupManifest,
+    report: &mut VerifyReport,
+    tables: &mut BTreeMap<String, TableCheck>,
+) {
+    for expected in &manifest.entries {
+        match report.entries.iter_mut().find(|e| e.name == expected.name) {
+            Some(entry) => {
+                let matches = entry.crc_ok
+                    && entry.size == expected.size
+                    && entry.sha256.eq_ignore_ascii_case(&expected.sha256);
+                entry.sha256_ok = Some(matches);
+                if entry.crc_ok && !matches {
+                    report
+                        .errors
+                        .push(format!("条目 {} 的SHA-256与清单不一致", entry.name));
+                }
+            }
+            None => report
+                .errors
+                .push(format!("清单中的条目 {} 在备份中不存在", expected.name)),
+        }
+    }
+
+    for entry in &report.entries {
+        if !manifest.entries.iter().any(|e| e.name == entry.name) {
+            report
+                .errors
+                .push(format!("条目 {} 不在清单中", entry.name));
+        }
+    }
+
+    for expected in manifest.tables.iter().filter(|t| t.kind == "table") {
+        let table = table_check(tables, &expected.name);
+        table.expected_rows = expected.rows;
+        if !table.has_structure && manifest.mode.includes_schema() {
+            report
+                .errors
+                .push(format!("清单中的表 {} 在备份中没有表结构", expected.name));
+        }
+        if let Some(rows) = expected.rows {
+            if rows != table.rows {
+                report.errors.push(format!(
+                    "表 {} 的行数与清单不一致：备份中 {} 行，清单记录 {} 行",
+                    expected.name, table.rows, rows
+                ));
+            }
+        }
+    }
+}
+
+fn table_check<'a>(tables: &'a mut BTreeMap<String, TableCheck>, name: &str) -> &'a mut TableCheck {
+    tables
+        .entry(name.to_string())
+        .or_insert_with(|| TableCheck {
+            name: name.to_string(),
+            ..Default::default()
+        })
+}
+
+// 语句是否以指定的关键字开头，忽略大小写和前导空白
+fn starts_with_keywords(statement: &[u8], keywords: &[u8]) -> bool {
+    let start = statement
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(statement.len());
+    let statement = &statement[start..];
+    statement.len() >= keywords.len() && statement[..keywords.len()].eq_ignore_ascii_case(keywords)
+}
+
+// 保留最后读取的若干字节的读取器
+struct TailReader<R> {
+    inner: R,
+    tail: Vec<u8>,
+}
+
+impl<R> TailReader<R> {
+    fn new(inner: R) -> Self {
+        TailReader {
+            inner,
+            tail: Vec::with_capacity(TAIL_SIZE * 2),
+        }
+    }
+
+    // 末尾是否包含mysqldump的结束标记
+    fn ends_with_marker(&self) -> bool {
+        self.tail
+            .windows(MYSQLDUMP_COMPLETED_MARKER.len())
+            .any(|window| window == MYSQLDUMP_COMPLETED_MARKER)
+    }
+
+    fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for TailReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.tail.extend_from_slice(&buf[..n]);
+        if self.tail.len() > TAIL_SIZE {
+            let excess = self.tail.len() - TAIL_SIZE;
+            self.tail.drain(..excess);
+        }
+        Ok(n)
+    }
+}
+
+// 试恢复并比较，清单中的行数和校验和作为期望值
+fn deep_verify_archive(
+    window: &Window,
+    connection: &ConnectionInfo,
+    archive_path: &str,
+    engine: Option<&str>,
+    key: Option<&DecryptionKey>,
+    cancel_requested: &AtomicBool,
+) -> Result<DeepVerifyReport, String> {
+    let mut report = DeepVerifyReport {
+        archive_path: archive_path.to_string(),
+        ..Default::default()
+    };
+
+    if Path::new(archive_path).is_dir() {
+        return Err("深度校验只支持单个备份文件，请选择目录中的备份文件".to_string());
+    }
+    let manifest = archive::read_manifest(Path::new(archive_path), key)?
+        .ok_or_else(|| "备份中没有清单，无法进行深度校验".to_string())?;
+    // 多数据库备份和用户备份无法恢复到单个临时数据库
+    if !manifest.databases.is_empty() || manifest.database.is_empty() {
+        return Err("多数据库备份和用户备份不支持深度校验".to_string());
+    }
+    if manifest.mode == BackupMode::DataOnly {
+        return Err("只包含数据的备份没有表结构，无法恢复到临时数据库".to_string());
+    }
+
+    let scratch_database = scratch_database_name(&manifest.database);
+    report.scratch_database = scratch_database.clone();
+
+    // 临时数据库名包含时间戳，已存在说明有同名的库，不能覆盖
+    {
+        let mut conn = connection.connect(None)?;
+        let existing: Option<String> = conn
+            .exec_first(
+                "SELECT SCHEMA_NAME FROM information_schema.SCHEMATA WHERE SCHEMA_NAME = ?",
+                (&scratch_database,),
+            )
+            .map_err(|e| format!("检查临时数据库失败: {}", e))?;
+        if existing.is_some() {
+            return Err(format!("临时数据库 {} 已存在", scratch_database));
+        }
+
+        let server_version: Option<String> = conn
+            .query_first("SELECT VERSION()")
+            .map_err(|e| format!("获取服务器信息失败: {}", e))?;
+        if server_version.as_deref() != Some(manifest.server_version.as_str()) {
+            report.warnings.push(format!(
+                "校验服务器版本 {} 与备份时的服务器版本 {} 不同，CHECKSUM TABLE结果可能不一致",
+                server_version.unwrap_or_default(),
+                manifest.server_version
+            ));
+        }
+    }
+
+    let options = RestoreOptions::into_database(&scratch_database);
+    let result = restore_archive(
+        window,
+        connection,
+        archive_path,
+        engine,
+        &options,
+        key,
+        cancel_requested,
+    )
+    .map_err(|e| {
+        if e == RESTORE_CANCELLED {
+            e
+        } else {
+            format!("恢复到临时数据库失败: {}", e)
+        }
+    })
+    .and_then(|_| {
+        // 恢复可能持续很久，比较时重新建立连接
+        let mut conn = connection.connect(None)?;
+        compare_restored_tables(
+            window,
+            &mut conn,
+            &scratch_database,
+            &manifest,
+            &mut report,
+            cancel_requested,
+        )
+    });
+
+    // 无论校验结果如何都删除临时数据库
+    send_restore_progress(window, 100, "正在删除临时数据库...", None);
+    let drop_result = connection.connect(None).and_then(|mut conn| {
+        conn.query_drop(format!(
+            "DROP DATABASE IF EXISTS {}",
+            quote_identifier(&scratch_database)
+        ))
+        .map_err(|e| e.to_string())
+    });
+    if let Err(e) = drop_result {
+        report.warnings.push(format!(
+            "删除临时数据库 {} 失败，请手动删除: {}",
+            scratch_database, e
+        ));
+    }
+
+    match result {
+        Ok(_) => {}
+        Err(e) if e == RESTORE_CANCELLED => return Err(e),
+        Err(e) => report.errors.push(e),
+    }
+
+    report.ok = report.errors.is_empty();
+    send_restore_progress(
+        window,
+        100,
+        if report.ok {
+            "深度校验通过"
+        } else {
+            "深度校验失败"
+        },
+        None,
+    );
+    Ok(report)
+}
+
+// 对恢复出的每张表执行COUNT(*)和CHECKSUM TABLE并与清单比较
+fn compare_restored_tables(
+    window: &Window,
+    conn: &mut mysql::Conn,
+    scratch_database: &str,
+    manifest: &BackupManifest,
+    report: &mut DeepVerifyReport,
+    cancel_requested: &AtomicBool,
+) -> Result<(), String> {
+    let tables: Vec<_> = manifest
+        .tables
+        .iter()
+        .filter(|table| table.kind == "table")
+        .collect();
+    if tables.iter().any(|table| table.checksum.is_none()) {
+        report
+            .warnings
+            .push("部分表在备份时没有记录CHECKSUM TABLE结果，这些表只比较行数".to_string());
+    }
+
+    for (index, table) in tables.iter().enumerate() {
+        if cancel_requested.load(Ordering::SeqCst) {
+            return Err(RESTORE_CANCELLED.to_string());
+        }
+
+        let percent = ((index as f32) / (tables.len() as f32) * 100.0) as u8;
+        send_restore_progress(
+            window,
+            percent,
+            "正在比较行数和校验和...",
+            Some(&table.name),
+        );
+
+        let qualified = format!(
+            "{}.{}",
+            quote_identifier(scratch_database),
+            quote_identifier(&table.name)
+        );
+        let mut comparison = TableComparison {
+            name: table.name.clone(),
+            expected_rows: table.rows,
+            restored_rows: None,
+            expected_checksum: table.checksum,
+            restored_checksum: None,
+            ok: true,
+        };
+
+        match conn.query_first::<u64, _>(format!("SELECT COUNT(*) FROM {}", qualified)) {
+            Ok(rows) => comparison.restored_rows = rows,
+            Err(e) => {
+                comparison.ok = false;
+                report
+                    .errors
+                    .push(format!("读取恢复后的表 {} 失败: {}", table.name, e));
+            }
+        }
+
+        if table.checksum.is_some() {
+            match conn
+                .query_first::<(String, Option<u64>), _>(format!("CHECKSUM TABLE {}", qualified))
+            {
+                Ok(row) => comparison.restored_checksum = row.and_then(|(_, checksum)| checksum),
+                Err(e) => {
+                    comparison.ok = false;
+                    report
+                        .errors
+                        .push(format!("计算恢复后的表 {} 的校验和失败: {}", table.name, e));
+                }
+            }
+        }
+
+        if comparison.ok {
+            check_restored_table(&mut comparison, &mut report.errors);
+        }
+
+        report.tables.push(comparison);
+    }
+
+    Ok(())
+}
+
+// 比较恢复后的行数和校验和与备份时记录的值，不一致时记录错误
+fn check_restored_table(comparison: &mut TableComparison, errors: &mut Vec<String>) {
+    if let Some(expected) = comparison.expected_rows {
+        if comparison.restored_rows != Some(expected) {
+            comparison.ok = false;
+            errors.push(format!(
+                "表 {} 的行数不一致：备份时 {} 行，恢复后 {} 行",
+                comparison.name,
+                expected,
+                comparison.restored_rows.unwrap_or(0)
+            ));
+        }
+    }
+    if let Some(expected) = comparison.expected_checksum {
+        if comparison.restored_checksum != Some(expected) {
+            comparison.ok = false;
+            errors.push(format!("表 {} 的校验和与备份时不一致", comparison.name));
+        }
+    }
+}
+
+// 生成临时数据库名，总长度不超过MySQL的64个字符限制
+fn scratch_database_name(database: &str) -> String {
+    let prefix: String = database.chars().take(40).collect();
+    format!(
+        "verify_{}_{}",
+        prefix,
+        chrono::Local::now().format("%Y%m%d%H%M%S")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    const ORDERS: &str = "CREATE TABLE `orders` (`id` int);\n\
+                          LOCK TABLES `orders` WRITE;\n\
+                          INSERT INTO `orders` (`id`) VALUES (1),(2);\n\
+                          UNLOCK TABLES;\n";
+
+    fn write_zip(path: &Path, entries: &[(&str, &str)], method: zip::CompressionMethod) {
+        let mut zip = ZipWriter::new(fs::File::create(path).unwrap());
+        for (name, content) in entries {
+            zip.start_file(*name, FileOptions::default().compression_method(method))
+                .unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn verify_zip(entries: &[(&str, &str)]) -> VerifyReport {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("BACKUP_test.zip");
+        write_zip(&path, entries, zip::CompressionMethod::Deflated);
+        verify_archive(&path, None).unwrap()
+    }
+
+    #[test]
+    fn verify_archive_counts_rows_in_paired_sections() {
+        let report = verify_zip(&[("table_orders.sql", ORDERS)]);
+        assert!(report.ok, "{:?}", report.errors);
+        assert!(!report.has_manifest);
+        assert_eq!(report.entries.len(), 1);
+        assert!(report.entries[0].crc_ok && report.entries[0].complete);
+        assert_eq!(report.entries[0].statements, 4);
+        assert_eq!(report.tables.len(), 1);
+        assert!(report.tables[0].has_structure);
+        assert_eq!(report.tables[0].rows, 2);
+    }
+
+    #[test]
+    fn verify_archive_reports_unclosed_data_section() {
+        let report = verify_zip(&[(
+            "table_orders.sql",
+            "CREATE TABLE `orders` (`id` int);\n\
+             LOCK TABLES `orders` WRITE;\n\
+             INSERT INTO `orders` (`id`) VALUES (1);\n",
+        )]);
+        assert!(!report.ok);
+        assert!(report
+            .errors
+            .iter()
+            .any(|e| e.contains("表 orders 的数据段没有结束")));
+    }
+
+    #[test]
+    fn verify_archive_reports_overlapping_data_sections() {
+        let report = verify_zip(&[(
+            "mysqldump_backup.sql",
+            "LOCK TABLES `a` WRITE;\n\
+             INSERT INTO `a` VALUES (1);\n\
+             LOCK TABLES `b` WRITE;\n\
+             INSERT INTO `b` VALUES (1);\n\
+             UNLOCK TABLES;\n\
+             -- Dump completed on 2024-01-15 10:00:00\n",
+        )]);
+        assert!(!report.ok);
+        assert!(report
+            .errors
+            .iter()
+            .any(|e| e.contains("表 a 的数据段没有结束就开始了表 b 的数据段")));
+    }
+
+    #[test]
+    fn verify_archive_reports_incomplete_statement_and_missing_marker() {
+        let report = verify_zip(&[(
+            "mysqldump_backup.sql",
+            "LOCK TABLES `a` WRITE;\nINSERT INTO `a` VALUES ('unterminated",
+        )]);
+        assert!(!report.ok);
+        assert!(!report.entries[0].complete);
+        assert!(report
+            .errors
+            .iter()
+            .any(|e| e.contains("最后一条语句不完整")));
+        assert!(report.errors.iter().any(|e| e.contains("缺少结束标记")));
+    }
+
+    #[test]
+    fn verify_archive_detects_corrupt_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("BACKUP_test.zip");
+        // 不压缩时条目内容原样存储，修改其中一个字节后CRC不再一致
+        write_zip(
+            &path,
+            &[("table_orders.sql", ORDERS)],
+            zip::CompressionMethod::Stored,
+        );
+        let mut bytes = fs::read(&path).unwrap();
+        let offset = bytes
+            .windows(b"VALUES (1),(2)".len())
+            .position(|window| window == b"VALUES (1),(2)")
+            .unwrap();
+        bytes[offset + 8] = b'7';
+        fs::write(&path, bytes).unwrap();
+
+        let report = verify_archive(&path, None).unwrap();
+        assert!(!report.ok);
+        assert!(!report.entries[0].crc_ok);
+        assert!(report
+            .errors
+            .iter()
+            .any(|e| e.contains("条目 table_orders.sql 读取失败")));
+    }
+
+    #[test]
+    fn verify_archive_detects_truncated_zip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("BACKUP_test.zip");
+        write_zip(
+            &path,
+            &[("table_orders.sql", ORDERS)],
+            zip::CompressionMethod::Deflated,
+        );
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+
+        let report = verify_archive(&path, None).unwrap();
+        assert!(!report.ok);
+        assert!(report.errors.iter().any(|e| e.contains("损坏或被截断")));
+    }
+
+    #[test]
+    fn verify_archive_detects_truncated_sql_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let path: PathBuf = dir.path().join("BACKUP_test.sql.gz");
+        let script = ORDERS.repeat(200);
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(script.as_bytes()).unwrap();
+        let bytes = encoder.finish().unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 16]).unwrap();
+
+        let report = verify_archive(&path, None).unwrap();
+        assert!(!report.ok);
+        assert_eq!(report.entries[0].name, archive::SQL_STREAM_ENTRY);
+        assert!(!report.entries[0].crc_ok);
+    }
+
+    #[test]
+    fn check_restored_table_compares_rows_and_checksums() {
+        let comparison = |restored_rows, restored_checksum| TableComparison {
+            name: "orders".to_string(),
+            expected_rows: Some(2),
+            restored_rows,
+            expected_checksum: Some(12345),
+            restored_checksum,
+            ok: true,
+        };
+
+        let mut errors = Vec::new();
+        let mut matching = comparison(Some(2), Some(12345));
+        check_restored_table(&mut matching, &mut errors);
+        assert!(matching.ok);
+        assert!(errors.is_empty());
+
+        let mut wrong_rows = comparison(Some(1), Some(12345));
+        check_restored_table(&mut wrong_rows, &mut errors);
+        assert!(!wrong_rows.ok);
+        assert_eq!(errors, ["表 orders 的行数不一致：备份时 2 行，恢复后 1 行"]);
+
+        errors.clear();
+        let mut wrong_checksum = comparison(Some(2), Some(54321));
+        check_restored_table(&mut wrong_checksum, &mut errors);
+        assert!(!wrong_checksum.ok);
+        assert_eq!(errors, ["表 orders 的校验和与备份时不一致"]);
+
+        // 备份时没有记录校验和的表只比较行数
+        errors.clear();
+        let mut rows_only = TableComparison {
+            expected_checksum: None,
+            ..comparison(Some(2), None)
+        };
+        check_restored_table(&mut rows_only, &mut errors);
+        assert!(rows_only.ok);
+        assert!(errors.is_empty());
+    }
+}
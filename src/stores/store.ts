@@ -8,12 +8,30 @@ import {
   isAutoStartEnabled,
 } from "../utils/autostart";
 import {
+  archiveExtensions,
   backupMysqlDatabase,
+  cancelBackup,
   checkMysqldumpAvailability,
   cleanupOldBackups,
+  encryptedSuffix,
+  findResumableBackup,
+  listenBackupProgress,
+  listenBackupSchedule,
+  setBackupJobLimits,
+  setBackupSchedule,
+  verifyBackup,
+} from "../utils/backup";
+import type {
+  ArchiveFormat,
+  BackupMode,
+  CompressionCodec,
+  EncryptionOptions,
+  ScheduleEvent,
+  TableFilter,
 } from "../utils/backup";
 import { useDateFormat } from "@vueuse/core";
 import { sendNotification } from "@tauri-apps/plugin-notification";
+import { ask } from "@tauri-apps/plugin-dialog";
 
 // 定义Store的状态接口
 interface State {
@@ -33,7 +51,11 @@ interface State {
   backup: {
     path: string;
     auto: boolean;
-    frequency: string;
+    cron: string; // 自动备份的cron表达式
+    timezone: string; // 自动备份使用的时区，为空时使用系统时区
+    jitterMinutes: number; // 自动备份随机延后的最大分钟数
+    catchUp: boolean; // 休眠或关机错过的自动备份在恢复后补做
+    nextBackupTime: string; // 下次自动备份时间
     keepDays: number; // 保留备份天数，0或负数表示不限制保留期限
     isBackingUp: boolean;
     backupProgress: number;
@@ -41,6 +63,25 @@ interface State {
     lastBackupTime: string;
     mysqldumpAvailable: boolean; // 此字段表示系统中是否有mysqldump命令可用
     backupEngine: string; // 备份引擎类型：'mysqldump' 或 'builtin'
+    archiveFormat: ArchiveFormat; // 备份文件格式
+    compression: CompressionCodec; // ZIP格式的压缩方式
+    compressionLevel: number | null; // 压缩级别，null表示使用默认级别
+    encryptionMode: "none" | "passphrase" | "recipients"; // 加密方式
+    encryptionPassphrase: string; // 加密密码
+    encryptionRecipients: string; // age公钥，每行一个
+    resumable: boolean; // 内置引擎的可续传备份
+    workers: number; // 内置引擎并行备份的线程数，1为串行备份
+    chunkRows: number; // 大表按唯一键分块的每块行数，0为不分块
+    recordChecksums: boolean; // 备份时记录每张表的CHECKSUM TABLE结果，供深度校验比较
+    includeTables: string; // 只备份匹配的表，每行一个模式
+    excludeTables: string; // 不备份匹配的表，每行一个模式
+    whereClauses: string; // 按表设置的WHERE条件，每行一个“表名: 条件”
+    mode: BackupMode; // 完整备份、只备份表结构或只备份数据
+    recordBinlogPosition: boolean; // 记录快照对应的binlog位置
+    writeChangeSource: boolean; // 写入注释掉的复制配置语句
+    maxConcurrentJobs: number; // 同时运行的备份任务数，0表示不限制
+    maxJobsPerServer: number; // 同一服务器同时运行的备份任务数，0表示不限制
+    jobId: number | null; // 当前备份任务的编号
     currentTableName?: string; // 当前正在备份的表名
   };
 
@@ -65,18 +106,31 @@ interface State {
   // 定时器
   connectionTimer: number | null;
   progressTimer: number | null; // 备份进度动画定时器
-  backupMonitorTimer: number | null; // 自动备份监控定时器
+  scheduleUnlisten: (() => void) | null; // 取消监听定时备份事件
+  scheduledJobUnlisten: (() => void) | null; // 取消监听定时备份任务进度
   isCheckingConnection: boolean;
   settingsSaveDisabled: boolean;
 }
 
-// 定义备份频率选项
+// 后端取消备份时返回的错误信息
+const BACKUP_CANCELLED = "备份已取消";
+
+// 定义备份频率选项（cron表达式：分 时 日 月 周）
 export const backupFrequencies = [
-  { title: "每天", value: "daily" },
-  { title: "每周", value: "weekly" },
-  { title: "每月", value: "monthly" },
+  { title: "每天 02:00", value: "0 2 * * *" },
+  { title: "工作日 02:30", value: "30 2 * * 1-5" },
+  { title: "每周日 03:00", value: "0 3 * * 0" },
+  { title: "每月1日 03:00", value: "0 3 1 * *" },
+  { title: "每6小时", value: "0 */6 * * *" },
 ];
 
+// 旧版本保存的备份频率对应的cron表达式
+const legacyFrequencies: Record<string, string> = {
+  daily: "0 2 * * *",
+  weekly: "0 3 * * 0",
+  monthly: "0 3 1 * *",
+};
+
 // 创建和导出store
 export const usePiniaStore = defineStore("main", {
   // 定义状态
@@ -94,7 +148,11 @@ export const usePiniaStore = defineStore("main", {
     backup: {
       path: "",
       auto: false,
-      frequency: "daily",
+      cron: "0 2 * * *",
+      timezone: "",
+      jitterMinutes: 0,
+      catchUp: true,
+      nextBackupTime: "",
       keepDays: 180, // 默认保留180天
       isBackingUp: false,
       backupProgress: 0,
@@ -102,6 +160,25 @@ export const usePiniaStore = defineStore("main", {
       lastBackupTime: "",
       mysqldumpAvailable: false,
       backupEngine: "builtin", // 默认使用内置引擎
+      archiveFormat: "zip",
+      compression: "deflate",
+      compressionLevel: null,
+      encryptionMode: "none",
+      encryptionPassphrase: "",
+      encryptionRecipients: "",
+      resumable: false,
+      workers: 1,
+      chunkRows: 0,
+      recordChecksums: false,
+      includeTables: "",
+      excludeTables: "",
+      whereClauses: "",
+      mode: "full",
+      recordBinlogPosition: false,
+      writeChangeSource: false,
+      maxConcurrentJobs: 2,
+      maxJobsPerServer: 1,
+      jobId: null,
       currentTableName: undefined,
     },
     system: {
@@ -120,7 +197,8 @@ export const usePiniaStore = defineStore("main", {
     },
     connectionTimer: null,
     progressTimer: null,
-    backupMonitorTimer: null,
+    scheduleUnlisten: null,
+    scheduledJobUnlisten: null,
     isCheckingConnection: false,
     settingsSaveDisabled: false,
   }),
@@ -178,6 +256,9 @@ export const usePiniaStore = defineStore("main", {
         await saveSetting("database.connectionUrl", connectionUrl);
         console.log("数据库连接URL已保存");
 
+        // 定时备份使用最新的连接信息
+        await this.syncBackupSchedule();
+
         console.log("数据库设置已保存");
       } catch (error) {
         console.error("保存数据库设置失败:", error);
@@ -379,106 +460,168 @@ export const usePiniaStore = defineStore("main", {
       }
     },
 
-    // 启动自动备份监控
-    startBackupMonitor() {
-      // 如果定时器已存在，先停止
-      this.stopBackupMonitor();
-
-      // 启动新的定时器，每10分钟检查一次是否需要自动备份
-      this.backupMonitorTimer = window.setInterval(() => {
-        this.checkAndAutoBackup();
-      }, 10 * 60 * 1000); // 10分钟 = 600000毫秒
+    // 把自动备份设置同步到后端调度器，由后端按计划执行备份
+    async syncBackupSchedule() {
+      try {
+        const status = await setBackupSchedule({
+          enabled: this.backup.auto && !!this.backup.path,
+          cron: this.backup.cron,
+          timezone: this.backup.timezone,
+          jitter_seconds: Math.max(0, this.backup.jitterMinutes) * 60,
+          catch_up: this.backup.catchUp,
+          host: this.database.host,
+          port: this.database.port,
+          username: this.database.username,
+          password: this.database.password,
+          database: this.database.database,
+          backup_dir: this.backup.path,
+          engine: this.backup.backupEngine,
+          options: {
+            format: this.backup.archiveFormat,
+            compression: this.backup.compression,
+            compression_level: this.backup.compressionLevel,
+            encryption: this.getBackupEncryption(),
+            resumable: this.backup.resumable,
+            workers: this.backup.workers,
+            chunk_rows: this.backup.chunkRows,
+            record_checksums: this.backup.recordChecksums,
+            table_filter: this.getTableFilter(),
+            mode: this.backup.mode,
+            record_binlog_position: this.backup.recordBinlogPosition,
+            write_change_source: this.backup.writeChangeSource,
+            // 定时备份遇到未完成的备份时直接继续
+            resume: true,
+          },
+          keep_days: this.backup.keepDays,
+        });
+        this.setNextBackupTime(status.next_run);
+      } catch (error) {
+        console.error("设置定时备份失败:", error);
+        this.showSnackbar(`设置定时备份失败: ${error}`, "error");
+      }
+    },
 
-      console.log("已启动自动备份监控");
+    // 更新下次自动备份时间的显示
+    setNextBackupTime(nextRun: string | null) {
+      this.backup.nextBackupTime = nextRun
+        ? new Date(nextRun).toLocaleString()
+        : "";
     },
 
-    // 停止自动备份监控
-    stopBackupMonitor() {
-      if (this.backupMonitorTimer) {
-        window.clearInterval(this.backupMonitorTimer);
-        this.backupMonitorTimer = null;
-        console.log("已停止自动备份监控");
-      }
+    // 监听后端调度器的定时备份事件
+    async startScheduleListener() {
+      if (this.scheduleUnlisten) return;
+      this.scheduleUnlisten = await listenBackupSchedule((event) =>
+        this.handleScheduleEvent(event)
+      );
+      console.log("已开始监听定时备份事件");
     },
 
-    // 检查并执行自动备份
-    async checkAndAutoBackup() {
-      // 检查自动备份是否启用
-      if (!this.backup.auto) {
-        console.log("自动备份未启用，跳过检查");
+    // 处理定时备份事件
+    async handleScheduleEvent(event: ScheduleEvent) {
+      console.log(`定时备份事件: ${event.kind}`, event);
+      // 备份作业的事件只提示结果，不占用主界面的进度显示
+      if (event.saved_job !== null) {
+        this.handleSavedJobEvent(event);
         return;
       }
-
-      // 检查数据库是否已连接
-      if (!this.database.isConnected) {
-        console.log("数据库未连接，跳过自动备份");
-        return;
+      if (event.next_run !== null || event.kind === "scheduled") {
+        this.setNextBackupTime(event.next_run);
       }
 
-      // 检查是否正在进行备份
-      if (this.backup.isBackingUp) {
-        console.log("备份正在进行中，跳过自动备份检查");
-        return;
+      switch (event.kind) {
+        case "started":
+          // 手动备份进行中时不占用进度显示，只在结束时提示
+          if (event.job_id === null || this.backup.isBackingUp) return;
+          this.backup.isBackingUp = true;
+          this.backup.jobId = event.job_id;
+          this.backup.backupProgress = 0;
+          this.backup.backupStatus = "自动备份进行中...";
+          this.scheduledJobUnlisten = await listenBackupProgress(
+            event.job_id,
+            (percent, status, currentTable) => {
+              this.backup.backupProgress = percent;
+              this.backup.backupStatus = status;
+              this.backup.currentTableName = currentTable;
+            }
+          );
+          break;
+        case "succeeded":
+          this.finishScheduledJob(event.job_id, "备份完成");
+          this.backup.lastBackupTime = new Date().toLocaleString();
+          await saveSetting("lastBackupTime", this.backup.lastBackupTime);
+          this.showSnackbar(
+            `自动备份成功，已保存到:\n${event.message}`,
+            "success"
+          );
+          sendNotification({
+            title: "MySQL备份完成",
+            body: `数据库 ${this.database.database} 自动备份已完成！\n文件已保存到: ${event.message}`,
+            icon: "icon.png",
+          });
+          break;
+        case "failed":
+          this.finishScheduledJob(
+            event.job_id,
+            event.message.includes(BACKUP_CANCELLED)
+              ? BACKUP_CANCELLED
+              : "备份出错"
+          );
+          if (event.message.includes(BACKUP_CANCELLED)) return;
+          this.showSnackbar(`自动备份失败: ${event.message}`, "error");
+          try {
+            await sendNotification({
+              title: "MySQL备份失败",
+              body: `数据库 ${this.database.database} 自动备份失败: ${event.message}`,
+              icon: "icon.png",
+            });
+          } catch (notifyError) {
+            console.error("发送失败通知出错:", notifyError);
+          }
+          break;
+        case "skipped":
+        case "missed":
+          this.showSnackbar(event.message, "warning");
+          break;
       }
+    },
 
-      // 检查是否设置了备份路径
-      if (!this.backup.path) {
-        console.log("未设置备份路径，跳过自动备份");
-        return;
+    // 处理备份作业的定时备份事件
+    handleSavedJobEvent(event: ScheduleEvent) {
+      switch (event.kind) {
+        case "succeeded":
+          this.showSnackbar(
+            `备份作业完成，已保存到:\n${event.message}`,
+            "success"
+          );
+          break;
+        case "failed":
+          if (!event.message.includes(BACKUP_CANCELLED)) {
+            this.showSnackbar(`备份作业失败: ${event.message}`, "error");
+            sendNotification({
+              title: "MySQL备份失败",
+              body: `备份作业失败: ${event.message}`,
+              icon: "icon.png",
+            });
+          }
+          break;
+        case "skipped":
+        case "missed":
+          this.showSnackbar(event.message, "warning");
+          break;
       }
+    },
 
-      try {
-        // 获取当前时间
-        const now = new Date();
-
-        // 如果没有上次备份时间，直接执行备份
-        if (!this.backup.lastBackupTime) {
-          console.log("没有上次备份记录，执行首次自动备份");
-          await this.startBackup();
-          return;
-        }
-
-        // 解析上次备份时间
-        const lastBackupTime = new Date(this.backup.lastBackupTime);
-
-        // 根据备份频率确定是否需要备份
-        let shouldBackup = false;
-
-        switch (this.backup.frequency) {
-          case "daily":
-            // 检查是否已过去24小时
-            shouldBackup =
-              now.getTime() - lastBackupTime.getTime() >= 24 * 60 * 60 * 1000;
-            break;
-          case "weekly":
-            // 检查是否已过去7天
-            shouldBackup =
-              now.getTime() - lastBackupTime.getTime() >=
-              7 * 24 * 60 * 60 * 1000;
-            break;
-          case "monthly":
-            // 检查是否已过去30天
-            shouldBackup =
-              now.getTime() - lastBackupTime.getTime() >=
-              30 * 24 * 60 * 60 * 1000;
-            break;
-          default:
-            console.log(
-              `未知的备份频率: ${this.backup.frequency}，使用每日备份`
-            );
-            shouldBackup =
-              now.getTime() - lastBackupTime.getTime() >= 24 * 60 * 60 * 1000;
-        }
-
-        if (shouldBackup) {
-          console.log(`已达到备份频率 ${this.backup.frequency}，开始自动备份`);
-          await this.startBackup();
-        } else {
-          console.log("未达到备份频率，跳过自动备份");
-        }
-      } catch (error) {
-        console.error("自动备份检查出错:", error);
-      }
+    // 定时备份任务结束后恢复备份状态，只处理由定时备份占用的进度显示
+    finishScheduledJob(jobId: number | null, status: string) {
+      if (!this.scheduledJobUnlisten || jobId !== this.backup.jobId) return;
+      this.scheduledJobUnlisten();
+      this.scheduledJobUnlisten = null;
+      this.backup.isBackingUp = false;
+      this.backup.jobId = null;
+      this.backup.backupProgress = 0;
+      this.backup.backupStatus = status;
+      this.backup.currentTableName = undefined;
     },
 
     // 启动进度动画（使用实时进度事件）
@@ -503,21 +646,128 @@ export const usePiniaStore = defineStore("main", {
       try {
         await saveSetting("backup.path", this.backup.path);
         await saveSetting("backup.auto", this.backup.auto);
-        await saveSetting("backup.frequency", this.backup.frequency);
+        await saveSetting("backup.cron", this.backup.cron);
+        await saveSetting("backup.timezone", this.backup.timezone);
+        await saveSetting("backup.jitterMinutes", this.backup.jitterMinutes);
+        await saveSetting("backup.catchUp", this.backup.catchUp);
         await saveSetting("backup.keepDays", this.backup.keepDays);
         await saveSetting("backup.engine", this.backup.backupEngine);
+        await saveSetting("backup.archiveFormat", this.backup.archiveFormat);
+        await saveSetting("backup.compression", this.backup.compression);
+        await saveSetting(
+          "backup.compressionLevel",
+          this.backup.compressionLevel
+        );
+        await saveSetting("backup.encryptionMode", this.backup.encryptionMode);
+        await saveSetting(
+          "backup.encryptionPassphrase",
+          this.backup.encryptionPassphrase
+        );
+        await saveSetting(
+          "backup.encryptionRecipients",
+          this.backup.encryptionRecipients
+        );
+        await saveSetting("backup.resumable", this.backup.resumable);
+        await saveSetting("backup.workers", this.backup.workers);
+        await saveSetting("backup.chunkRows", this.backup.chunkRows);
+        await saveSetting(
+          "backup.recordChecksums",
+          this.backup.recordChecksums
+        );
+        await saveSetting("backup.includeTables", this.backup.includeTables);
+        await saveSetting("backup.excludeTables", this.backup.excludeTables);
+        await saveSetting("backup.whereClauses", this.backup.whereClauses);
+        await saveSetting("backup.mode", this.backup.mode);
+        await saveSetting(
+          "backup.recordBinlogPosition",
+          this.backup.recordBinlogPosition
+        );
+        await saveSetting(
+          "backup.writeChangeSource",
+          this.backup.writeChangeSource
+        );
+        await saveSetting(
+          "backup.maxConcurrentJobs",
+          this.backup.maxConcurrentJobs
+        );
+        await saveSetting(
+          "backup.maxJobsPerServer",
+          this.backup.maxJobsPerServer
+        );
+        await this.applyBackupJobLimits();
+        await this.syncBackupSchedule();
       } catch (error) {
         console.error("保存备份设置失败:", error);
         this.showSnackbar("保存备份设置失败", "error");
       }
     },
 
+    // 把同时运行的备份任务数限制同步到后端
+    async applyBackupJobLimits() {
+      try {
+        await setBackupJobLimits({
+          max_total: this.backup.maxConcurrentJobs,
+          max_per_server: this.backup.maxJobsPerServer,
+        });
+      } catch (error) {
+        console.error("设置备份任务数限制失败:", error);
+      }
+    },
+
     // 生成唯一的备份文件名
     generateBackupFileName(): string {
       const now = new Date();
       // 使用 useDateFormat 格式化日期为 YYYYMMDDHHmm 格式
       const timestamp = useDateFormat(now, "YYYYMMDDHHmm").value;
-      return `BACKUP_${timestamp}.zip`;
+      const extension = archiveExtensions[this.backup.archiveFormat];
+      const suffix = this.getBackupEncryption() ? encryptedSuffix : "";
+      return `BACKUP_${timestamp}${extension}${suffix}`;
+    },
+
+    // 根据加密设置生成加密选项，不加密时返回undefined
+    getBackupEncryption(): EncryptionOptions | undefined {
+      if (this.backup.encryptionMode === "passphrase") {
+        return this.backup.encryptionPassphrase
+          ? { passphrase: this.backup.encryptionPassphrase }
+          : undefined;
+      }
+      if (this.backup.encryptionMode === "recipients") {
+        const recipients = this.backup.encryptionRecipients
+          .split("\n")
+          .map((line) => line.trim())
+          .filter((line) => line && !line.startsWith("#"));
+        return recipients.length > 0 ? { recipients } : undefined;
+      }
+      return undefined;
+    },
+
+    // 根据表过滤设置生成过滤条件，没有设置时返回undefined
+    getTableFilter(): TableFilter | undefined {
+      const lines = (text: string) =>
+        text
+          .split("\n")
+          .map((line) => line.trim())
+          .filter((line) => line && !line.startsWith("#"));
+
+      const include = lines(this.backup.includeTables);
+      const exclude = lines(this.backup.excludeTables);
+      const where_clauses: Record<string, string> = {};
+      for (const line of lines(this.backup.whereClauses)) {
+        const separator = line.indexOf(":");
+        if (separator > 0) {
+          const table = line.slice(0, separator).trim();
+          where_clauses[table] = line.slice(separator + 1).trim();
+        }
+      }
+
+      if (
+        include.length === 0 &&
+        exclude.length === 0 &&
+        Object.keys(where_clauses).length === 0
+      ) {
+        return undefined;
+      }
+      return { include, exclude, where_clauses };
     },
 
     // 生成完整的备份文件路径
@@ -564,6 +814,8 @@ export const usePiniaStore = defineStore("main", {
         return;
       }
 
+      const resume = await this.shouldResumeBackup();
+
       try {
         // 重置状态
         this.backup.isBackingUp = true;
@@ -571,6 +823,7 @@ export const usePiniaStore = defineStore("main", {
         this.backup.backupStatus = "正在准备备份...";
 
         // 生成备份文件路径
+        const encryption = this.getBackupEncryption();
         const backupFilePath = this.getBackupFilePath();
         console.log(`备份文件将保存到: ${backupFilePath}`);
 
@@ -611,9 +864,43 @@ export const usePiniaStore = defineStore("main", {
             this.database.database,
             backupFilePath,
             progressCallback, // 传递进度回调函数
-            this.backup.backupEngine // 传递备份引擎设置
+            this.backup.backupEngine, // 传递备份引擎设置
+            {
+              format: this.backup.archiveFormat,
+              compression: this.backup.compression,
+              compression_level: this.backup.compressionLevel,
+              encryption,
+              resumable: this.backup.resumable,
+              resume,
+              workers: this.backup.workers,
+              chunk_rows: this.backup.chunkRows,
+              record_checksums: this.backup.recordChecksums,
+              table_filter: this.getTableFilter(),
+              mode: this.backup.mode,
+              record_binlog_position: this.backup.recordBinlogPosition,
+              write_change_source: this.backup.writeChangeSource,
+            },
+            (jobId) => {
+              this.backup.jobId = jobId;
+            }
           );
 
+          // 校验新生成的备份，校验失败时不清理旧备份
+          // 使用公钥加密的备份需要私钥才能解密，无法在备份后校验
+          if (encryption?.recipients) {
+            console.log("备份使用公钥加密，跳过备份后校验");
+          } else {
+            this.backup.backupStatus = "正在校验备份...";
+            this.backup.currentTableName = undefined;
+            const report = await verifyBackup(
+              backupFilePath,
+              encryption ? { passphrase: encryption.passphrase } : undefined
+            );
+            if (!report.ok) {
+              throw new Error(`备份校验失败: ${report.errors.join("; ")}`);
+            }
+          }
+
           // 备份完成
           this.updateBackupProgress(100);
           this.backup.backupStatus = "备份完成";
@@ -652,8 +939,16 @@ export const usePiniaStore = defineStore("main", {
           throw new Error(`MySQL备份失败: ${error}`);
         }
       } catch (error) {
-        this.backup.backupStatus = "备份出错";
         this.backup.backupProgress = 0;
+
+        // 用户主动取消时不作为失败处理
+        if (String(error).includes(BACKUP_CANCELLED)) {
+          this.backup.backupStatus = BACKUP_CANCELLED;
+          this.showSnackbar(BACKUP_CANCELLED, "info");
+          return;
+        }
+
+        this.backup.backupStatus = "备份出错";
         this.showSnackbar(`备份错误: ${error}`, "error");
 
         // 备份失败也发送通知
@@ -670,6 +965,51 @@ export const usePiniaStore = defineStore("main", {
         // 确保停止进度动画
         this.stopProgressAnimation();
         this.backup.isBackingUp = false;
+        this.backup.jobId = null;
+      }
+    },
+
+    // 可续传备份存在未完成的备份时询问是否继续
+    async shouldResumeBackup(): Promise<boolean> {
+      if (!this.backup.resumable || this.backup.backupEngine !== "builtin") {
+        return false;
+      }
+
+      try {
+        const pending = await findResumableBackup(
+          this.backup.path,
+          this.database.host,
+          this.database.port,
+          this.database.database
+        );
+        if (!pending) return false;
+
+        const startedAt = new Date(pending.started_at).toLocaleString();
+        return await ask(
+          `发现开始于 ${startedAt} 的未完成备份，已完成 ${pending.done_items}/${pending.total_items} 个工作单元。\n\n继续备份会跳过已完成的部分，但各表数据将不属于同一个快照；重新开始会丢弃已完成的部分。`,
+          {
+            title: "继续未完成的备份",
+            kind: "warning",
+            okLabel: "继续备份",
+            cancelLabel: "重新开始",
+          }
+        );
+      } catch (error) {
+        console.error("检查未完成的备份失败:", error);
+        return false;
+      }
+    },
+
+    // 取消正在运行的备份
+    async cancelBackup() {
+      if (!this.backup.isBackingUp) return;
+
+      try {
+        this.backup.backupStatus = "正在取消备份...";
+        await cancelBackup(this.backup.jobId ?? undefined);
+      } catch (error) {
+        console.error("取消备份失败:", error);
+        this.showSnackbar(`取消备份失败: ${error}`, "error");
       }
     },
 
@@ -804,8 +1144,73 @@ export const usePiniaStore = defineStore("main", {
         // 加载备份设置
         this.backup.path = await getSetting("backup.path", "");
         this.backup.auto = await getSetting("backup.auto", false);
-        this.backup.frequency = await getSetting("backup.frequency", "daily");
+        // 旧版本只保存了备份频率，转换为对应的cron表达式
+        const legacyFrequency = await getSetting("backup.frequency", "daily");
+        this.backup.cron = await getSetting(
+          "backup.cron",
+          legacyFrequencies[legacyFrequency] ?? legacyFrequencies.daily
+        );
+        this.backup.timezone = await getSetting("backup.timezone", "");
+        this.backup.jitterMinutes = await getSetting("backup.jitterMinutes", 0);
+        this.backup.catchUp = await getSetting("backup.catchUp", true);
         this.backup.keepDays = await getSetting("backup.keepDays", 180);
+        this.backup.archiveFormat = await getSetting<ArchiveFormat>(
+          "backup.archiveFormat",
+          "zip"
+        );
+        this.backup.compression = await getSetting<CompressionCodec>(
+          "backup.compression",
+          "deflate"
+        );
+        this.backup.compressionLevel = await getSetting<number | null>(
+          "backup.compressionLevel",
+          null
+        );
+        this.backup.encryptionMode = await getSetting<
+          "none" | "passphrase" | "recipients"
+        >("backup.encryptionMode", "none");
+        this.backup.encryptionPassphrase = await getSetting(
+          "backup.encryptionPassphrase",
+          ""
+        );
+        this.backup.encryptionRecipients = await getSetting(
+          "backup.encryptionRecipients",
+          ""
+        );
+        this.backup.resumable = await getSetting("backup.resumable", false);
+        this.backup.workers = await getSetting("backup.workers", 1);
+        this.backup.chunkRows = await getSetting("backup.chunkRows", 0);
+        this.backup.recordChecksums = await getSetting(
+          "backup.recordChecksums",
+          false
+        );
+        this.backup.includeTables = await getSetting(
+          "backup.includeTables",
+          ""
+        );
+        this.backup.excludeTables = await getSetting(
+          "backup.excludeTables",
+          ""
+        );
+        this.backup.whereClauses = await getSetting("backup.whereClauses", "");
+        this.backup.mode = await getSetting<BackupMode>("backup.mode", "full");
+        this.backup.recordBinlogPosition = await getSetting(
+          "backup.recordBinlogPosition",
+          false
+        );
+        this.backup.writeChangeSource = await getSetting(
+          "backup.writeChangeSource",
+          false
+        );
+        this.backup.maxConcurrentJobs = await getSetting(
+          "backup.maxConcurrentJobs",
+          2
+        );
+        this.backup.maxJobsPerServer = await getSetting(
+          "backup.maxJobsPerServer",
+          1
+        );
+        await this.applyBackupJobLimits();
 
         // 检查mysqldump可用性
         await this.checkMysqldumpAvailability();
@@ -835,8 +1240,9 @@ export const usePiniaStore = defineStore("main", {
         // 启动数据库连接状态监控
         this.startConnectionMonitor();
 
-        // 启动自动备份监控
-        this.startBackupMonitor();
+        // 监听定时备份事件，并把自动备份设置同步到后端调度器
+        await this.startScheduleListener();
+        await this.syncBackupSchedule();
       } catch (error) {
         console.error("加载设置失败:", error);
         this.showSnackbar("加载设置失败", "error");
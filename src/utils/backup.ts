@@ -5,12 +5,148 @@ import { listen } from "@tauri-apps/api/event";
 export type ProgressCallback = (
   percent: number,
   status: string,
-  currentTable?: string
+  currentTable?: string,
+  worker?: number
 ) => void;
 
+// 备份选项，与Rust端BackupOptions对应，未设置的字段使用默认值
+export interface BackupOptions {
+  // 在全局读锁期间备份非事务表（如MyISAM），使其与快照一致
+  lock_non_transactional?: boolean;
+  // 内置引擎并行备份的工作线程数，0或1表示串行备份，最多16个
+  workers?: number;
+  // 大表按唯一键切分的每块行数，0表示不分块
+  chunk_rows?: number;
+  // 在快照内记录每张表的CHECKSUM TABLE结果，供深度校验比较（仅内置引擎）
+  record_checksums?: boolean;
+  // 备份文件格式，默认zip
+  format?: ArchiveFormat;
+  // ZIP条目的压缩方式，默认deflate，其他格式固定使用格式本身的压缩算法
+  compression?: CompressionCodec;
+  // 压缩级别，为空时使用算法的默认级别；deflate、bzip2和gzip为0-9，zstd为-7到22
+  compression_level?: number | null;
+  // 加密选项，不设置时不加密
+  encryption?: EncryptionOptions;
+  // 内置引擎的可续传备份，中断后保留已完成的表，下次备份同一数据库时可以继续
+  resumable?: boolean;
+  // 存在未完成的可续传备份时继续，否则丢弃重新开始
+  resume?: boolean;
+  // 备份多个数据库或整个服务器
+  server?: ServerBackupOptions;
+  // 按表名选择要备份的表，以及按表设置的WHERE条件
+  table_filter?: TableFilter;
+  // 完整备份、只备份表结构或只备份数据，默认full
+  mode?: BackupMode;
+  // 记录快照对应的binlog位置和GTID集合，需要RELOAD和REPLICATION CLIENT权限
+  record_binlog_position?: boolean;
+  // 在备份中写入注释掉的CHANGE REPLICATION SOURCE TO语句（仅内置引擎）
+  write_change_source?: boolean;
+}
+
+// 备份包含的内容，与Rust端BackupMode对应
+export type BackupMode = "full" | "schema_only" | "data_only";
+
+// 表过滤条件，与Rust端TableFilter对应。模式默认为通配符（*和?），
+// 以re:开头时为正则表达式；多数据库备份中表名可以写成“数据库.表”
+export interface TableFilter {
+  // 只备份匹配任一模式的表，为空时备份所有表
+  include?: string[];
+  // 不备份匹配任一模式的表，优先于include
+  exclude?: string[];
+  // 表名到WHERE条件的映射，只备份满足条件的行（仅内置引擎）
+  where_clauses?: Record<string, string>;
+}
+
+// 多数据库备份选项，与Rust端ServerBackupOptions对应
+export interface ServerBackupOptions {
+  databases?: string[];
+  // 备份除系统数据库外的所有数据库
+  all_databases?: boolean;
+  // 每个数据库写入单独的归档，保存在以备份文件名命名的目录中
+  separate_archives?: boolean;
+  // 备份用户、角色和授权
+  include_users?: boolean;
+}
+
+// 未完成的可续传备份，与Rust端ResumableBackup对应
+export interface ResumableBackup {
+  started_at: string;
+  // 每次继续备份的时间
+  resumed_at: string[];
+  done_items: number;
+  total_items: number;
+}
+
+// 备份加密选项，密码和公钥只能选择一种，加密后的文件名追加.age
+export interface EncryptionOptions {
+  // 用于加密的密码
+  passphrase?: string;
+  // age格式的X25519公钥（age1...）
+  recipients?: string[];
+}
+
+// 恢复和校验加密备份时使用的密钥
+export interface DecryptionKey {
+  // 备份时使用的密码
+  passphrase?: string;
+  // age格式的X25519私钥（AGE-SECRET-KEY-1...）或私钥文件内容
+  identity?: string;
+}
+
+// 加密备份文件名追加的后缀
+export const encryptedSuffix = ".age";
+
+// 备份文件格式
+export type ArchiveFormat = "zip" | "sql_gz" | "sql_zst" | "tar_zst";
+
+// ZIP条目的压缩方式
+export type CompressionCodec = "deflate" | "zstd" | "bzip2" | "store";
+
+// 各备份格式对应的文件扩展名
+export const archiveExtensions: Record<ArchiveFormat, string> = {
+  zip: ".zip",
+  sql_gz: ".sql.gz",
+  sql_zst: ".sql.zst",
+  tar_zst: ".tar.zst",
+};
+
+// 备份任务状态
+export type BackupJobStatus =
+  | "queued"
+  | "running"
+  | "succeeded"
+  | "failed"
+  | "cancelled";
+
+// 备份任务，与Rust端BackupJob对应
+export interface BackupJob {
+  id: number;
+  host: string;
+  port: number;
+  database: string;
+  output_path: string;
+  engine: string | null;
+  status: BackupJobStatus;
+  created_at: string;
+  started_at: string | null;
+  finished_at: string | null;
+  // 成功时为备份文件路径
+  result: string | null;
+  error: string | null;
+}
+
+// 同时运行的备份任务数限制，0表示不限制
+export interface BackupJobLimits {
+  // 所有服务器合计
+  max_total: number;
+  // 同一服务器（主机和端口相同）
+  max_per_server: number;
+}
+
 /**
  * 执行MySQL数据库备份
  * 使用内置的备份功能或系统中的mysqldump（如果可用）
+ * 备份作为后台任务执行，超出同时运行的任务数限制时先排队
  * @param host 数据库主机地址
  * @param port 数据库端口
  * @param username 数据库用户名
@@ -19,6 +155,8 @@ export type ProgressCallback = (
  * @param outputPath 备份文件输出路径
  * @param progressCallback 进度更新回调函数
  * @param engine 备份引擎类型，'mysqldump'或'builtin'
+ * @param options 其他备份选项
+ * @param onJobStarted 任务提交后回调，参数为任务编号，可用于取消任务
  * @returns 成功时返回备份文件路径，失败时抛出错误
  */
 export async function backupMysqlDatabase(
@@ -29,7 +167,9 @@ export async function backupMysqlDatabase(
   database: string,
   outputPath: string,
   progressCallback?: ProgressCallback,
-  engine?: string
+  engine?: string,
+  options?: BackupOptions,
+  onJobStarted?: (jobId: number) => void
 ): Promise<string> {
   try {
     console.log(`开始备份MySQL数据库: ${database}`);
@@ -39,29 +179,34 @@ export async function backupMysqlDatabase(
       }`
     );
 
-    // 注册进度更新事件监听器
+    // 注册进度更新事件监听器，只处理本任务的进度
     let unlisten: (() => void) | null = null;
+    let jobId: number | null = null;
 
     if (progressCallback) {
       unlisten = await listen("backup-progress", (event) => {
         const payload = event.payload as {
+          job_id: number;
           percent: number;
           status: string;
           current_table?: string;
+          worker?: number;
         };
+        if (payload.job_id !== jobId) return;
 
         // 调用回调函数更新进度
         progressCallback(
           payload.percent,
           payload.status,
-          payload.current_table
+          payload.current_table,
+          payload.worker
         );
       });
     }
 
     try {
-      // 调用Rust函数执行备份（在子进程中异步执行）
-      const result = await invoke<string>("backup_mysql", {
+      // 提交备份任务（在后台线程中执行），再等待任务结束
+      jobId = await invoke<number>("start_backup", {
         host,
         port,
         username,
@@ -69,7 +214,10 @@ export async function backupMysqlDatabase(
         database,
         outputPath,
         engine, // 传递备份引擎参数
+        options,
       });
+      onJobStarted?.(jobId);
+      const result = await invoke<string>("wait_backup_job", { jobId });
 
       console.log(`备份成功: ${result}`);
       return result;
@@ -85,6 +233,311 @@ export async function backupMysqlDatabase(
   }
 }
 
+/**
+ * 取消备份任务，backupMysqlDatabase会以"备份已取消"错误结束
+ * @param jobId 任务编号，不指定时取消所有未结束的任务
+ * @returns 有任务被取消时返回true
+ */
+export async function cancelBackup(jobId?: number): Promise<boolean> {
+  return await invoke<boolean>("cancel_backup", { jobId });
+}
+
+/**
+ * 列出正在运行、排队和最近结束的备份任务
+ * @returns 按任务编号排序的任务列表
+ */
+export async function listBackupJobs(): Promise<BackupJob[]> {
+  return await invoke<BackupJob[]>("list_backup_jobs");
+}
+
+/**
+ * 查询备份任务
+ * @param jobId 任务编号
+ * @returns 任务信息，任务不存在时抛出错误
+ */
+export async function getBackupJob(jobId: number): Promise<BackupJob> {
+  return await invoke<BackupJob>("get_backup_job", { jobId });
+}
+
+/**
+ * 等待备份任务结束
+ * @param jobId 任务编号
+ * @returns 成功时返回备份文件路径，失败或取消时抛出错误
+ */
+export async function waitBackupJob(jobId: number): Promise<string> {
+  return await invoke<string>("wait_backup_job", { jobId });
+}
+
+/**
+ * 读取同时运行的备份任务数限制
+ */
+export async function getBackupJobLimits(): Promise<BackupJobLimits> {
+  return await invoke<BackupJobLimits>("get_backup_job_limits");
+}
+
+/**
+ * 设置同时运行的备份任务数限制，排队的任务按新限制开始
+ * @param limits 任务数限制，0表示不限制
+ */
+export async function setBackupJobLimits(
+  limits: BackupJobLimits
+): Promise<void> {
+  await invoke("set_backup_job_limits", { limits });
+}
+
+// 定时备份的触发时间设置，与Rust端ScheduleTiming对应
+export interface ScheduleTiming {
+  enabled: boolean;
+  // cron表达式：5段为标准格式（分 时 日 月 周），6或7段为带秒（和年）的格式
+  cron: string;
+  // IANA时区名（如Asia/Shanghai），为空时使用系统时区
+  timezone: string;
+  // 每次触发随机延后的最大秒数
+  jitter_seconds: number;
+  // 休眠或关机错过的备份在恢复后补做一次
+  catch_up: boolean;
+}
+
+// 定时备份设置，与Rust端ScheduleConfig对应
+export interface ScheduleConfig extends ScheduleTiming {
+  host: string;
+  port: number;
+  username: string;
+  password: string;
+  database: string;
+  backup_dir: string;
+  engine: string | null;
+  options: BackupOptions;
+  // 备份成功后清理超过保留天数的备份，0或负数表示不清理
+  keep_days: number;
+}
+
+// 定时备份状态
+export interface ScheduleStatus {
+  config: ScheduleConfig;
+  // 含随机延后的下次备份时间，RFC 3339格式
+  next_run: string | null;
+  last_run: string | null;
+  // 正在运行的定时备份任务编号
+  running_job: number | null;
+  // 各备份作业的定时备份状态
+  saved_jobs: SavedJobSchedule[];
+}
+
+// 备份作业的定时备份状态
+export interface SavedJobSchedule {
+  saved_job: number;
+  next_run: string | null;
+  last_run: string | null;
+  // 正在运行的备份任务，每个数据库一个
+  running_jobs: number[];
+}
+
+// 定时备份事件，通过backup-schedule事件发送
+export interface ScheduleEvent {
+  kind: "scheduled" | "started" | "skipped" | "missed" | "succeeded" | "failed";
+  // 备份作业编号，为空表示通过setBackupSchedule设置的定时备份
+  saved_job: number | null;
+  job_id: number | null;
+  next_run: string | null;
+  // 成功时为备份文件路径，失败时为错误信息
+  message: string;
+}
+
+/**
+ * 读取后端定时备份的设置和状态
+ */
+export async function getBackupSchedule(): Promise<ScheduleStatus> {
+  return await invoke<ScheduleStatus>("get_backup_schedule");
+}
+
+/**
+ * 设置定时备份，保存后立即按新设置计算下次备份时间
+ * @param config 定时备份设置
+ * @returns 新的定时备份状态，cron表达式或时区无效时抛出错误
+ */
+export async function setBackupSchedule(
+  config: ScheduleConfig
+): Promise<ScheduleStatus> {
+  return await invoke<ScheduleStatus>("set_backup_schedule", { config });
+}
+
+/**
+ * 预览cron表达式接下来的触发时间
+ * @param cron cron表达式
+ * @param timezone 时区名，为空时使用系统时区
+ * @param count 预览的次数，默认5次
+ * @returns RFC 3339格式的时间列表，表达式或时区无效时抛出错误
+ */
+export async function previewBackupSchedule(
+  cron: string,
+  timezone: string,
+  count?: number
+): Promise<string[]> {
+  return await invoke<string[]>("preview_backup_schedule", {
+    cron,
+    timezone,
+    count,
+  });
+}
+
+// 数据库服务器的连接配置，与Rust端ConnectionProfile对应
+export interface ConnectionProfile {
+  // 为0时表示新建
+  id: number;
+  name: string;
+  host: string;
+  port: number;
+  username: string;
+  password: string;
+}
+
+// 备份作业，与Rust端SavedJob对应
+export interface SavedJob {
+  // 为0时表示新建
+  id: number;
+  name: string;
+  profile_id: number;
+  databases: string[];
+  engine: string | null;
+  // 备份保存目录，每个数据库的备份放在以数据库名命名的子目录中
+  destination: string;
+  options: BackupOptions;
+  // 所有数据库作为一次多数据库备份保存在备份目录中
+  server_backup: boolean;
+  schedule: ScheduleTiming;
+  // 备份成功后清理超过保留天数的备份，0或负数表示不清理
+  keep_days: number;
+}
+
+/**
+ * 列出连接配置
+ */
+export async function listConnectionProfiles(): Promise<ConnectionProfile[]> {
+  return await invoke<ConnectionProfile[]>("list_connection_profiles");
+}
+
+/**
+ * 新建（id为0）或修改连接配置
+ * @returns 保存后的连接配置
+ */
+export async function saveConnectionProfile(
+  profile: ConnectionProfile
+): Promise<ConnectionProfile> {
+  return await invoke<ConnectionProfile>("save_connection_profile", {
+    profile,
+  });
+}
+
+/**
+ * 删除连接配置，仍被备份作业使用时抛出错误
+ */
+export async function deleteConnectionProfile(id: number): Promise<void> {
+  await invoke("delete_connection_profile", { id });
+}
+
+/**
+ * 列出备份作业
+ */
+export async function listSavedJobs(): Promise<SavedJob[]> {
+  return await invoke<SavedJob[]>("list_saved_jobs");
+}
+
+/**
+ * 新建备份作业
+ * @returns 分配了编号的备份作业，设置无效时抛出错误
+ */
+export async function createSavedJob(job: SavedJob): Promise<SavedJob> {
+  return await invoke<SavedJob>("create_saved_job", { job });
+}
+
+/**
+ * 修改备份作业，定时设置变化时从现在开始重新计算下次备份时间
+ * @returns 保存后的备份作业，设置无效时抛出错误
+ */
+export async function updateSavedJob(job: SavedJob): Promise<SavedJob> {
+  return await invoke<SavedJob>("update_saved_job", { job });
+}
+
+/**
+ * 删除备份作业
+ */
+export async function deleteSavedJob(id: number): Promise<void> {
+  await invoke("delete_saved_job", { id });
+}
+
+/**
+ * 立即运行备份作业，每个数据库提交一个备份任务
+ * 任务结束时通过backup-schedule事件通知
+ * @returns 各数据库的备份任务编号
+ */
+export async function runSavedJob(id: number): Promise<number[]> {
+  return await invoke<number[]>("run_saved_job", { id });
+}
+
+/**
+ * 监听定时备份事件
+ * @param callback 事件回调
+ * @returns 取消监听的函数
+ */
+export async function listenBackupSchedule(
+  callback: (event: ScheduleEvent) => void
+): Promise<() => void> {
+  return await listen<ScheduleEvent>("backup-schedule", (event) =>
+    callback(event.payload)
+  );
+}
+
+/**
+ * 监听指定备份任务的进度，用于跟踪不是由本窗口发起的任务（如定时备份）
+ * @param jobId 任务编号
+ * @param progressCallback 进度更新回调函数
+ * @returns 取消监听的函数
+ */
+export async function listenBackupProgress(
+  jobId: number,
+  progressCallback: ProgressCallback
+): Promise<() => void> {
+  return await listen("backup-progress", (event) => {
+    const payload = event.payload as {
+      job_id: number;
+      percent: number;
+      status: string;
+      current_table?: string;
+      worker?: number;
+    };
+    if (payload.job_id !== jobId) return;
+    progressCallback(
+      payload.percent,
+      payload.status,
+      payload.current_table,
+      payload.worker
+    );
+  });
+}
+
+/**
+ * 查找备份目录中同一数据库未完成的可续传备份
+ * @param backupDir 备份文件所在目录
+ * @param host 数据库主机地址
+ * @param port 数据库端口
+ * @param database 数据库名
+ * @returns 未完成的备份信息，没有时返回null
+ */
+export async function findResumableBackup(
+  backupDir: string,
+  host: string,
+  port: number,
+  database: string
+): Promise<ResumableBackup | null> {
+  return await invoke<ResumableBackup | null>("find_resumable_backup", {
+    backupDir,
+    host,
+    port,
+    database,
+  });
+}
+
 /**
  * 检查MySQL备份功能可用性
  * 由于我们实现了内置备份功能，此函数始终返回true
@@ -129,3 +582,379 @@ export async function cleanupOldBackups(
     throw error;
   }
 }
+
+// 恢复选项，与Rust端RestoreOptions对应，未设置的字段使用默认值
+export interface RestoreOptions {
+  // 恢复到的数据库名，不设置时使用备份中的数据库名
+  target_database?: string;
+  // 只恢复这些表，不设置或为空时恢复全部表以及存储过程、视图、触发器和事件
+  tables?: string[];
+  // 表已存在时的处理方式：删除重建、清空后导入、跳过已存在的表
+  conflict?: "drop_recreate" | "truncate_load" | "skip_existing";
+}
+
+/**
+ * 从备份文件恢复MySQL数据库
+ * 支持mysqldump和内置引擎生成的备份文件
+ * @param host 数据库主机地址
+ * @param port 数据库端口
+ * @param username 数据库用户名
+ * @param password 数据库密码
+ * @param archivePath 备份文件路径
+ * @param progressCallback 进度更新回调函数
+ * @param engine 执行引擎类型，'mysql'或'builtin'，不指定时优先使用mysql客户端
+ * @param options 目标数据库、表和冲突处理等恢复选项
+ * @param key 加密备份的解密密钥
+ * @returns 成功时返回备份文件路径，失败时抛出错误
+ */
+export async function restoreMysqlDatabase(
+  host: string,
+  port: number,
+  username: string,
+  password: string,
+  archivePath: string,
+  progressCallback?: ProgressCallback,
+  engine?: string,
+  options?: RestoreOptions,
+  key?: DecryptionKey
+): Promise<string> {
+  try {
+    console.log(`开始恢复MySQL数据库: ${archivePath}`);
+
+    // 注册进度更新事件监听器
+    let unlisten: (() => void) | null = null;
+
+    if (progressCallback) {
+      unlisten = await listen("restore-progress", (event) => {
+        const payload = event.payload as {
+          percent: number;
+          status: string;
+          current_table?: string;
+        };
+
+        progressCallback(payload.percent, payload.status, payload.current_table);
+      });
+    }
+
+    try {
+      const result = await invoke<string>("restore_mysql", {
+        host,
+        port,
+        username,
+        password,
+        archivePath,
+        engine,
+        options,
+        key,
+      });
+
+      console.log(`恢复成功: ${result}`);
+      return result;
+    } finally {
+      if (unlisten) {
+        unlisten();
+      }
+    }
+  } catch (error) {
+    console.error("恢复MySQL数据库失败:", error);
+    throw error;
+  }
+}
+
+/**
+ * 取消正在运行的恢复
+ * @returns 有恢复在运行时返回true
+ */
+export async function cancelRestore(): Promise<boolean> {
+  return await invoke<boolean>("cancel_restore");
+}
+
+// 备份快照对应的binlog位置
+export interface BinlogPosition {
+  file: string;
+  position: number;
+  // 快照时已执行的GTID集合，服务器未启用GTID时为空
+  gtid_executed: string;
+}
+
+// 按时间点恢复的终点，只能设置一种，重放到第一个满足条件的事务之前
+export interface PitrTarget {
+  // 本地时间，格式为YYYY-MM-DD HH:MM:SS
+  datetime?: string;
+  gtid?: string;
+  // 停在包含该位置的事务之前，不设置文件时使用备份位置所在的文件
+  file?: string;
+  position?: number;
+}
+
+// 按时间点恢复的选项，与Rust端PitrOptions对应
+export interface PitrOptions {
+  // 本地binlog文件所在的目录，不设置时通过复制协议从服务器读取
+  binlog_dir?: string;
+  target: PitrTarget;
+  // 恢复备份的选项，不能只恢复部分表
+  restore?: RestoreOptions;
+}
+
+// 按时间点恢复的预览：重放的范围以及终点之后被跳过的事务
+export interface PitrPreview {
+  start: BinlogPosition;
+  stop_file: string;
+  stop_position: number;
+  // 没有找到终点时，时间终点重放到现有binlog的末尾，GTID和位置终点不能恢复
+  stop_found: boolean;
+  applied_transactions: number;
+  skipped: {
+    file: string;
+    position: number;
+    timestamp: string | null;
+    gtid: string | null;
+    statements: string[];
+  }[];
+  // 被跳过的事务没有全部列出
+  more_skipped: boolean;
+}
+
+/**
+ * 预览按时间点恢复，不修改数据库
+ * @param host 数据库主机地址
+ * @param port 数据库端口
+ * @param username 数据库用户名
+ * @param password 数据库密码
+ * @param archivePath 备份文件路径，备份需要记录binlog位置
+ * @param options binlog来源、重放终点和恢复选项
+ * @param key 加密备份的解密密钥
+ * @returns 重放的范围以及将被跳过的事务
+ */
+export async function previewPointInTimeRecovery(
+  host: string,
+  port: number,
+  username: string,
+  password: string,
+  archivePath: string,
+  options: PitrOptions,
+  key?: DecryptionKey
+): Promise<PitrPreview> {
+  try {
+    return await invoke<PitrPreview>("preview_point_in_time_recovery", {
+      host,
+      port,
+      username,
+      password,
+      archivePath,
+      options,
+      key,
+    });
+  } catch (error) {
+    console.error("预览按时间点恢复失败:", error);
+    throw error;
+  }
+}
+
+/**
+ * 按时间点恢复：恢复备份后重放binlog到指定的终点之前
+ * @param host 数据库主机地址
+ * @param port 数据库端口
+ * @param username 数据库用户名
+ * @param password 数据库密码
+ * @param archivePath 备份文件路径，备份需要记录binlog位置
+ * @param options binlog来源、重放终点和恢复选项
+ * @param progressCallback 进度更新回调函数
+ * @param engine 执行引擎类型，'mysql'或'builtin'，不指定时优先使用mysql客户端
+ * @param key 加密备份的解密密钥
+ * @returns 成功时返回备份文件路径，失败时抛出错误
+ */
+export async function pointInTimeRecovery(
+  host: string,
+  port: number,
+  username: string,
+  password: string,
+  archivePath: string,
+  options: PitrOptions,
+  progressCallback?: ProgressCallback,
+  engine?: string,
+  key?: DecryptionKey
+): Promise<string> {
+  try {
+    console.log(`开始按时间点恢复MySQL数据库: ${archivePath}`);
+
+    // 注册进度更新事件监听器
+    let unlisten: (() => void) | null = null;
+
+    if (progressCallback) {
+      unlisten = await listen("restore-progress", (event) => {
+        const payload = event.payload as {
+          percent: number;
+          status: string;
+          current_table?: string;
+        };
+
+        progressCallback(payload.percent, payload.status, payload.current_table);
+      });
+    }
+
+    try {
+      const result = await invoke<string>("point_in_time_recovery", {
+        host,
+        port,
+        username,
+        password,
+        archivePath,
+        engine,
+        options,
+        key,
+      });
+
+      console.log(`按时间点恢复成功: ${result}`);
+      return result;
+    } finally {
+      if (unlisten) {
+        unlisten();
+      }
+    }
+  } catch (error) {
+    console.error("按时间点恢复MySQL数据库失败:", error);
+    throw error;
+  }
+}
+
+// 校验报告，与Rust端VerifyReport对应
+export interface VerifyReport {
+  archive_path: string;
+  // 没有任何错误时为true
+  ok: boolean;
+  has_manifest: boolean;
+  entries: {
+    name: string;
+    size: number;
+    crc_ok: boolean;
+    sha256: string;
+    sha256_ok: boolean | null;
+    statements: number;
+    complete: boolean;
+  }[];
+  tables: {
+    name: string;
+    has_structure: boolean;
+    rows: number;
+    expected_rows: number | null;
+  }[];
+  errors: string[];
+  warnings: string[];
+}
+
+/**
+ * 校验备份文件，检查CRC、清单中的SHA-256和SQL语句的完整性，不会恢复数据
+ * @param archivePath 备份文件路径
+ * @param key 加密备份的解密密钥
+ * @returns 校验报告，ok为false时errors中包含具体问题
+ */
+export async function verifyBackup(
+  archivePath: string,
+  key?: DecryptionKey
+): Promise<VerifyReport> {
+  try {
+    console.log(`开始校验备份文件: ${archivePath}`);
+    const report = await invoke<VerifyReport>("verify_backup", {
+      archivePath,
+      key,
+    });
+    if (report.ok) {
+      console.log("备份校验通过");
+    } else {
+      console.error("备份校验失败:", report.errors);
+    }
+    return report;
+  } catch (error) {
+    console.error("校验备份文件失败:", error);
+    throw error;
+  }
+}
+
+// 深度校验报告，与Rust端DeepVerifyReport对应
+export interface DeepVerifyReport {
+  archive_path: string;
+  // 用于试恢复的临时数据库，校验结束后会被删除
+  scratch_database: string;
+  // 没有任何错误时为true
+  ok: boolean;
+  tables: {
+    name: string;
+    expected_rows: number | null;
+    restored_rows: number | null;
+    expected_checksum: number | null;
+    restored_checksum: number | null;
+    ok: boolean;
+  }[];
+  errors: string[];
+  warnings: string[];
+}
+
+/**
+ * 深度校验备份文件：恢复到临时数据库，比较每张表的行数和校验和后删除临时数据库
+ * 进度通过restore-progress事件报告，可以使用cancelRestore取消
+ * @param host 数据库主机地址
+ * @param port 数据库端口
+ * @param username 数据库用户名
+ * @param password 数据库密码
+ * @param archivePath 备份文件路径
+ * @param progressCallback 进度更新回调函数
+ * @param engine 执行引擎类型，'mysql'或'builtin'，不指定时优先使用mysql客户端
+ * @param key 加密备份的解密密钥
+ * @returns 深度校验报告
+ */
+export async function deepVerifyBackup(
+  host: string,
+  port: number,
+  username: string,
+  password: string,
+  archivePath: string,
+  progressCallback?: ProgressCallback,
+  engine?: string,
+  key?: DecryptionKey
+): Promise<DeepVerifyReport> {
+  try {
+    console.log(`开始深度校验备份文件: ${archivePath}`);
+
+    // 注册进度更新事件监听器
+    let unlisten: (() => void) | null = null;
+
+    if (progressCallback) {
+      unlisten = await listen("restore-progress", (event) => {
+        const payload = event.payload as {
+          percent: number;
+          status: string;
+          current_table?: string;
+        };
+
+        progressCallback(payload.percent, payload.status, payload.current_table);
+      });
+    }
+
+    try {
+      const report = await invoke<DeepVerifyReport>("deep_verify_backup", {
+        host,
+        port,
+        username,
+        password,
+        archivePath,
+        engine,
+        key,
+      });
+
+      if (report.ok) {
+        console.log("深度校验通过");
+      } else {
+        console.error("深度校验失败:", report.errors);
+      }
+      return report;
+    } finally {
+      if (unlisten) {
+        unlisten();
+      }
+    }
+  } catch (error) {
+    console.error("深度校验备份文件失败:", error);
+    throw error;
+  }
+}